@@ -0,0 +1,68 @@
+//! Benchmarks for building and querying large graphs.
+
+#[macro_use]
+extern crate criterion;
+
+use cincinnati::{ConcreteRelease, Graph, Release};
+use criterion::{black_box, BenchmarkId, Criterion};
+use semver::Version;
+use std::collections::HashMap;
+
+/// Builds a chain of `n` releases, looking up every edge end by version.
+fn build_graph(n: u64) -> Graph {
+    let mut graph = Graph::default();
+
+    for i in 0..n {
+        graph
+            .add_release(Release::Concrete(ConcreteRelease {
+                version: Version::new(i, 0, 0),
+                payload: format!("image:{}.0.0", i),
+                metadata: HashMap::new(),
+            }))
+            .expect("release to be added");
+    }
+    for i in 1..n {
+        let from = graph
+            .find_by_version(&format!("{}.0.0", i - 1))
+            .expect("release to exist");
+        let to = graph
+            .find_by_version(&format!("{}.0.0", i))
+            .expect("release to exist");
+        graph.add_edge(&from, &to).expect("edge to be added");
+    }
+
+    graph
+}
+
+fn bench_build(c: &mut Criterion) {
+    let mut group = c.benchmark_group("build_graph");
+    group.sample_size(10);
+    for n in &[1_000, 10_000, 50_000] {
+        group.bench_with_input(BenchmarkId::from_parameter(n), n, |b, n| {
+            b.iter(|| build_graph(black_box(*n)))
+        });
+    }
+    group.finish();
+}
+
+fn bench_find_by_version(c: &mut Criterion) {
+    let graph = build_graph(50_000);
+    c.bench_function("find_by_version", |b| {
+        b.iter(|| graph.find_by_version(black_box("25000.0.0")))
+    });
+}
+
+fn bench_iter_releases_mut(c: &mut Criterion) {
+    let mut graph = build_graph(50_000);
+    c.bench_function("iter_releases_mut", |b| {
+        b.iter(|| graph.iter_releases_mut(|_| Ok(())))
+    });
+}
+
+criterion_group!(
+    benches,
+    bench_build,
+    bench_find_by_version,
+    bench_iter_releases_mut
+);
+criterion_main!(benches);
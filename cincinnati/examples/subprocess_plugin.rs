@@ -0,0 +1,42 @@
+//! Subprocess plugin, as used by the tests of `SubprocessPlugin`.
+//!
+//! It reads a `PluginExchange` from stdin and writes it unchanged to stdout.
+//! The `action` parameter makes it misbehave instead:
+//!  * `fail`: write a `PluginError` and exit with a non-zero status.
+//!  * `exit`: exit with a non-zero status without any output.
+//!  * `log`: log to stderr before succeeding.
+//!  * `sleep`: log to stderr and sleep for a minute before succeeding.
+
+use cincinnati::plugins::interface::{PluginError, PluginError_Kind, PluginExchange};
+use failure::Fallible;
+use protobuf::Message;
+use std::io::{Read, Write};
+
+fn main() -> Fallible<()> {
+    let mut input = Vec::new();
+    std::io::stdin().read_to_end(&mut input)?;
+    let exchange: PluginExchange = protobuf::parse_from_bytes(&input)?;
+
+    match exchange.get_parameters().get("action").map(String::as_str) {
+        Some("fail") => {
+            let mut error = PluginError::new();
+            error.set_kind(PluginError_Kind::INVALID_PARAM);
+            error.set_value("failed on purpose".to_string());
+            std::io::stdout().write_all(&error.write_to_bytes()?)?;
+            std::process::exit(1);
+        }
+        Some("exit") => std::process::exit(2),
+        Some("log") => eprintln!(
+            "processing {} nodes",
+            exchange.get_graph().get_nodes().len()
+        ),
+        Some("sleep") => {
+            eprintln!("sleeping for a minute");
+            std::thread::sleep(std::time::Duration::from_secs(60));
+        }
+        _ => {}
+    }
+
+    std::io::stdout().write_all(&exchange.write_to_bytes()?)?;
+    Ok(())
+}
@@ -0,0 +1,192 @@
+//! Fluent construction of graphs
+//!
+//! Releases are added by version and payload, and edges by the version
+//! strings of their ends. Versions which are only referenced by edges
+//! become abstract releases. All input is validated when the graph is built.
+
+use crate::{AbstractRelease, ConcreteRelease, Graph, Release};
+use failure::{Fallible, ResultExt};
+use semver::Version;
+use std::collections::HashMap;
+
+/// Builder for graphs, see the module documentation.
+#[derive(Clone, Debug, Default)]
+pub struct GraphBuilder {
+    releases: Vec<(String, String, HashMap<String, String>)>,
+    edges: Vec<(String, String)>,
+    require_resolved: bool,
+}
+
+impl GraphBuilder {
+    /// Creates a builder without any releases or edges.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds a concrete release with the given version, payload and metadata.
+    pub fn release<V, P>(
+        mut self,
+        version: V,
+        payload: P,
+        metadata: HashMap<String, String>,
+    ) -> Self
+    where
+        V: Into<String>,
+        P: Into<String>,
+    {
+        self.releases
+            .push((version.into(), payload.into(), metadata));
+        self
+    }
+
+    /// Adds an edge between the releases with the given versions.
+    pub fn edge<F, T>(mut self, from: F, to: T) -> Self
+    where
+        F: Into<String>,
+        T: Into<String>,
+    {
+        self.edges.push((from.into(), to.into()));
+        self
+    }
+
+    /// Whether edges must only refer to added releases.
+    ///
+    /// If set, `build` fails instead of adding abstract releases for unknown versions.
+    pub fn require_resolved(mut self, require_resolved: bool) -> Self {
+        self.require_resolved = require_resolved;
+        self
+    }
+
+    /// Builds the graph.
+    ///
+    /// Fails on invalid or duplicate versions, duplicate edges, edges which
+    /// would introduce a cycle, and, if required, edges to unknown versions.
+    pub fn build(self) -> Fallible<Graph> {
+        let mut graph = Graph::default();
+        let require_resolved = self.require_resolved;
+
+        for (version, payload, metadata) in self.releases {
+            let version = parse_version(&version)?;
+            graph.add_release(Release::Concrete(ConcreteRelease {
+                version,
+                payload,
+                metadata,
+            }))?;
+        }
+
+        for (from, to) in self.edges {
+            let mut resolve = |version: &str| -> Fallible<_> {
+                if let Some(id) = graph.find_by_version(version) {
+                    return Ok(id);
+                }
+                ensure!(
+                    !require_resolved,
+                    "edge {} -> {} refers to unknown release {}",
+                    from,
+                    to,
+                    version
+                );
+                graph.add_release(Release::Abstract(AbstractRelease {
+                    version: parse_version(version)?,
+                }))
+            };
+            let (from_id, to_id) = (resolve(&from)?, resolve(&to)?);
+            graph
+                .add_edge(&from_id, &to_id)
+                .context(format!("failed to add edge {} -> {}", from, to))?;
+        }
+
+        Ok(graph)
+    }
+}
+
+fn parse_version(version: &str) -> Fallible<Version> {
+    Ok(Version::parse(version).context(format!("invalid version '{}'", version))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::generate_graph;
+    use maplit::hashmap;
+
+    #[test]
+    fn build_graph() -> Fallible<()> {
+        let graph = GraphBuilder::new()
+            .release("1.0.0", "image/1.0.0", HashMap::new())
+            .release("2.0.0", "image/2.0.0", HashMap::new())
+            .release("3.0.0", "image/3.0.0", HashMap::new())
+            .edge("1.0.0", "2.0.0")
+            .edge("2.0.0", "3.0.0")
+            .edge("1.0.0", "3.0.0")
+            .build()?;
+        assert_eq!(graph, generate_graph());
+
+        Ok(())
+    }
+
+    #[test]
+    fn build_graph_with_abstract_releases() -> Fallible<()> {
+        let graph = GraphBuilder::new()
+            .release(
+                "1.0.0",
+                "image/1.0.0",
+                hashmap! { "key".to_string() => "value".to_string() },
+            )
+            .edge("1.0.0", "2.0.0+amd64")
+            .build()?;
+
+        let v1 = graph.find_by_version("1.0.0").expect("1.0.0 to exist");
+        let v2 = graph
+            .find_by_version("2.0.0+amd64")
+            .expect("2.0.0 to exist");
+        match graph.find_by_releaseid(&v1)? {
+            Release::Concrete(release) => {
+                assert_eq!(release.metadata.get("key"), Some(&"value".to_string()))
+            }
+            release => panic!("expected a concrete release, got {:?}", release),
+        }
+        match graph.find_by_releaseid(&v2)? {
+            Release::Abstract(_) => {}
+            release => panic!("expected an abstract release, got {:?}", release),
+        }
+        assert_eq!(graph.next_releases(&v1)?.count(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn build_rejects_invalid_input() {
+        let builder = GraphBuilder::new()
+            .release("1.0.0", "image/1.0.0", HashMap::new())
+            .release("2.0.0", "image/2.0.0", HashMap::new())
+            .edge("1.0.0", "2.0.0");
+
+        for (name, builder) in vec![
+            (
+                "invalid release version",
+                builder
+                    .clone()
+                    .release("a.b.c", "image/a.b.c", HashMap::new()),
+            ),
+            (
+                "duplicate release",
+                builder
+                    .clone()
+                    .release("1.0.0", "image/other", HashMap::new()),
+            ),
+            ("invalid edge version", builder.clone().edge("1.0.0", "3")),
+            ("duplicate edge", builder.clone().edge("1.0.0", "2.0.0")),
+            ("cycle", builder.clone().edge("2.0.0", "1.0.0")),
+            (
+                "unresolved edge",
+                builder
+                    .clone()
+                    .edge("2.0.0", "3.0.0")
+                    .require_resolved(true),
+            ),
+        ] {
+            assert!(builder.build().is_err(), "{}", name);
+        }
+    }
+}
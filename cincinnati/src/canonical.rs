@@ -0,0 +1,281 @@
+//! Canonical serialization of graphs
+//!
+//! The serialization of a `Graph` follows the order in which its releases
+//! and edges were added, so equal graphs may serialize differently.
+//! The canonical serialization has the same format, but only depends on the
+//! content of the graph, which makes it suitable for diffing and golden tests.
+
+use crate::{Edge, Graph, Release, Risk};
+use petgraph::visit::{EdgeRef, IntoEdgeReferences, IntoNodeReferences};
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+
+/// Wrapper which serializes a graph canonically.
+///
+/// Nodes are ordered by version, and releases which only differ in their
+/// build metadata by their full version string. Edges refer to the nodes
+/// by these positions and are ordered by them. Metadata is ordered by key,
+/// risks by name and their matching rules by parameter and values.
+pub struct Canonical<'a>(&'a Graph);
+
+impl Graph {
+    /// Returns a wrapper which serializes this graph canonically.
+    pub fn canonical(&self) -> Canonical {
+        Canonical(self)
+    }
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum CanonicalRelease<'a> {
+    Concrete {
+        version: String,
+        payload: &'a str,
+        metadata: BTreeMap<&'a str, &'a str>,
+    },
+    Abstract {
+        version: String,
+    },
+}
+
+#[derive(Serialize)]
+struct EdgeMetadata<'a> {
+    from: usize,
+    to: usize,
+    metadata: BTreeMap<&'a str, &'a str>,
+}
+
+#[derive(Serialize)]
+struct ConditionalEdge<'a> {
+    from: usize,
+    to: usize,
+    risks: Vec<CanonicalRisk<'a>>,
+}
+
+#[derive(Serialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "camelCase")]
+struct CanonicalRisk<'a> {
+    name: &'a str,
+    url: &'a str,
+    message: &'a str,
+    matching_rules: Vec<CanonicalMatchingRule<'a>>,
+}
+
+#[derive(Serialize, PartialEq, Eq, PartialOrd, Ord)]
+struct CanonicalMatchingRule<'a> {
+    parameter: &'a str,
+    values: Vec<&'a str>,
+}
+
+impl<'a> Serialize for Canonical<'a> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut releases: Vec<_> = self.0.dag.node_references().collect();
+        releases.sort_by(|(_, a), (_, b)| compare_releases(a, b));
+
+        let positions: HashMap<daggy::NodeIndex, usize> = releases
+            .iter()
+            .enumerate()
+            .map(|(position, (index, _))| (*index, position))
+            .collect();
+
+        let mut edges: Vec<(usize, usize, &Edge)> = self
+            .0
+            .dag
+            .edge_references()
+            .map(|edge| {
+                (
+                    positions[&edge.source()],
+                    positions[&edge.target()],
+                    edge.weight(),
+                )
+            })
+            .collect();
+        edges.sort_by_key(|(from, to, _)| (*from, *to));
+
+        let nodes: Vec<CanonicalRelease> = releases
+            .into_iter()
+            .map(|(_, release)| match release {
+                Release::Concrete(release) => CanonicalRelease::Concrete {
+                    version: release.version.to_string(),
+                    payload: &release.payload,
+                    metadata: sorted(&release.metadata),
+                },
+                Release::Abstract(release) => CanonicalRelease::Abstract {
+                    version: release.version.to_string(),
+                },
+            })
+            .collect();
+
+        let unconditional_edges: Vec<(usize, usize)> = edges
+            .iter()
+            .filter(|(_, _, edge)| !edge.is_conditional())
+            .map(|(from, to, _)| (*from, *to))
+            .collect();
+
+        let edges_metadata: Vec<EdgeMetadata> = edges
+            .iter()
+            .filter(|(_, _, edge)| !edge.metadata.is_empty())
+            .map(|(from, to, edge)| EdgeMetadata {
+                from: *from,
+                to: *to,
+                metadata: sorted(&edge.metadata),
+            })
+            .collect();
+
+        let conditional_edges: Vec<ConditionalEdge> = edges
+            .iter()
+            .filter(|(_, _, edge)| edge.is_conditional())
+            .map(|(from, to, edge)| ConditionalEdge {
+                from: *from,
+                to: *to,
+                risks: sorted_risks(&edge.risks),
+            })
+            .collect();
+
+        // The optional fields are omitted like in the serialization of `Graph`.
+        let mut state = serializer.serialize_struct(
+            "Graph",
+            2 + !edges_metadata.is_empty() as usize + !conditional_edges.is_empty() as usize,
+        )?;
+        state.serialize_field("nodes", &nodes)?;
+        state.serialize_field("edges", &unconditional_edges)?;
+        if !edges_metadata.is_empty() {
+            state.serialize_field("edgeMetadata", &edges_metadata)?;
+        }
+        if !conditional_edges.is_empty() {
+            state.serialize_field("conditionalEdges", &conditional_edges)?;
+        }
+        state.end()
+    }
+}
+
+/// Orders releases by version, and releases which only differ in their
+/// build metadata by their full version string.
+fn compare_releases(a: &Release, b: &Release) -> Ordering {
+    a.version()
+        .cmp(b.version())
+        .then_with(|| a.version().to_string().cmp(&b.version().to_string()))
+}
+
+fn sorted_risks(risks: &[Risk]) -> Vec<CanonicalRisk> {
+    let mut risks: Vec<CanonicalRisk> = risks
+        .iter()
+        .map(|risk| {
+            let mut matching_rules: Vec<CanonicalMatchingRule> = risk
+                .matching_rules
+                .iter()
+                .map(|rule| {
+                    let mut values: Vec<&str> = rule.values.iter().map(String::as_str).collect();
+                    values.sort();
+                    CanonicalMatchingRule {
+                        parameter: &rule.parameter,
+                        values,
+                    }
+                })
+                .collect();
+            matching_rules.sort();
+            CanonicalRisk {
+                name: &risk.name,
+                url: &risk.url,
+                message: &risk.message,
+                matching_rules,
+            }
+        })
+        .collect();
+    risks.sort();
+    risks
+}
+
+fn sorted(metadata: &HashMap<String, String>) -> BTreeMap<&str, &str> {
+    metadata
+        .iter()
+        .map(|(key, value)| (key.as_str(), value.as_str()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::generate_graph_in_order;
+    use failure::Fallible;
+
+    #[test]
+    fn canonical_serialization_ignores_insertion_order() -> Fallible<()> {
+        let graph = generate_graph_in_order(&["3.0.0", "2.0.0+arm64", "1.0.0", "2.0.0+amd64"])?;
+        let reordered = generate_graph_in_order(&["1.0.0", "2.0.0+amd64", "2.0.0+arm64", "3.0.0"])?;
+
+        let expected = concat!(
+            r#"{"nodes":["#,
+            r#"{"version":"1.0.0","payload":"image:1.0.0","metadata":{"a":"1.0.0","b":"value"}},"#,
+            r#"{"version":"2.0.0+amd64","payload":"image:2.0.0+amd64","metadata":{"a":"2.0.0+amd64","b":"value"}},"#,
+            r#"{"version":"2.0.0+arm64","payload":"image:2.0.0+arm64","metadata":{"a":"2.0.0+arm64","b":"value"}},"#,
+            r#"{"version":"3.0.0"}],"#,
+            r#""edges":[[0,1],[0,2],[2,3]],"#,
+            r#""edgeMetadata":[{"from":0,"to":2,"metadata":{"key":"value"}}]}"#,
+        );
+        assert_eq!(serde_json::to_string(&graph.canonical())?, expected);
+        assert_eq!(serde_json::to_string(&reordered.canonical())?, expected);
+
+        let deserialized: Graph = serde_json::from_str(expected)?;
+        assert_eq!(deserialized, graph);
+
+        Ok(())
+    }
+
+    #[test]
+    fn canonical_serialization_sorts_risks() -> Fallible<()> {
+        use crate::{MatchingRule, Risk};
+
+        let risk = |name: &str, rules: Vec<(&str, Vec<&str>)>| Risk {
+            name: name.to_string(),
+            url: format!("https://example.com/{}", name),
+            message: name.to_string(),
+            matching_rules: rules
+                .into_iter()
+                .map(|(parameter, values)| MatchingRule {
+                    parameter: parameter.to_string(),
+                    values: values.into_iter().map(String::from).collect(),
+                })
+                .collect(),
+        };
+        let generate = |risks: Vec<Risk>| -> Fallible<Graph> {
+            let mut graph =
+                generate_graph_in_order(&["1.0.0", "2.0.0+amd64", "2.0.0+arm64", "3.0.0"])?;
+            let v1 = graph.find_by_version("1.0.0").unwrap();
+            let v3 = graph.find_by_version("3.0.0").unwrap();
+            graph.add_conditional_edge(&v1, &v3, risks)?;
+            Ok(graph)
+        };
+
+        let graph = generate(vec![
+            risk(
+                "B",
+                vec![("arch", vec!["arm64", "amd64"]), ("channel", vec!["fast"])],
+            ),
+            risk("A", vec![]),
+        ])?;
+        let reordered = generate(vec![
+            risk("A", vec![]),
+            risk(
+                "B",
+                vec![("channel", vec!["fast"]), ("arch", vec!["amd64", "arm64"])],
+            ),
+        ])?;
+
+        let canonical = serde_json::to_string(&graph.canonical())?;
+        assert_eq!(canonical, serde_json::to_string(&reordered.canonical())?);
+        assert!(canonical.contains(concat!(
+            r#""conditionalEdges":[{"from":0,"to":3,"risks":["#,
+            r#"{"name":"A","url":"https://example.com/A","message":"A","matchingRules":[]},"#,
+            r#"{"name":"B","url":"https://example.com/B","message":"B","matchingRules":["#,
+            r#"{"parameter":"arch","values":["amd64","arm64"]},"#,
+            r#"{"parameter":"channel","values":["fast"]}]}]}]"#,
+        )));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,414 @@
+//! Computation and rendering of the differences between two graphs
+
+use crate::{version_key, Graph, Release, VersionKey};
+use petgraph::visit::{EdgeRef, IntoEdgeReferences, IntoNodeReferences};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+
+/// Differences between two graphs.
+///
+/// Releases are identified by their version, and edges by the versions of
+/// the releases they connect. All collections are ordered by semantic
+/// version, with the build metadata breaking ties, to make the output
+/// deterministic.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct GraphDiff {
+    /// Versions of the releases which only exist in the new graph.
+    pub added_releases: Vec<String>,
+
+    /// Versions of the releases which only exist in the old graph.
+    pub removed_releases: Vec<String>,
+
+    /// Edges as `[from, to]` version pairs which only exist in the new graph.
+    pub added_edges: Vec<(String, String)>,
+
+    /// Edges as `[from, to]` version pairs which only exist in the old graph.
+    pub removed_edges: Vec<(String, String)>,
+
+    /// Payload changes of the releases which exist in both graphs.
+    ///
+    /// Abstract releases have an empty payload, hence a release which became
+    /// concrete or abstract has a changed payload as well.
+    pub changed_payloads: Vec<PayloadChange>,
+
+    /// Metadata changes of the releases which exist in both graphs, as `[version, diff]` pairs.
+    pub changed_metadata: Vec<(String, MetadataDiff)>,
+}
+
+/// Change of the payload of a release.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct PayloadChange {
+    pub version: String,
+    pub old: String,
+    pub new: String,
+}
+
+/// Differences between the metadata of two releases with the same version.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct MetadataDiff {
+    /// Keys and values which only exist in the new release.
+    pub added: BTreeMap<String, String>,
+
+    /// Keys and values which only exist in the old release.
+    pub removed: BTreeMap<String, String>,
+
+    /// Keys which exist in both releases with different values.
+    pub changed: BTreeMap<String, MetadataValueChange>,
+}
+
+/// Change of a single metadata value.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct MetadataValueChange {
+    pub old: String,
+    pub new: String,
+}
+
+impl GraphDiff {
+    /// Returns true if both graphs were equal.
+    pub fn is_empty(&self) -> bool {
+        self.added_releases.is_empty()
+            && self.removed_releases.is_empty()
+            && self.added_edges.is_empty()
+            && self.removed_edges.is_empty()
+            && self.changed_payloads.is_empty()
+            && self.changed_metadata.is_empty()
+    }
+}
+
+impl MetadataDiff {
+    /// Returns true if both metadata maps were equal.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    fn new(old: &HashMap<String, String>, new: &HashMap<String, String>) -> Self {
+        let mut diff = MetadataDiff::default();
+
+        for (key, old_value) in old {
+            match new.get(key) {
+                None => {
+                    diff.removed.insert(key.to_owned(), old_value.to_owned());
+                }
+                Some(new_value) if new_value != old_value => {
+                    diff.changed.insert(
+                        key.to_owned(),
+                        MetadataValueChange {
+                            old: old_value.to_owned(),
+                            new: new_value.to_owned(),
+                        },
+                    );
+                }
+                Some(_) => {}
+            }
+        }
+
+        for (key, new_value) in new {
+            if !old.contains_key(key) {
+                diff.added.insert(key.to_owned(), new_value.to_owned());
+            }
+        }
+
+        diff
+    }
+}
+
+impl fmt::Display for GraphDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "no changes");
+        }
+
+        let mut lines = Vec::new();
+        for version in &self.added_releases {
+            lines.push(format!("+ release {}", version));
+        }
+        for version in &self.removed_releases {
+            lines.push(format!("- release {}", version));
+        }
+        for (from, to) in &self.added_edges {
+            lines.push(format!("+ edge {} -> {}", from, to));
+        }
+        for (from, to) in &self.removed_edges {
+            lines.push(format!("- edge {} -> {}", from, to));
+        }
+        for change in &self.changed_payloads {
+            lines.push(format!(
+                "~ payload of release {}: '{}' -> '{}'",
+                change.version, change.old, change.new
+            ));
+        }
+        for (version, metadata) in &self.changed_metadata {
+            lines.push(format!("~ metadata of release {}", version));
+            for (key, value) in &metadata.added {
+                lines.push(format!("    + {}={}", key, value));
+            }
+            for (key, value) in &metadata.removed {
+                lines.push(format!("    - {}={}", key, value));
+            }
+            for (key, change) in &metadata.changed {
+                lines.push(format!("    ~ {}={} -> {}", key, change.old, change.new));
+            }
+        }
+
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+impl Graph {
+    /// Returns the differences between this graph and the `other` graph.
+    ///
+    /// This graph is considered to be the old one, `other` the new one.
+    pub fn diff(&self, other: &Graph) -> GraphDiff {
+        let (releases, other_releases) = (self.releases_by_version(), other.releases_by_version());
+        let (edges, other_edges) = (self.edges_by_version(), other.edges_by_version());
+        let version = |key: &VersionKey| key.0.to_string();
+        let edge = |(from, to): &(VersionKey, VersionKey)| (version(from), version(to));
+        let common_releases = || {
+            releases.iter().filter_map(|(key, release)| {
+                Some((version(key), *release, *other_releases.get(key)?))
+            })
+        };
+
+        GraphDiff {
+            added_releases: other_releases
+                .keys()
+                .filter(|key| !releases.contains_key(*key))
+                .map(version)
+                .collect(),
+            removed_releases: releases
+                .keys()
+                .filter(|key| !other_releases.contains_key(*key))
+                .map(version)
+                .collect(),
+            added_edges: other_edges.difference(&edges).map(edge).collect(),
+            removed_edges: edges.difference(&other_edges).map(edge).collect(),
+            changed_payloads: common_releases()
+                .filter_map(|(version, release, other_release)| {
+                    let (old, new) = (payload(release), payload(other_release));
+                    if old == new {
+                        None
+                    } else {
+                        Some(PayloadChange {
+                            version,
+                            old: old.to_string(),
+                            new: new.to_string(),
+                        })
+                    }
+                })
+                .collect(),
+            changed_metadata: common_releases()
+                .filter_map(|(version, release, other_release)| {
+                    let empty = HashMap::new();
+                    let diff = MetadataDiff::new(
+                        release.metadata().unwrap_or(&empty),
+                        other_release.metadata().unwrap_or(&empty),
+                    );
+                    if diff.is_empty() {
+                        None
+                    } else {
+                        Some((version, diff))
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    fn releases_by_version(&self) -> BTreeMap<VersionKey, &Release> {
+        self.dag
+            .node_references()
+            .map(|(_, release)| (version_key(release.version()), release))
+            .collect()
+    }
+
+    fn edges_by_version(&self) -> BTreeSet<(VersionKey, VersionKey)> {
+        self.dag
+            .edge_references()
+            .map(|edge| {
+                (
+                    version_key(self.dag[edge.source()].version()),
+                    version_key(self.dag[edge.target()].version()),
+                )
+            })
+            .collect()
+    }
+}
+
+/// Returns the payload of concrete releases, and an empty one for abstract releases.
+fn payload(release: &Release) -> &str {
+    match release {
+        Release::Concrete(release) => &release.payload,
+        Release::Abstract(_) => "",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate serde_json;
+
+    use super::*;
+    use crate::testing::{find, generate_custom_graph};
+    use failure::Fallible;
+
+    #[test]
+    fn diff_of_equal_graphs_is_empty() {
+        let graph = generate_custom_graph(
+            "image",
+            (0..3).map(|i| (i, Default::default())).collect(),
+            None,
+        );
+
+        let diff = graph.diff(&graph);
+        assert!(diff.is_empty());
+        assert_eq!(diff.to_string(), "no changes");
+    }
+
+    #[test]
+    fn diff_reports_releases_edges_and_metadata() -> Fallible<()> {
+        let old = generate_custom_graph(
+            "image",
+            (0..3)
+                .map(|i| {
+                    (
+                        i,
+                        [("key".to_string(), "old".to_string())]
+                            .iter()
+                            .cloned()
+                            .collect(),
+                    )
+                })
+                .collect(),
+            None,
+        );
+
+        let mut new = old.clone();
+        let (v0, v1, v2) = (
+            find(&new, "0.0.0")?,
+            find(&new, "1.0.0")?,
+            find(&new, "2.0.0")?,
+        );
+        assert_eq!(new.remove_releases(vec![v0]), 1);
+        new.remove_edge(&v1, &v2)?;
+        let v3 = new.add_release(crate::Release::Concrete(crate::ConcreteRelease {
+            version: semver::Version::new(3, 0, 0),
+            payload: "image:3.0.0".to_string(),
+            metadata: HashMap::new(),
+        }))?;
+        new.add_edge(&v1, &v3)?;
+        {
+            let metadata = new.get_metadata_as_ref_mut(&v1)?;
+            metadata.insert("key".to_string(), "new".to_string());
+            metadata.insert("added".to_string(), "value".to_string());
+        }
+        new.get_metadata_as_ref_mut(&v2)?.remove("key");
+
+        let diff = old.diff(&new);
+        assert_eq!(diff.added_releases, vec!["3.0.0"]);
+        assert_eq!(diff.removed_releases, vec!["0.0.0"]);
+        assert_eq!(
+            diff.added_edges,
+            vec![("1.0.0".to_string(), "3.0.0".to_string())]
+        );
+        assert_eq!(
+            diff.removed_edges,
+            vec![
+                ("0.0.0".to_string(), "1.0.0".to_string()),
+                ("1.0.0".to_string(), "2.0.0".to_string()),
+            ]
+        );
+        assert_eq!(diff.changed_metadata.len(), 2);
+
+        assert_eq!(
+            diff.to_string(),
+            vec![
+                "+ release 3.0.0",
+                "- release 0.0.0",
+                "+ edge 1.0.0 -> 3.0.0",
+                "- edge 0.0.0 -> 1.0.0",
+                "- edge 1.0.0 -> 2.0.0",
+                "~ metadata of release 1.0.0",
+                "    + added=value",
+                "    ~ key=old -> new",
+                "~ metadata of release 2.0.0",
+                "    - key=old",
+            ]
+            .join("\n")
+        );
+
+        let json = serde_json::to_string(&diff)?;
+        assert_eq!(serde_json::from_str::<GraphDiff>(&json)?, diff);
+
+        Ok(())
+    }
+
+    #[test]
+    fn diff_is_ordered_by_semantic_version() -> Fallible<()> {
+        let old = crate::GraphBuilder::new().build()?;
+        let new = crate::GraphBuilder::new()
+            .edge("1.10.0", "10.0.0")
+            .edge("1.10.0", "9.0.0")
+            .edge("1.9.0+arm64", "9.0.0")
+            .edge("1.9.0+amd64", "9.0.0")
+            .build()?;
+
+        let diff = old.diff(&new);
+        assert_eq!(
+            diff.added_releases,
+            vec!["1.9.0+amd64", "1.9.0+arm64", "1.10.0", "9.0.0", "10.0.0"]
+        );
+        assert_eq!(
+            diff.added_edges,
+            vec![
+                ("1.9.0+amd64".to_string(), "9.0.0".to_string()),
+                ("1.9.0+arm64".to_string(), "9.0.0".to_string()),
+                ("1.10.0".to_string(), "9.0.0".to_string()),
+                ("1.10.0".to_string(), "10.0.0".to_string()),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn diff_reports_payloads() -> Fallible<()> {
+        let old = crate::GraphBuilder::new()
+            .release("1.0.0", "image:1.0.0", HashMap::new())
+            .release("2.0.0", "image:2.0.0", HashMap::new())
+            .edge("1.0.0", "2.0.0")
+            .edge("2.0.0", "3.0.0")
+            .build()?;
+        let new = crate::GraphBuilder::new()
+            .release("1.0.0", "image:1.0.0", HashMap::new())
+            .release("2.0.0", "other:2.0.0", HashMap::new())
+            .release("3.0.0", "image:3.0.0", HashMap::new())
+            .edge("1.0.0", "2.0.0")
+            .edge("2.0.0", "3.0.0")
+            .build()?;
+
+        let diff = old.diff(&new);
+        assert_eq!(
+            diff.changed_payloads,
+            vec![
+                PayloadChange {
+                    version: "2.0.0".to_string(),
+                    old: "image:2.0.0".to_string(),
+                    new: "other:2.0.0".to_string(),
+                },
+                PayloadChange {
+                    version: "3.0.0".to_string(),
+                    old: "".to_string(),
+                    new: "image:3.0.0".to_string(),
+                },
+            ]
+        );
+        assert_eq!(
+            diff.to_string(),
+            vec![
+                "~ payload of release 2.0.0: 'image:2.0.0' -> 'other:2.0.0'",
+                "~ payload of release 3.0.0: '' -> 'image:3.0.0'",
+            ]
+            .join("\n")
+        );
+
+        Ok(())
+    }
+}
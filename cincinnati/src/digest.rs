@@ -0,0 +1,83 @@
+//! Content digest of graphs
+
+use crate::Graph;
+use failure::Fallible;
+use sha2::{Digest, Sha256};
+
+impl Graph {
+    /// Returns the hex-encoded SHA-256 digest of the graph's canonical serialization.
+    ///
+    /// The digest covers the releases, the edges and their metadata, but not
+    /// the order in which they were added, so equal graphs have equal digests.
+    pub fn digest(&self) -> Fallible<String> {
+        let (_, digest) = self.canonical_json_with_digest()?;
+        Ok(digest)
+    }
+
+    /// Returns the canonical JSON serialization of the graph together with its digest.
+    ///
+    /// Services which serve the canonical JSON use this to serialize the graph only once.
+    pub fn canonical_json_with_digest(&self) -> Fallible<(String, String)> {
+        let canonical = serde_json::to_string(&self.canonical())?;
+        let digest = digest(canonical.as_bytes());
+        Ok((canonical, digest))
+    }
+}
+
+/// Returns the hex-encoded SHA-256 digest of `data`.
+pub fn digest(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{generate_custom_graph, generate_graph_in_order};
+    use maplit::hashmap;
+
+    #[test]
+    fn digest_ignores_insertion_order() -> Fallible<()> {
+        let graph = generate_graph_in_order(&["1.0.0", "2.0.0+amd64", "2.0.0+arm64", "3.0.0"])?;
+        let reordered = generate_graph_in_order(&["3.0.0", "2.0.0+arm64", "1.0.0", "2.0.0+amd64"])?;
+
+        let digest = graph.digest()?;
+        assert_eq!(digest.len(), 64);
+        assert!(digest.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_eq!(digest, reordered.digest()?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn digest_covers_content() -> Fallible<()> {
+        let metadata = || vec![(0, hashmap! {}), (1, hashmap! {}), (2, hashmap! {})];
+        let digest = generate_custom_graph("image", metadata(), None).digest()?;
+
+        let mut changed_metadata = generate_custom_graph("image", metadata(), None);
+        let id = changed_metadata.find_by_version("1.0.0").unwrap();
+        changed_metadata
+            .get_metadata_as_ref_mut(&id)?
+            .insert("key".to_string(), "value".to_string());
+        assert_ne!(digest, changed_metadata.digest()?);
+
+        let changed_edges = generate_custom_graph("image", metadata(), Some(vec![(0, 1), (0, 2)]));
+        assert_ne!(digest, changed_edges.digest()?);
+
+        let changed_payload = generate_custom_graph("other", metadata(), None);
+        assert_ne!(digest, changed_payload.digest()?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn digest_is_computed_from_canonical_json() -> Fallible<()> {
+        let graph = generate_graph_in_order(&["3.0.0", "2.0.0+arm64", "1.0.0", "2.0.0+amd64"])?;
+
+        let (canonical_json, digest) = graph.canonical_json_with_digest()?;
+        assert_eq!(canonical_json, serde_json::to_string(&graph.canonical())?);
+        assert_eq!(digest, graph.digest()?);
+        assert_eq!(digest, super::digest(canonical_json.as_bytes()));
+
+        Ok(())
+    }
+}
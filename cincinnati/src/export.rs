@@ -0,0 +1,503 @@
+//! Export of graphs to the DOT and GraphML formats
+//!
+//! Nodes and edges are identified by their position in the serialized JSON
+//! representation of the graph.
+
+use crate::{Edge, Graph, Release};
+use petgraph::visit::{EdgeRef, IntoEdgeReferences, IntoNodeReferences};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::{self, Write};
+
+/// Media type of the DOT representation of a graph.
+pub const DOT_CONTENT_TYPE: &str = "text/vnd.graphviz";
+
+/// Media type of the GraphML representation of a graph.
+pub const GRAPHML_CONTENT_TYPE: &str = "application/graphml+xml";
+
+/// Colors which are assigned to the distinct entries of the `color_by` metadata key.
+static PALETTE: &[&str] = &[
+    "#1f77b4", "#ff7f0e", "#2ca02c", "#d62728", "#9467bd", "#8c564b", "#e377c2", "#7f7f7f",
+    "#bcbd22", "#17becf",
+];
+
+/// Options for the exported representations.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct ExportOptions {
+    /// Release metadata key, e.g. `io.openshift.upgrades.graph.release.channels`.
+    /// Its value is a comma-separated list, each distinct entry gets its own
+    /// color and releases are filled with the colors of all their entries.
+    pub color_by: Option<String>,
+
+    /// Edge metadata key whose value is used as the label of each edge.
+    pub edge_label: Option<String>,
+}
+
+impl ExportOptions {
+    /// Returns the hex-encoded SHA-256 digest of the options.
+    ///
+    /// Together with the digest of a graph, it identifies the representation
+    /// exported with these options.
+    pub fn digest(&self) -> String {
+        let options = serde_json::to_vec(self).expect("serializing the options can't fail");
+        crate::digest::digest(&options)
+    }
+}
+
+impl Graph {
+    /// Returns the DOT representation of the graph.
+    ///
+    /// The release metadata key `url` is used as link for the release, and
+    /// conditional edges are dashed.
+    pub fn to_dot(&self, options: &ExportOptions) -> String {
+        let mut dot = String::new();
+        self.write_dot(&mut dot, options)
+            .expect("writing to a String can't fail");
+        dot
+    }
+
+    /// Writes the DOT representation of the graph, see `to_dot`.
+    pub fn write_dot<W: Write>(&self, w: &mut W, options: &ExportOptions) -> fmt::Result {
+        let positions = self.node_positions();
+        let colors = self.colors(options);
+
+        writeln!(w, "digraph Upgrades {{")?;
+        writeln!(w, "  labelloc=t;")?;
+        writeln!(w, "  rankdir=BT;")?;
+
+        for (index, release) in self.dag.node_references() {
+            write!(
+                w,
+                "  {} [ label=\"{}\"",
+                positions[&index],
+                escape_dot(&release.version().to_string())
+            )?;
+            if let Some(url) = release.metadata().and_then(|metadata| metadata.get("url")) {
+                write!(w, " href=\"{}\"", escape_dot(url))?;
+            }
+            match colors_of(release, options, &colors).as_slice() {
+                [] => {}
+                [color] => write!(w, " style=filled fillcolor=\"{}\"", color)?,
+                colors => write!(w, " style=wedged fillcolor=\"{}\"", colors.join(":"))?,
+            }
+            writeln!(w, " ];")?;
+        }
+
+        for edge in self.dag.edge_references() {
+            let mut attributes = vec![];
+            if let Some(label) = label_of(edge.weight(), options) {
+                attributes.push(format!("label=\"{}\"", escape_dot(label)));
+            }
+            if edge.weight().is_conditional() {
+                attributes.push("style=dashed".to_string());
+                attributes.push(format!(
+                    "tooltip=\"{}\"",
+                    escape_dot(&risk_names(edge.weight()))
+                ));
+            }
+
+            write!(
+                w,
+                "  {}->{}",
+                positions[&edge.source()],
+                positions[&edge.target()]
+            )?;
+            if !attributes.is_empty() {
+                write!(w, " [ {} ]", attributes.join(" "))?;
+            }
+            writeln!(w, ";")?;
+        }
+
+        writeln!(w, "}}")
+    }
+
+    /// Returns the GraphML representation of the graph.
+    ///
+    /// All release and edge metadata is exported as attributes named
+    /// `metadata.<key>`.
+    pub fn to_graphml(&self, options: &ExportOptions) -> String {
+        let mut graphml = String::new();
+        self.write_graphml(&mut graphml, options)
+            .expect("writing to a String can't fail");
+        graphml
+    }
+
+    /// Writes the GraphML representation of the graph, see `to_graphml`.
+    pub fn write_graphml<W: Write>(&self, w: &mut W, options: &ExportOptions) -> fmt::Result {
+        let positions = self.node_positions();
+        let colors = self.colors(options);
+
+        let node_keys: BTreeSet<&String> = self
+            .dag
+            .node_references()
+            .filter_map(|(_, release)| release.metadata())
+            .flat_map(HashMap::keys)
+            .collect();
+        let node_keys: BTreeMap<&String, String> = node_keys
+            .into_iter()
+            .enumerate()
+            .map(|(i, key)| (key, format!("node_metadata_{}", i)))
+            .collect();
+        let edge_keys: BTreeSet<&String> = self
+            .dag
+            .edge_references()
+            .flat_map(|edge| edge.weight().metadata.keys())
+            .collect();
+        let edge_keys: BTreeMap<&String, String> = edge_keys
+            .into_iter()
+            .enumerate()
+            .map(|(i, key)| (key, format!("edge_metadata_{}", i)))
+            .collect();
+
+        writeln!(w, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(
+            w,
+            r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#
+        )?;
+        let mut write_key = |id: &str, target: &str, name: &str, kind: &str| {
+            writeln!(
+                w,
+                r#"  <key id="{}" for="{}" attr.name="{}" attr.type="{}"/>"#,
+                id,
+                target,
+                escape_xml(name),
+                kind
+            )
+        };
+        write_key("version", "node", "version", "string")?;
+        write_key("payload", "node", "payload", "string")?;
+        if options.color_by.is_some() {
+            write_key("color", "node", "color", "string")?;
+        }
+        for (key, id) in &node_keys {
+            write_key(id, "node", &format!("metadata.{}", key), "string")?;
+        }
+        if options.edge_label.is_some() {
+            write_key("label", "edge", "label", "string")?;
+        }
+        write_key("risks", "edge", "risks", "string")?;
+        for (key, id) in &edge_keys {
+            write_key(id, "edge", &format!("metadata.{}", key), "string")?;
+        }
+
+        writeln!(w, r#"  <graph id="Upgrades" edgedefault="directed">"#)?;
+
+        for (index, release) in self.dag.node_references() {
+            writeln!(w, r#"    <node id="n{}">"#, positions[&index])?;
+            write_data(w, "version", &release.version().to_string())?;
+            if let Release::Concrete(release) = release {
+                write_data(w, "payload", &release.payload)?;
+            }
+            let release_colors = colors_of(release, options, &colors);
+            if !release_colors.is_empty() {
+                write_data(w, "color", &release_colors.join(":"))?;
+            }
+            if let Some(metadata) = release.metadata() {
+                for (key, id) in &node_keys {
+                    if let Some(value) = metadata.get(*key) {
+                        write_data(w, id, value)?;
+                    }
+                }
+            }
+            writeln!(w, "    </node>")?;
+        }
+
+        for edge in self.dag.edge_references() {
+            writeln!(
+                w,
+                r#"    <edge source="n{}" target="n{}">"#,
+                positions[&edge.source()],
+                positions[&edge.target()]
+            )?;
+            if let Some(label) = label_of(edge.weight(), options) {
+                write_data(w, "label", label)?;
+            }
+            if edge.weight().is_conditional() {
+                write_data(w, "risks", &risk_names(edge.weight()))?;
+            }
+            for (key, id) in &edge_keys {
+                if let Some(value) = edge.weight().metadata.get(*key) {
+                    write_data(w, id, value)?;
+                }
+            }
+            writeln!(w, "    </edge>")?;
+        }
+
+        writeln!(w, "  </graph>")?;
+        writeln!(w, "</graphml>")
+    }
+
+    /// Assigns a color to each distinct entry of the `color_by` metadata key, ordered by entry.
+    fn colors(&self, options: &ExportOptions) -> HashMap<&str, &'static str> {
+        let key = match &options.color_by {
+            Some(key) => key,
+            None => return HashMap::new(),
+        };
+
+        let values: BTreeSet<&str> = self
+            .dag
+            .node_references()
+            .filter_map(|(_, release)| release.metadata()?.get(key))
+            .flat_map(|value| entries(value))
+            .collect();
+
+        values
+            .into_iter()
+            .zip(PALETTE.iter().cycle())
+            .map(|(value, color)| (value, *color))
+            .collect()
+    }
+}
+
+/// Returns the colors of all entries of the release's `color_by` metadata value.
+fn colors_of(
+    release: &Release,
+    options: &ExportOptions,
+    colors: &HashMap<&str, &'static str>,
+) -> Vec<&'static str> {
+    let value = options
+        .color_by
+        .as_ref()
+        .and_then(|key| release.metadata()?.get(key));
+    let mut release_colors: Vec<&'static str> = vec![];
+    for entry in value.map(|value| entries(value)).into_iter().flatten() {
+        if let Some(color) = colors.get(entry) {
+            if !release_colors.contains(color) {
+                release_colors.push(color);
+            }
+        }
+    }
+    release_colors
+}
+
+/// Splits a comma-separated metadata value into its non-empty entries.
+fn entries(value: &str) -> impl Iterator<Item = &str> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+}
+
+fn label_of<'a>(edge: &'a Edge, options: &ExportOptions) -> Option<&'a String> {
+    edge.metadata.get(options.edge_label.as_ref()?)
+}
+
+fn risk_names(edge: &Edge) -> String {
+    edge.risks
+        .iter()
+        .map(|risk| risk.name.as_str())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn write_data<W: Write>(w: &mut W, key: &str, value: &str) -> fmt::Result {
+    writeln!(
+        w,
+        r#"      <data key="{}">{}</data>"#,
+        key,
+        escape_xml(value)
+    )
+}
+
+/// Escapes a string for use inside a quoted DOT identifier.
+///
+/// Line breaks are replaced by the DOT escape sequence for a centered line.
+fn escape_dot(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+        .replace('\r', "\\n")
+}
+
+/// Escapes a string for use as XML text or attribute value.
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::generate_custom_graph;
+    use crate::{MatchingRule, Risk};
+    use failure::Fallible;
+    use maplit::hashmap;
+
+    static CHANNELS: &str = "io.openshift.upgrades.graph.release.channels";
+
+    /// Returns the graph 0 -> 1 -> 2 and 0 -> 2, where the latter edge is
+    /// conditional and has metadata.
+    fn generate_test_graph() -> Fallible<Graph> {
+        let mut graph = generate_custom_graph(
+            "image",
+            vec![
+                (
+                    0,
+                    hashmap! {
+                        CHANNELS.to_string() => "a".to_string(),
+                        "url".to_string() => "https://example.com/\"0\"".to_string(),
+                    },
+                ),
+                (1, hashmap! { CHANNELS.to_string() => "b".to_string() }),
+                (2, hashmap! { CHANNELS.to_string() => "a".to_string() }),
+            ],
+            None,
+        );
+        let v0 = graph
+            .find_by_version("0.0.0")
+            .ok_or_else(|| format_err!("couldn't find 0.0.0"))?;
+        let v2 = graph
+            .find_by_version("2.0.0")
+            .ok_or_else(|| format_err!("couldn't find 2.0.0"))?;
+        graph.add_conditional_edge(
+            &v0,
+            &v2,
+            vec![Risk {
+                name: "SkipRisk".to_string(),
+                url: "https://example.com/skip".to_string(),
+                message: "skipping is risky".to_string(),
+                matching_rules: vec![MatchingRule {
+                    parameter: "arch".to_string(),
+                    values: vec!["arm64".to_string()],
+                }],
+            }],
+        )?;
+        graph
+            .get_edge_metadata_as_ref_mut(&v0, &v2)?
+            .insert("reason".to_string(), "skip <1>".to_string());
+
+        Ok(graph)
+    }
+
+    #[test]
+    fn to_dot_without_options() -> Fallible<()> {
+        let graph = generate_test_graph()?;
+
+        assert_eq!(
+            graph.to_dot(&ExportOptions::default()),
+            [
+                "digraph Upgrades {",
+                "  labelloc=t;",
+                "  rankdir=BT;",
+                r#"  0 [ label="0.0.0" href="https://example.com/\"0\"" ];"#,
+                r#"  1 [ label="1.0.0" ];"#,
+                r#"  2 [ label="2.0.0" ];"#,
+                "  0->1;",
+                "  1->2;",
+                r#"  0->2 [ style=dashed tooltip="SkipRisk" ];"#,
+                "}\n",
+            ]
+            .join("\n")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn to_dot_colors_nodes_and_labels_edges() -> Fallible<()> {
+        let graph = generate_test_graph()?;
+        let options = ExportOptions {
+            color_by: Some(CHANNELS.to_string()),
+            edge_label: Some("reason".to_string()),
+        };
+
+        let dot = graph.to_dot(&options);
+        assert!(dot.contains(r##"  1 [ label="1.0.0" style=filled fillcolor="#ff7f0e" ];"##));
+        assert!(dot.contains(r##"  2 [ label="2.0.0" style=filled fillcolor="#1f77b4" ];"##));
+        assert!(dot.contains(r#"  0->2 [ label="skip <1>" style=dashed tooltip="SkipRisk" ];"#));
+
+        Ok(())
+    }
+
+    #[test]
+    fn to_dot_colors_each_entry() -> Fallible<()> {
+        let graph = generate_custom_graph(
+            "image",
+            vec![
+                (0, hashmap! { CHANNELS.to_string() => "a".to_string() }),
+                (1, hashmap! { CHANNELS.to_string() => "a, b".to_string() }),
+                (2, hashmap! { CHANNELS.to_string() => "b,".to_string() }),
+            ],
+            None,
+        );
+        let options = ExportOptions {
+            color_by: Some(CHANNELS.to_string()),
+            ..Default::default()
+        };
+
+        let dot = graph.to_dot(&options);
+        assert!(dot.contains(r##"  0 [ label="0.0.0" style=filled fillcolor="#1f77b4" ];"##));
+        assert!(
+            dot.contains(r##"  1 [ label="1.0.0" style=wedged fillcolor="#1f77b4:#ff7f0e" ];"##)
+        );
+        assert!(dot.contains(r##"  2 [ label="2.0.0" style=filled fillcolor="#ff7f0e" ];"##));
+
+        let graphml = graph.to_graphml(&options);
+        assert!(graphml.contains(r##"      <data key="color">#1f77b4:#ff7f0e</data>"##));
+
+        Ok(())
+    }
+
+    #[test]
+    fn options_digest_covers_all_options() {
+        let default = ExportOptions::default();
+        let color_by = ExportOptions {
+            color_by: Some(CHANNELS.to_string()),
+            ..Default::default()
+        };
+        let edge_label = ExportOptions {
+            edge_label: Some(CHANNELS.to_string()),
+            ..Default::default()
+        };
+
+        assert_eq!(default.digest(), ExportOptions::default().digest());
+        assert_ne!(default.digest(), color_by.digest());
+        assert_ne!(default.digest(), edge_label.digest());
+        assert_ne!(color_by.digest(), edge_label.digest());
+    }
+
+    #[test]
+    fn escape_dot_escapes_line_breaks() {
+        assert_eq!(escape_dot("a \"b\"\\\nc\r\nd\re"), r#"a \"b\"\\\nc\nd\ne"#);
+    }
+
+    #[test]
+    fn to_graphml_exports_all_metadata() -> Fallible<()> {
+        let graph = generate_test_graph()?;
+        let options = ExportOptions {
+            color_by: Some(CHANNELS.to_string()),
+            edge_label: Some("reason".to_string()),
+        };
+
+        let graphml = graph.to_graphml(&options);
+        for expected in &[
+            r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#,
+            r#"  <key id="node_metadata_0" for="node" attr.name="metadata.io.openshift.upgrades.graph.release.channels" attr.type="string"/>"#,
+            r#"  <key id="node_metadata_1" for="node" attr.name="metadata.url" attr.type="string"/>"#,
+            r#"  <key id="edge_metadata_0" for="edge" attr.name="metadata.reason" attr.type="string"/>"#,
+            r#"    <node id="n0">"#,
+            r#"      <data key="payload">image:0.0.0</data>"#,
+            r##"      <data key="color">#1f77b4</data>"##,
+            r#"      <data key="node_metadata_1">https://example.com/&quot;0&quot;</data>"#,
+            r#"    <edge source="n0" target="n2">"#,
+            r#"      <data key="label">skip &lt;1&gt;</data>"#,
+            r#"      <data key="risks">SkipRisk</data>"#,
+        ] {
+            assert!(
+                graphml.contains(expected),
+                "missing {} in {}",
+                expected,
+                graphml
+            );
+        }
+        assert!(graphml.ends_with("  </graph>\n</graphml>\n"));
+
+        Ok(())
+    }
+}
@@ -13,6 +13,7 @@
 // limitations under the License.
 
 extern crate daggy;
+extern crate petgraph;
 #[macro_use]
 extern crate failure;
 extern crate serde;
@@ -33,6 +34,7 @@ extern crate actix_web;
 extern crate futures;
 extern crate prometheus;
 extern crate serde_json;
+extern crate sha2;
 #[macro_use]
 extern crate custom_debug_derive;
 pub extern crate futures_locks;
@@ -42,25 +44,69 @@ extern crate tokio;
 
 #[macro_use]
 pub mod plugins;
+pub mod builder;
+pub mod canonical;
+pub mod diff;
+pub mod digest;
+pub mod export;
+pub mod merge;
+pub mod metadata;
+pub mod paths;
+pub mod payload;
+pub mod query;
+pub mod stats;
+pub mod subgraph;
+pub mod validation;
 
-use daggy::petgraph::visit::{IntoNodeReferences, NodeRef};
-use daggy::{Dag, Walker};
 use failure::{Error, Fallible};
-use serde::de::{self, Deserialize, Deserializer, MapAccess, Visitor};
+use petgraph::algo::DfsSpace;
+use petgraph::stable_graph::StableDiGraph;
+use petgraph::visit::{EdgeRef, IntoEdgeReferences, IntoNodeReferences, NodeRef, Visitable};
+use petgraph::Direction;
+use semver::Version;
+use serde::de::{self, Deserialize, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor};
 use serde::ser::{Serialize, SerializeStruct, Serializer};
+use std::cmp::Ordering;
 use std::collections::HashMap;
-use std::{collections, fmt};
+use std::convert::TryFrom;
+use std::fmt;
 
+pub use builder::GraphBuilder;
 pub use daggy::WouldCycle;
+pub use diff::GraphDiff;
+pub use export::ExportOptions;
+pub use merge::{MergeConflict, MergeStrategy};
+pub use paths::UpgradePath;
+pub use payload::ImageReference;
+pub use query::Query;
+pub use stats::GraphStats;
 
 pub const CONTENT_TYPE: &str = "application/json";
+/// Media type of the protobuf representation of a graph, see `plugins::interface::Graph`.
+pub const PROTOBUF_CONTENT_TYPE: &str = "application/x-protobuf";
 const EXPECT_NODE_WEIGHT: &str = "all exisitng nodes to have a weight (release)";
 
-/// Graph type which stores `Release` as node-weights and `Empty` as edge-weights.
+/// Underlying graph type, which keeps the indices of all nodes stable on node removal.
+type Dag = StableDiGraph<Release, Edge>;
+
+/// Graph type which stores `Release` as node-weights and `Edge` as edge-weights.
 #[derive(Debug, Default)]
 #[cfg_attr(test, derive(Clone))]
 pub struct Graph {
-    dag: Dag<Release, Empty>,
+    dag: Dag,
+
+    /// Generation of every node slot in `dag`, indexed by `NodeIndex`.
+    ///
+    /// The generation of a slot is increased whenever its release is removed.
+    /// This invalidates all `ReleaseId`s of the removed release, even if the
+    /// slot is reused by a release which is added later on.
+    generations: Vec<u64>,
+
+    /// Index of all release versions in `dag`, which allows constant time lookups by version.
+    versions: HashMap<String, ReleaseId>,
+
+    /// Reusable workspace for the cycle detection in `add_edge`.
+    cycle_state: DfsSpace<daggy::NodeIndex, <Dag as Visitable>::Map>,
 }
 
 /// Wrapper enum for the concrete and abstract release types.
@@ -72,58 +118,127 @@ pub enum Release {
 }
 
 impl Release {
-    /// Return the version string of a given `Release`.
-    pub fn version(&self) -> &str {
+    /// Return the version of a given `Release`.
+    pub fn version(&self) -> &Version {
         match self {
             Release::Abstract(release) => &release.version,
             Release::Concrete(release) => &release.version,
         }
     }
+
+    /// Return the metadata of a given `Release`, which abstract releases don't have.
+    pub fn metadata(&self) -> Option<&HashMap<String, String>> {
+        match self {
+            Release::Abstract(_) => None,
+            Release::Concrete(release) => Some(&release.metadata),
+        }
+    }
+
+    fn version_mut(&mut self) -> &mut Version {
+        match self {
+            Release::Abstract(release) => &mut release.version,
+            Release::Concrete(release) => &mut release.version,
+        }
+    }
+}
+
+/// Releases are ordered by their version.
+///
+/// Distinct releases with the same version, e.g. differing only in their
+/// build metadata, are not comparable.
+impl PartialOrd for Release {
+    fn partial_cmp(&self, other: &Release) -> Option<Ordering> {
+        if self == other {
+            return Some(Ordering::Equal);
+        }
+        match self.version().cmp(other.version()) {
+            Ordering::Equal => None,
+            ordering => Some(ordering),
+        }
+    }
 }
 
 /// Type to represent a Release with all its information.
-#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct ConcreteRelease {
-    pub version: String,
+    pub version: Version,
     pub payload: String,
     pub metadata: HashMap<String, String>,
 }
 
+impl PartialEq for ConcreteRelease {
+    fn eq(&self, other: &ConcreteRelease) -> bool {
+        versions_eq(&self.version, &other.version)
+            && self.payload == other.payload
+            && self.metadata == other.metadata
+    }
+}
+
 /// Abtract release only storing a version.
 ///
 /// It can be used for adding an edge between an existing and a non-existing
 /// release, and is expected to later be filled up with a `ConcreteRelease` once
 /// the graph is completed.
-#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct AbstractRelease {
-    pub version: String,
+    pub version: Version,
+}
+
+impl PartialEq for AbstractRelease {
+    fn eq(&self, other: &AbstractRelease) -> bool {
+        versions_eq(&self.version, &other.version)
+    }
+}
+
+/// Compares versions including their build metadata, which `semver::Version` ignores.
+///
+/// The build metadata distinguishes the releases of different architectures.
+fn versions_eq(a: &Version, b: &Version) -> bool {
+    a == b && a.build == b.build
+}
+
+/// Key which orders versions semantically and distinguishes their build metadata.
+///
+/// `semver::Version`'s ordering ignores the build metadata, which is compared separately.
+pub(crate) type VersionKey = (Version, Vec<semver::Identifier>);
+
+pub(crate) fn version_key(version: &Version) -> VersionKey {
+    (version.clone(), version.build.clone())
 }
 
 /// Abstraction over a node in the graph representing a `Release`
+///
+/// A `ReleaseId` stays valid until its release is removed from the graph,
+/// regardless of the removal of other releases. Using it afterwards results
+/// in an `errors::InvalidReleaseId` error.
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
-pub struct ReleaseId(daggy::NodeIndex);
+pub struct ReleaseId {
+    index: daggy::NodeIndex,
+    generation: u64,
+}
 
 /// Can be used to iterate over all direct children of the given release.
 ///
 /// See the `next_releases` method for more information.
 pub struct NextReleases<'a> {
-    children: daggy::Children<Release, Empty, daggy::petgraph::graph::DefaultIx>,
-    dag: &'a Dag<Release, Empty>,
+    edges: petgraph::stable_graph::Edges<'a, Edge, petgraph::Directed>,
+    graph: &'a Graph,
 }
 
 impl<'a> Iterator for NextReleases<'a> {
-    type Item = (daggy::EdgeIndex, daggy::NodeIndex, &'a Release);
+    type Item = (daggy::EdgeIndex, ReleaseId, &'a Release);
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.children
-            .walk_next(self.dag)
-            .map(|(edge_index, node_index)| {
-                (
-                    edge_index,
-                    node_index,
-                    self.dag.node_weight(node_index).expect(EXPECT_NODE_WEIGHT),
-                )
-            })
+        self.edges.next().map(|edge| {
+            (
+                edge.id(),
+                self.graph.release_id(edge.target()),
+                self.graph
+                    .dag
+                    .node_weight(edge.target())
+                    .expect(EXPECT_NODE_WEIGHT),
+            )
+        })
     }
 }
 
@@ -131,27 +246,86 @@ impl<'a> Iterator for NextReleases<'a> {
 ///
 /// See the `previous_releases` method for more information.
 pub struct PreviousReleases<'a> {
-    parents: daggy::Parents<Release, Empty, daggy::petgraph::graph::DefaultIx>,
-    dag: &'a Dag<Release, Empty>,
+    edges: petgraph::stable_graph::Edges<'a, Edge, petgraph::Directed>,
+    graph: &'a Graph,
 }
 
 impl<'a> Iterator for PreviousReleases<'a> {
-    type Item = (daggy::EdgeIndex, daggy::NodeIndex, &'a Release);
+    type Item = (daggy::EdgeIndex, ReleaseId, &'a Release);
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.parents
-            .walk_next(self.dag)
-            .map(|(edge_index, node_index)| {
-                (
-                    edge_index,
-                    node_index,
-                    self.dag.node_weight(node_index).expect(EXPECT_NODE_WEIGHT),
-                )
-            })
+        self.edges.next().map(|edge| {
+            (
+                edge.id(),
+                self.graph.release_id(edge.source()),
+                self.graph
+                    .dag
+                    .node_weight(edge.source())
+                    .expect(EXPECT_NODE_WEIGHT),
+            )
+        })
+    }
+}
+
+/// Type used as edge-weights inside `Graph`, describing the transition between two releases.
+///
+/// An edge with risks is conditional, it is only safe to take for clients
+/// which aren't affected by any of the risks.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Edge {
+    pub metadata: HashMap<String, String>,
+    pub risks: Vec<Risk>,
+}
+
+impl Edge {
+    /// Returns true if the edge has declared risks.
+    pub fn is_conditional(&self) -> bool {
+        !self.risks.is_empty()
     }
 }
 
-/// Dummy type used as edge-weights inside `Graph`.
+/// Known risk of a conditional edge.
+#[derive(Debug, Deserialize, Serialize, PartialEq, Eq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Risk {
+    pub name: String,
+    pub url: String,
+    pub message: String,
+    pub matching_rules: Vec<MatchingRule>,
+}
+
+impl Risk {
+    /// Returns true if the risk applies to a client with the given request parameters.
+    ///
+    /// A risk without matching rules applies to all clients.
+    pub fn applies_to(&self, parameters: &HashMap<String, String>) -> bool {
+        self.matching_rules.is_empty()
+            || self
+                .matching_rules
+                .iter()
+                .any(|rule| rule.matches(parameters))
+    }
+}
+
+/// Rule which matches clients by the value of a request parameter.
+#[derive(Debug, Deserialize, Serialize, PartialEq, Eq, Clone)]
+pub struct MatchingRule {
+    pub parameter: String,
+    pub values: Vec<String>,
+}
+
+impl MatchingRule {
+    /// Returns true if the parameter has one of the rule's values.
+    ///
+    /// A missing parameter matches, as the risk can't be ruled out for the client.
+    pub fn matches(&self, parameters: &HashMap<String, String>) -> bool {
+        parameters
+            .get(&self.parameter)
+            .map_or(true, |value| self.values.contains(value))
+    }
+}
+
+/// Dummy type used in the `WouldCycle` error returned by `Graph::add_edge`.
 #[derive(Debug, Clone)]
 pub struct Empty;
 
@@ -172,6 +346,45 @@ pub mod errors {
         pub(crate) from: String,
         pub(crate) to: String,
     }
+
+    /// Release ID is invalid
+    #[derive(Debug, Fail, Eq, PartialEq)]
+    #[fail(
+        display = "release id {:?} is invalid, its release might have been removed",
+        id
+    )]
+    pub struct InvalidReleaseId {
+        pub(crate) id: super::ReleaseId,
+    }
+
+    /// Graph failed the validation
+    #[derive(Debug, Fail, Eq, PartialEq)]
+    #[fail(display = "graph is invalid: {}", findings)]
+    pub struct InvalidGraph {
+        pub(crate) findings: String,
+    }
+
+    /// Graphs couldn't be merged due to conflicts
+    #[derive(Debug, Eq, PartialEq)]
+    pub struct MergeConflicts {
+        pub(crate) conflicts: Vec<super::MergeConflict>,
+    }
+
+    impl MergeConflicts {
+        /// Returns the conflicts which prevented the merge.
+        pub fn conflicts(&self) -> &[super::MergeConflict] {
+            &self.conflicts
+        }
+    }
+
+    impl failure::Fail for MergeConflicts {}
+
+    impl std::fmt::Display for MergeConflicts {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            let conflicts: Vec<String> = self.conflicts.iter().map(ToString::to_string).collect();
+            write!(f, "graphs have merge conflicts: {}", conflicts.join("; "))
+        }
+    }
 }
 
 impl Graph {
@@ -183,9 +396,12 @@ impl Graph {
         R: Into<Release>,
     {
         let release = release.into();
-        match self.find_by_version(&release.version()) {
+        match self.find_by_version(&release.version().to_string()) {
             Some(id) => {
-                let node = self.dag.node_weight_mut(id.0).expect(EXPECT_NODE_WEIGHT);
+                let node = self
+                    .dag
+                    .node_weight_mut(id.index)
+                    .expect(EXPECT_NODE_WEIGHT);
                 if let Release::Concrete(_) = node {
                     bail!(
                         "Concrete release with the same version ({}) already exists",
@@ -195,25 +411,146 @@ impl Graph {
                 *node = release;
                 Ok(id)
             }
-            None => Ok(ReleaseId(self.dag.add_node(release))),
+            None => Ok(self.add_node(release)),
         }
     }
 
+    /// Add a node to the DAG and register its version in the index.
+    fn add_node(&mut self, release: Release) -> ReleaseId {
+        let version = release.version().to_string();
+        let index = self.dag.add_node(release);
+        if index.index() == self.generations.len() {
+            self.generations.push(0);
+        }
+        let id = self.release_id(index);
+        self.versions.insert(version, id.clone());
+        id
+    }
+
+    /// Remove a node from the DAG and keep the version index consistent.
+    ///
+    /// This invalidates all `ReleaseId`s which refer to the removed node.
+    fn remove_node(&mut self, index: daggy::NodeIndex) -> Option<Release> {
+        let removed = self.dag.remove_node(index)?;
+        self.versions.remove(&removed.version().to_string());
+        self.generations[index.index()] += 1;
+
+        Some(removed)
+    }
+
+    /// Update the version index for releases whose version has been changed.
+    ///
+    /// This is required after handing out mutable references to releases.
+    /// `changed` holds the indices of the changed releases and their previous
+    /// versions. If a changed version collides with the version of another
+    /// release, all changed versions are restored and an error is returned.
+    fn reindex_versions(&mut self, changed: Vec<(daggy::NodeIndex, Version)>) -> Fallible<()> {
+        for (_, previous) in &changed {
+            self.versions.remove(&previous.to_string());
+        }
+
+        let mut inserted = Vec::with_capacity(changed.len());
+        let mut collisions = Vec::new();
+        for (index, _) in &changed {
+            let version = self.dag[*index].version().to_string();
+            if self.versions.contains_key(&version) {
+                collisions.push(version);
+            } else {
+                self.versions
+                    .insert(version.clone(), self.release_id(*index));
+                inserted.push(version);
+            }
+        }
+
+        if collisions.is_empty() {
+            return Ok(());
+        }
+
+        for version in inserted {
+            self.versions.remove(&version);
+        }
+        for (index, previous) in changed {
+            self.versions
+                .insert(previous.to_string(), self.release_id(index));
+            *self.dag[index].version_mut() = previous;
+        }
+        bail!(
+            "changed versions collide with existing releases, restored the previous versions: {}",
+            collisions.join(", ")
+        )
+    }
+
+    /// Returns the ReleaseId for the existing node with the given index.
+    fn release_id(&self, index: daggy::NodeIndex) -> ReleaseId {
+        ReleaseId {
+            index,
+            generation: self.generations[index.index()],
+        }
+    }
+
+    /// Returns the node index for the given ReleaseId.
+    ///
+    /// Fails with the `InvalidReleaseId` error if the release has been removed.
+    fn node_index(&self, id: &ReleaseId) -> Fallible<daggy::NodeIndex> {
+        if self.dag.contains_node(id.index)
+            && self.generations.get(id.index.index()) == Some(&id.generation)
+        {
+            Ok(id.index)
+        } else {
+            Err(Error::from(errors::InvalidReleaseId { id: id.clone() }))
+        }
+    }
+
+    /// Returns the position of each node in the serialized representations of the graph.
+    ///
+    /// Node indices can have gaps after releases have been removed, while the
+    /// serialized node lists are contiguous.
+    fn node_positions(&self) -> HashMap<daggy::NodeIndex, usize> {
+        self.dag
+            .node_indices()
+            .enumerate()
+            .map(|(position, index)| (index, position))
+            .collect()
+    }
+
     /// Add a transition (edge) from `source` to `target`.
     ///
     /// Fails with the `WoulcCycle` error if the new edge would lead to a cycle.
     pub fn add_edge(&mut self, from: &ReleaseId, to: &ReleaseId) -> Result<(), Error> {
-        if self.dag.find_edge(from.0, to.0).is_some() {
+        let (from_index, to_index) = (self.node_index(from)?, self.node_index(to)?);
+
+        if self.dag.find_edge(from_index, to_index).is_some() {
             return Err(Error::from(errors::EdgeAlreadyExists {
                 from: self.find_by_releaseid(from)?.version().to_string(),
                 to: self.find_by_releaseid(to)?.version().to_string(),
             }));
         }
 
-        self.dag
-            .add_edge(from.0, to.0, Empty {})
-            .map(|_| ())
-            .map_err(Into::into)
+        if petgraph::algo::has_path_connecting(
+            &self.dag,
+            to_index,
+            from_index,
+            Some(&mut self.cycle_state),
+        ) {
+            return Err(Error::from(WouldCycle(Empty {})));
+        }
+
+        self.dag.add_edge(from_index, to_index, Edge::default());
+        Ok(())
+    }
+
+    /// Add a conditional transition (edge) with the given risks from `source` to `target`.
+    ///
+    /// Fails like `add_edge`.
+    pub fn add_conditional_edge(
+        &mut self,
+        from: &ReleaseId,
+        to: &ReleaseId,
+        risks: Vec<Risk>,
+    ) -> Result<(), Error> {
+        self.add_edge(from, to)?;
+        *self.get_edge_risks_as_ref_mut(from, to)? = risks;
+        Ok(())
     }
 
     /// Add edges for all given key/value pairs of releases.
@@ -225,22 +562,20 @@ impl Graph {
 
     /// Returns a Some(ReleaseId) if the version exists in the graph, None otherwise.
     pub fn find_by_version(&self, version: &str) -> Option<ReleaseId> {
-        self.dag
-            .node_references()
-            .find(|nr| nr.weight().version() == version)
-            .map(|nr| ReleaseId(nr.id()))
+        self.versions.get(version).cloned()
     }
 
     /// Returns a Release for the given &ReleaseId
     pub fn find_by_releaseid(&self, id: &ReleaseId) -> Fallible<&Release> {
-        self.dag
-            .node_weight(id.0)
-            .ok_or_else(move || format_err!("could not find Release with id: {:?}", id))
+        let index = self.node_index(id)?;
+        Ok(self.dag.node_weight(index).expect(EXPECT_NODE_WEIGHT))
     }
 
     /// Removes the directed edge between the given releases.
     pub fn remove_edge(&mut self, from: &ReleaseId, to: &ReleaseId) -> Result<(), Error> {
-        if let Some(edge) = self.dag.find_edge(from.0, to.0) {
+        let (from_index, to_index) = (self.node_index(from)?, self.node_index(to)?);
+
+        if let Some(edge) = self.dag.find_edge(from_index, to_index) {
             self.dag
                 .remove_edge(edge)
                 .map(|_| ())
@@ -283,24 +618,39 @@ impl Graph {
     /// filter_fn returns true.
     ///
     /// filter_fn is able to mutate the release as it receives a mutable borrow.
-    pub fn find_by_fn_mut<F>(&mut self, mut filter_fn: F) -> Vec<(ReleaseId, String)>
+    /// Fails if a changed version collides with the version of another release,
+    /// see `reindex_versions`.
+    pub fn find_by_fn_mut<F>(&mut self, mut filter_fn: F) -> Fallible<Vec<(ReleaseId, String)>>
     where
         F: FnMut(&mut Release) -> bool,
     {
+        let indices: Vec<daggy::NodeIndex> = self.dag.node_indices().collect();
+
+        let mut found = Vec::new();
+        let mut changed = Vec::new();
+        for index in indices {
+            let release = self.dag.node_weight_mut(index).expect(EXPECT_NODE_WEIGHT);
+            let previous = release.version().clone();
+            let matched = filter_fn(release);
+            if !versions_eq(&previous, release.version()) {
+                changed.push((index, previous));
+            }
+            if matched {
+                let version = release.version().to_string();
+                found.push((self.release_id(index), version));
+            }
+        }
+
+        self.reindex_versions(changed)?;
+
+        Ok(found)
+    }
+
+    /// Iterates over all releases and their ReleaseIds.
+    pub fn iter_releases(&self) -> impl Iterator<Item = (ReleaseId, &Release)> + '_ {
         self.dag
-            .node_weights_mut()
-            .enumerate()
-            .filter_map(|(i, nw)| {
-                if filter_fn(nw) {
-                    Some((
-                        ReleaseId(daggy::NodeIndex::from(i as u32)),
-                        nw.version().to_string(),
-                    ))
-                } else {
-                    None
-                }
-            })
-            .collect()
+            .node_references()
+            .map(move |(index, release)| (self.release_id(index), release))
     }
 
     /// Returns tuples of ReleaseId and its version String for releases which
@@ -316,7 +666,7 @@ impl Graph {
                 }
                 false
             })
-            .map(|nr| (ReleaseId(nr.id()), nr.1.version().to_owned()))
+            .map(|nr| (self.release_id(nr.id()), nr.1.version().to_string()))
             .collect()
     }
 
@@ -329,8 +679,8 @@ impl Graph {
                 if let Release::Concrete(release) = nr.weight() {
                     if let Some(value) = release.metadata.get(key) {
                         return Some((
-                            ReleaseId(nr.id()),
-                            release.version.to_owned(),
+                            self.release_id(nr.id()),
+                            release.version.to_string(),
                             value.to_owned(),
                         ));
                     }
@@ -345,30 +695,106 @@ impl Graph {
         &mut self,
         release_id: &ReleaseId,
     ) -> Result<&mut HashMap<String, String>, Error> {
-        match self.dag.node_weight_mut(release_id.0) {
+        let index = self.node_index(release_id)?;
+        match self.dag.node_weight_mut(index) {
             Some(Release::Concrete(release)) => Ok(&mut release.metadata),
             _ => bail!("could not get metadata reference"),
         }
     }
 
+    /// Returns a reference to the metadata of the edge between the given releases.
+    pub fn get_edge_metadata_as_ref(
+        &self,
+        from: &ReleaseId,
+        to: &ReleaseId,
+    ) -> Result<&HashMap<String, String>, Error> {
+        let edge = self.find_edge(from, to)?;
+        Ok(&self.dag[edge].metadata)
+    }
+
+    /// Returns a mutable reference to the metadata of the edge between the given releases.
+    pub fn get_edge_metadata_as_ref_mut(
+        &mut self,
+        from: &ReleaseId,
+        to: &ReleaseId,
+    ) -> Result<&mut HashMap<String, String>, Error> {
+        let edge = self.find_edge(from, to)?;
+        Ok(&mut self.dag[edge].metadata)
+    }
+
+    /// Returns a reference to the risks of the edge between the given releases.
+    pub fn get_edge_risks_as_ref(
+        &self,
+        from: &ReleaseId,
+        to: &ReleaseId,
+    ) -> Result<&[Risk], Error> {
+        let edge = self.find_edge(from, to)?;
+        Ok(&self.dag[edge].risks)
+    }
+
+    /// Returns a mutable reference to the risks of the edge between the given releases.
+    ///
+    /// Clearing the risks makes the edge unconditional.
+    pub fn get_edge_risks_as_ref_mut(
+        &mut self,
+        from: &ReleaseId,
+        to: &ReleaseId,
+    ) -> Result<&mut Vec<Risk>, Error> {
+        let edge = self.find_edge(from, to)?;
+        Ok(&mut self.dag[edge].risks)
+    }
+
+    /// Returns tuples of the source and target ReleaseIds and the risks of all conditional edges.
+    pub fn conditional_edges(&self) -> Vec<(ReleaseId, ReleaseId, &[Risk])> {
+        self.dag
+            .edge_references()
+            .filter(|edge| edge.weight().is_conditional())
+            .map(|edge| {
+                (
+                    self.release_id(edge.source()),
+                    self.release_id(edge.target()),
+                    edge.weight().risks.as_slice(),
+                )
+            })
+            .collect()
+    }
+
+    /// Returns the index of the edge between the given releases.
+    ///
+    /// Fails with the `EdgeDoesntExist` error if there is no such edge.
+    fn find_edge(&self, from: &ReleaseId, to: &ReleaseId) -> Fallible<daggy::EdgeIndex> {
+        let (from_index, to_index) = (self.node_index(from)?, self.node_index(to)?);
+
+        self.dag.find_edge(from_index, to_index).ok_or_else(|| {
+            Error::from(errors::EdgeDoesntExist {
+                from: self.dag[from_index].version().to_string(),
+                to: self.dag[to_index].version().to_string(),
+            })
+        })
+    }
+
     /// Returns `NextReleases` for the given release.
     ///
     /// `NextReleases` can be used to iterate over all direct children of the given release.
-    pub fn next_releases(&self, source: &ReleaseId) -> NextReleases {
-        NextReleases {
-            children: self.dag.children(source.0),
-            dag: &self.dag,
-        }
+    pub fn next_releases(&self, source: &ReleaseId) -> Fallible<NextReleases> {
+        Ok(NextReleases {
+            edges: self
+                .dag
+                .edges_directed(self.node_index(source)?, Direction::Outgoing),
+            graph: self,
+        })
     }
 
     /// Returns `PreviousReleases` for the given release.
     ///
     /// `PreviousReleases` can be used to iterate over all direct parents of the given release.
-    pub fn previous_releases(&self, source: &ReleaseId) -> PreviousReleases {
-        PreviousReleases {
-            parents: self.dag.parents(source.0),
-            dag: &self.dag,
-        }
+    pub fn previous_releases(&self, source: &ReleaseId) -> Fallible<PreviousReleases> {
+        Ok(PreviousReleases {
+            edges: self
+                .dag
+                .edges_directed(self.node_index(source)?, Direction::Incoming),
+            graph: self,
+        })
     }
 
     /// Return the number of releases (nodes) in the graph.
@@ -376,26 +802,19 @@ impl Graph {
         self.dag.node_count() as u64
     }
 
-    /// Removes the nodes with the given ReleaseIds and returns the number of
+    /// Removes the releases with the given ReleaseIds and returns the number of
     /// removed releases.
     ///
-    /// The ReleaseIds are sorted and removed in reverse order.
-    /// This is required because `daggy::Dag::remove_node()` shifts the NodeIndices
-    /// and therefore invalidates all the ones which are higher than the removed one.
-    pub fn remove_releases(&mut self, mut to_remove: Vec<ReleaseId>) -> usize {
-        to_remove.sort_by(|a, b| {
-            use std::cmp::Ordering::*;
-
-            if a.0 < b.0 {
-                Less
-            } else if a.0 == b.0 {
-                Equal
-            } else {
-                Greater
-            }
-        });
-
-        self.remove_nodes(to_remove.into_iter().map(|ri| ri.0).collect())
+    /// ReleaseIds which are invalid, e.g. because they have been removed before, are skipped.
+    /// All other ReleaseIds remain valid.
+    pub fn remove_releases(&mut self, to_remove: Vec<ReleaseId>) -> usize {
+        to_remove
+            .into_iter()
+            .filter(|id| match self.node_index(id) {
+                Ok(index) => self.remove_node(index).is_some(),
+                Err(_) => false,
+            })
+            .count()
     }
 
     /// Removes the nodes with the given NodeIndex and returns the number of
@@ -403,8 +822,7 @@ impl Graph {
     pub fn remove_nodes(&mut self, to_remove: Vec<daggy::NodeIndex>) -> usize {
         to_remove
             .into_iter()
-            .rev()
-            .filter(|ni| self.dag.remove_node(*ni).is_some())
+            .filter(|ni| self.remove_node(*ni).is_some())
             .count()
     }
 
@@ -424,102 +842,404 @@ impl Graph {
             })
             .collect();
 
-        to_remove
-            .iter()
-            .filter(|ni| self.dag.remove_node(**ni).is_some())
-            .count()
+        self.remove_nodes(to_remove)
     }
 
     /// Iterates over all releases mutably
     ///
     /// f is able to mutate the release as it receives a mutable borrow.
+    /// Fails like `find_by_fn_mut` if a changed version collides.
     pub fn iter_releases_mut<F>(&mut self, mut f: F) -> Result<(), Error>
     where
         F: FnMut(&mut Release) -> Result<(), Error>,
     {
-        self.dag
-            .node_weights_mut()
-            .try_for_each(|mut nw| f(&mut nw))
+        let indices: Vec<daggy::NodeIndex> = self.dag.node_indices().collect();
+
+        let mut changed = Vec::new();
+        let mut result = Ok(());
+        for index in indices {
+            let release = self.dag.node_weight_mut(index).expect(EXPECT_NODE_WEIGHT);
+            let previous = release.version().clone();
+            result = f(release);
+            if !versions_eq(&previous, release.version()) {
+                changed.push((index, previous));
+            }
+            if result.is_err() {
+                break;
+            }
+        }
+
+        let reindexed = self.reindex_versions(changed);
+        result.and(reindexed)
+    }
+
+    /// Serializes the graph to its protobuf representation, see `plugins::interface::Graph`.
+    pub fn to_protobuf(&self) -> Fallible<Vec<u8>> {
+        use protobuf::Message;
+
+        Ok(plugins::interface::Graph::try_from(self)?.write_to_bytes()?)
     }
 }
 
-impl<'a> Deserialize<'a> for Graph {
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+/// Limits for deserializing untrusted graphs, see `Graph::from_json_reader`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GraphLimits {
+    /// Maximum number of bytes to read.
+    pub max_bytes: Option<u64>,
+    /// Maximum number of nodes.
+    pub max_nodes: Option<usize>,
+    /// Maximum number of edges, conditional ones included.
+    pub max_edges: Option<usize>,
+}
+
+impl Graph {
+    /// Deserializes a graph from JSON read from the given reader.
+    ///
+    /// Nodes are added to the graph as they are read, and reading fails as
+    /// soon as the input exceeds one of the given limits. The reader is
+    /// buffered internally, so it doesn't need to be wrapped in a `BufReader`.
+    pub fn from_json_reader<R>(reader: R, limits: &GraphLimits) -> Fallible<Self>
     where
-        D: Deserializer<'a>,
+        R: std::io::Read,
     {
-        #[derive(Deserialize)]
-        #[serde(field_identifier, rename_all = "lowercase")]
-        enum Field {
-            Edges,
-            Nodes,
+        let reader = LimitedReader {
+            inner: std::io::BufReader::new(reader),
+            remaining: limits.max_bytes,
+        };
+        let mut deserializer = serde_json::Deserializer::from_reader(reader);
+        let graph = deserializer.deserialize_struct(
+            "Graph",
+            GRAPH_FIELDS,
+            GraphVisitor {
+                max_nodes: limits.max_nodes,
+                max_edges: limits.max_edges,
+            },
+        )?;
+        deserializer.end()?;
+
+        Ok(graph)
+    }
+}
+
+/// Reader which fails once more than the given number of bytes has been read.
+struct LimitedReader<R> {
+    inner: R,
+    remaining: Option<u64>,
+}
+
+impl<R: std::io::Read> std::io::Read for LimitedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        if let Some(remaining) = self.remaining {
+            if read as u64 > remaining {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "graph exceeds the maximum size",
+                ));
+            }
+            self.remaining = Some(remaining - read as u64);
         }
+        Ok(read)
+    }
+}
 
-        struct GraphVisitor;
+const GRAPH_FIELDS: &[&str] = &["nodes", "edges", "edgeMetadata", "conditionalEdges"];
+
+#[derive(Deserialize)]
+#[serde(field_identifier, rename_all = "lowercase")]
+enum GraphField {
+    Edges,
+    Nodes,
+    #[serde(rename = "edgeMetadata")]
+    EdgeMetadata,
+    #[serde(rename = "conditionalEdges")]
+    ConditionalEdges,
+}
 
-        impl<'de> Visitor<'de> for GraphVisitor {
-            type Value = Graph;
+#[derive(Deserialize)]
+struct EdgeMetadataIn {
+    from: daggy::NodeIndex,
+    to: daggy::NodeIndex,
+    metadata: HashMap<String, String>,
+}
 
-            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-                formatter.write_str("struct Graph")
-            }
+#[derive(Deserialize)]
+struct ConditionalEdgeIn {
+    from: daggy::NodeIndex,
+    to: daggy::NodeIndex,
+    risks: Vec<Risk>,
+}
 
-            fn visit_map<V>(self, mut map: V) -> Result<Graph, V::Error>
-            where
-                V: MapAccess<'de>,
-            {
-                let mut edges: Option<Vec<(daggy::NodeIndex, daggy::NodeIndex)>> = None;
-                let mut nodes: Option<Vec<Release>> = None;
-                while let Some(key) = map.next_key()? {
-                    match key {
-                        Field::Edges => {
-                            if edges.is_some() {
-                                return Err(de::Error::duplicate_field("edges"));
-                            }
-                            edges = Some(map.next_value()?);
-                        }
-                        Field::Nodes => {
-                            if nodes.is_some() {
-                                return Err(de::Error::duplicate_field("nodes"));
-                            }
-                            nodes = Some(map.next_value()?);
-                        }
+struct GraphVisitor {
+    max_nodes: Option<usize>,
+    max_edges: Option<usize>,
+}
+
+impl<'de> Visitor<'de> for GraphVisitor {
+    type Value = Graph;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("struct Graph")
+    }
+
+    fn visit_map<V>(self, mut map: V) -> Result<Graph, V::Error>
+    where
+        V: MapAccess<'de>,
+    {
+        // Nodes are added to the graph while they are deserialized. The
+        // edges refer to them by position, so they are only added once all
+        // nodes are known.
+        let mut graph = Graph::default();
+        let mut has_nodes = false;
+        // Plain and conditional edges share the same limit.
+        let mut remaining_edges = self.max_edges;
+        let mut remaining_edge_metadata = self.max_edges;
+        let mut edges: Option<Vec<(daggy::NodeIndex, daggy::NodeIndex)>> = None;
+        let mut edge_metadata: Option<Vec<EdgeMetadataIn>> = None;
+        let mut conditional_edges: Option<Vec<ConditionalEdgeIn>> = None;
+        while let Some(key) = map.next_key()? {
+            match key {
+                GraphField::Edges => {
+                    if edges.is_some() {
+                        return Err(de::Error::duplicate_field("edges"));
                     }
+                    edges = Some(map.next_value_seed(BoundedSeqSeed {
+                        remaining: &mut remaining_edges,
+                        max: self.max_edges,
+                        what: "edges",
+                        marker: std::marker::PhantomData,
+                    })?);
                 }
-                let edges = edges.ok_or_else(|| de::Error::missing_field("edges"))?;
-                let nodes = nodes.ok_or_else(|| de::Error::missing_field("nodes"))?;
-                let mut graph = Graph {
-                    dag: Dag::with_capacity(nodes.len(), edges.len()),
-                };
-                let mut versions = collections::HashSet::with_capacity(nodes.len());
-                for node in nodes {
-                    // Validate version string is non-empty.
-                    if node.version().is_empty() {
-                        return Err(de::Error::invalid_value(
-                            de::Unexpected::Str(node.version()),
-                            &"a non-empty string version",
-                        ));
+                GraphField::Nodes => {
+                    if has_nodes {
+                        return Err(de::Error::duplicate_field("nodes"));
                     }
-                    // Validate version string is unique in "nodes" set.
-                    if !versions.insert(node.version().to_string()) {
-                        return Err(de::Error::invalid_value(
-                            de::Unexpected::Str(node.version()),
-                            &"a unique string version",
-                        ));
+                    map.next_value_seed(NodesSeed {
+                        graph: &mut graph,
+                        max_nodes: self.max_nodes,
+                    })?;
+                    has_nodes = true;
+                }
+                GraphField::EdgeMetadata => {
+                    if edge_metadata.is_some() {
+                        return Err(de::Error::duplicate_field("edgeMetadata"));
                     }
-                    graph.dag.add_node(node);
+                    edge_metadata = Some(map.next_value_seed(BoundedSeqSeed {
+                        remaining: &mut remaining_edge_metadata,
+                        max: self.max_edges,
+                        what: "edge metadata entries",
+                        marker: std::marker::PhantomData,
+                    })?);
+                }
+                GraphField::ConditionalEdges => {
+                    if conditional_edges.is_some() {
+                        return Err(de::Error::duplicate_field("conditionalEdges"));
+                    }
+                    conditional_edges = Some(map.next_value_seed(BoundedSeqSeed {
+                        remaining: &mut remaining_edges,
+                        max: self.max_edges,
+                        what: "edges",
+                        marker: std::marker::PhantomData,
+                    })?);
                 }
-                graph
-                    .dag
-                    .add_edges(edges.into_iter().map(|(s, t)| (s, t, Empty {})))
-                    .map_err(|_| {
-                        de::Error::invalid_value(serde::de::Unexpected::StructVariant, &self)
-                    })?;
-                Ok(graph)
             }
         }
+        let edges = edges.ok_or_else(|| de::Error::missing_field("edges"))?;
+        if !has_nodes {
+            return Err(de::Error::missing_field("nodes"));
+        }
+        for (source, target) in edges {
+            // Validate both ends of the edge refer to existing nodes.
+            if !graph.dag.contains_node(source) || !graph.dag.contains_node(target) {
+                return Err(de::Error::invalid_value(
+                    serde::de::Unexpected::StructVariant,
+                    &self,
+                ));
+            }
+            // Validate the edge isn't declared twice.
+            if graph.dag.find_edge(source, target).is_some() {
+                return Err(de::Error::custom(format!(
+                    "found duplicate edge [{}, {}]",
+                    source.index(),
+                    target.index()
+                )));
+            }
+            graph.dag.add_edge(source, target, Edge::default());
+        }
+        for ConditionalEdgeIn { from, to, risks } in conditional_edges.unwrap_or_default() {
+            // Validate both ends of the edge refer to existing nodes.
+            if !graph.dag.contains_node(from) || !graph.dag.contains_node(to) {
+                return Err(de::Error::invalid_value(
+                    serde::de::Unexpected::StructVariant,
+                    &self,
+                ));
+            }
+            // Validate the conditional edge declares risks, it would be a plain edge otherwise.
+            if risks.is_empty() {
+                return Err(de::Error::custom(format!(
+                    "found conditional edge [{}, {}] without risks",
+                    from.index(),
+                    to.index()
+                )));
+            }
+            // Validate the conditional edge doesn't duplicate another edge.
+            if graph.dag.find_edge(from, to).is_some() {
+                return Err(de::Error::custom(format!(
+                    "found duplicate conditional edge [{}, {}]",
+                    from.index(),
+                    to.index()
+                )));
+            }
+            graph.dag.add_edge(
+                from,
+                to,
+                Edge {
+                    risks,
+                    ..Default::default()
+                },
+            );
+        }
+        // Validate the edges don't introduce any cycle.
+        if petgraph::algo::is_cyclic_directed(&graph.dag) {
+            return Err(de::Error::invalid_value(
+                serde::de::Unexpected::StructVariant,
+                &self,
+            ));
+        }
+        for EdgeMetadataIn { from, to, metadata } in edge_metadata.unwrap_or_default() {
+            // Validate the metadata belongs to an existing edge.
+            let edge = graph.dag.find_edge(from, to).ok_or_else(|| {
+                de::Error::custom(format!(
+                    "found metadata for non-existent edge [{}, {}]",
+                    from.index(),
+                    to.index()
+                ))
+            })?;
+            graph.dag[edge].metadata = metadata;
+        }
+        Ok(graph)
+    }
+}
 
-        deserializer.deserialize_struct("Graph", &["nodes", "edges"], GraphVisitor)
+/// Seed which collects a sequence, failing as soon as it exceeds the remaining
+/// number of elements.
+struct BoundedSeqSeed<'a, T> {
+    remaining: &'a mut Option<usize>,
+    max: Option<usize>,
+    what: &'static str,
+    marker: std::marker::PhantomData<T>,
+}
+
+impl<'a, 'de, T> DeserializeSeed<'de> for BoundedSeqSeed<'a, T>
+where
+    T: Deserialize<'de>,
+{
+    type Value = Vec<T>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Vec<T>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'a, 'de, T> Visitor<'de> for BoundedSeqSeed<'a, T>
+where
+    T: Deserialize<'de>,
+{
+    type Value = Vec<T>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "a sequence of {}", self.what)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Vec<T>, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut elements = Vec::new();
+        while let Some(element) = seq.next_element()? {
+            if let Some(remaining) = self.remaining {
+                if *remaining == 0 {
+                    return Err(de::Error::custom(format!(
+                        "graph exceeds the maximum of {} {}",
+                        self.max.unwrap_or_default(),
+                        self.what
+                    )));
+                }
+                *remaining -= 1;
+            }
+            elements.push(element);
+        }
+        Ok(elements)
+    }
+}
+
+/// Seed which adds the deserialized nodes to the graph one by one.
+struct NodesSeed<'a> {
+    graph: &'a mut Graph,
+    max_nodes: Option<usize>,
+}
+
+impl<'a, 'de> DeserializeSeed<'de> for NodesSeed<'a> {
+    type Value = ();
+
+    fn deserialize<D>(self, deserializer: D) -> Result<(), D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'a, 'de> Visitor<'de> for NodesSeed<'a> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a sequence of releases")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<(), A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        while let Some(node) = seq.next_element::<Release>()? {
+            if let Some(max_nodes) = self.max_nodes {
+                if self.graph.dag.node_count() >= max_nodes {
+                    return Err(de::Error::custom(format!(
+                        "graph exceeds the maximum of {} nodes",
+                        max_nodes
+                    )));
+                }
+            }
+            // Validate version string is unique in "nodes" set.
+            let version = node.version().to_string();
+            if self.graph.versions.contains_key(&version) {
+                return Err(de::Error::invalid_value(
+                    de::Unexpected::Str(&version),
+                    &"a unique string version",
+                ));
+            }
+            self.graph.add_node(node);
+        }
+        Ok(())
+    }
+}
+
+impl<'a> Deserialize<'a> for Graph {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'a>,
+    {
+        deserializer.deserialize_struct(
+            "Graph",
+            GRAPH_FIELDS,
+            GraphVisitor {
+                max_nodes: None,
+                max_edges: None,
+            },
+        )
     }
 }
 
@@ -528,15 +1248,72 @@ impl Serialize for Graph {
     where
         S: Serializer,
     {
-        struct Edges<'a>(&'a [daggy::petgraph::graph::Edge<Empty>]);
-        struct Nodes<'a>(&'a [daggy::petgraph::graph::Node<Release>]);
+        struct Edges<'a>(&'a Dag, &'a HashMap<daggy::NodeIndex, usize>);
+        struct EdgesMetadata<'a>(&'a Dag, &'a HashMap<daggy::NodeIndex, usize>);
+        struct ConditionalEdges<'a>(&'a Dag, &'a HashMap<daggy::NodeIndex, usize>);
+        struct Nodes<'a>(&'a Dag);
+
+        #[derive(Serialize)]
+        struct EdgeMetadata<'a> {
+            from: usize,
+            to: usize,
+            metadata: &'a HashMap<String, String>,
+        }
+
+        #[derive(Serialize)]
+        struct ConditionalEdge<'a> {
+            from: usize,
+            to: usize,
+            risks: &'a [Risk],
+        }
 
         impl<'a> Serialize for Edges<'a> {
             fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
             where
                 S: Serializer,
             {
-                serializer.collect_seq(self.0.iter().map(|edge| (edge.source(), edge.target())))
+                serializer.collect_seq(
+                    self.0
+                        .edge_references()
+                        .filter(|edge| !edge.weight().is_conditional())
+                        .map(|edge| (self.1[&edge.source()], self.1[&edge.target()])),
+                )
+            }
+        }
+
+        impl<'a> Serialize for ConditionalEdges<'a> {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: Serializer,
+            {
+                serializer.collect_seq(
+                    self.0
+                        .edge_references()
+                        .filter(|edge| edge.weight().is_conditional())
+                        .map(|edge| ConditionalEdge {
+                            from: self.1[&edge.source()],
+                            to: self.1[&edge.target()],
+                            risks: &edge.weight().risks,
+                        }),
+                )
+            }
+        }
+
+        impl<'a> Serialize for EdgesMetadata<'a> {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: Serializer,
+            {
+                serializer.collect_seq(
+                    self.0
+                        .edge_references()
+                        .filter(|edge| !edge.weight().metadata.is_empty())
+                        .map(|edge| EdgeMetadata {
+                            from: self.1[&edge.source()],
+                            to: self.1[&edge.target()],
+                            metadata: &edge.weight().metadata,
+                        }),
+                )
             }
         }
 
@@ -545,86 +1322,71 @@ impl Serialize for Graph {
             where
                 S: Serializer,
             {
-                serializer.collect_seq(self.0.iter().map(|node| &node.weight))
+                serializer.collect_seq(self.0.node_references().map(|(_, release)| release))
             }
         }
 
-        let mut state = serializer.serialize_struct("Graph", 2)?;
-        state.serialize_field("nodes", &Nodes(&self.dag.raw_nodes()))?;
-        state.serialize_field("edges", &Edges(&self.dag.raw_edges()))?;
+        // Edge metadata and conditional edges are only serialized if present,
+        // to keep the output identical for clients which only know about nodes
+        // and edges. Conditional edges are kept out of "edges", so that these
+        // clients don't take them unconditionally.
+        let has_edge_metadata = self
+            .dag
+            .edge_references()
+            .any(|edge| !edge.weight().metadata.is_empty());
+        let has_conditional_edges = self
+            .dag
+            .edge_references()
+            .any(|edge| edge.weight().is_conditional());
+
+        let positions = self.node_positions();
+        let mut state = serializer.serialize_struct(
+            "Graph",
+            2 + has_edge_metadata as usize + has_conditional_edges as usize,
+        )?;
+        state.serialize_field("nodes", &Nodes(&self.dag))?;
+        state.serialize_field("edges", &Edges(&self.dag, &positions))?;
+        if has_edge_metadata {
+            state.serialize_field("edgeMetadata", &EdgesMetadata(&self.dag, &positions))?;
+        }
+        if has_conditional_edges {
+            state.serialize_field("conditionalEdges", &ConditionalEdges(&self.dag, &positions))?;
+        }
         state.end()
     }
 }
 
 impl PartialEq for Graph {
     fn eq(&self, other: &Graph) -> bool {
-        use daggy::petgraph::visit::IntoNeighbors;
-
-        let asc_order_release_by_version = {
-            use std::cmp::Ordering::{self, *};
-
-            |a: &&Release, b: &&Release| -> Ordering {
-                if a.version() < b.version() {
-                    Less
-                } else if a.version() == b.version() {
-                    Equal
-                } else {
-                    Greater
-                }
-            }
-        };
+        // Returns the children of the given node with their connecting edges, ordered by version.
+        fn children(graph: &Graph, node_index: daggy::NodeIndex) -> Vec<(&Release, &Edge)> {
+            let mut children: Vec<(&Release, &Edge)> = graph
+                .dag
+                .edges(node_index)
+                .map(|edge| (&graph.dag[edge.target()], edge.weight()))
+                .collect();
+            children.sort_by(|(a, _), (b, _)| {
+                (a.version(), &a.version().build).cmp(&(b.version(), &b.version().build))
+            });
+            children
+        }
 
         // Look through all nodes in self
         self.dag.node_references().all(|node_ref| {
-            let dag_other = &other.dag;
             let node_index = node_ref.0;
             let release = node_ref.1;
 
             // For each node in self, look through all nodes in other and find a match
-            dag_other
+            other
+                .dag
                 .node_references()
                 .filter(|node_ref_other| {
                     let node_index_other = node_ref_other.0;
                     let release_other = node_ref_other.1;
 
-                    // Ensure the set of neighbors of release and release_other are identical
-                    let compare_neighbors = || {
-                        let (neighbors_count, neighbors_other_count) = (
-                            self.dag.neighbors(node_index).count(),
-                            dag_other.neighbors(node_index_other).count(),
-                        );
-
-                        if neighbors_count != neighbors_other_count {
-                            return false;
-                        }
-
-                        let mut neighbors = self
-                            .dag
-                            .neighbors(node_index)
-                            .zip(dag_other.neighbors(node_index_other))
-                            .fold(
-                                Vec::with_capacity(neighbors_count * 2),
-                                |mut neighbors, (neighbor, neighbor_other)| {
-                                    neighbors.push(
-                                        self.dag.node_weight(neighbor).expect(EXPECT_NODE_WEIGHT),
-                                    );
-                                    neighbors.push(
-                                        dag_other
-                                            .node_weight(neighbor_other)
-                                            .expect(EXPECT_NODE_WEIGHT),
-                                    );
-                                    neighbors
-                                },
-                            );
-
-                        // dedup() requires consecutive sorting
-                        neighbors.sort_by(asc_order_release_by_version);
-                        neighbors.dedup();
-
-                        neighbors.len() == neighbors_count
-                    };
-
-                    release == release_other && compare_neighbors()
+                    // Ensure the children of release and release_other and their edges are identical
+                    release == release_other
+                        && children(self, node_index) == children(other, node_index_other)
                 })
                 // Ensure each node in self has exactly one matching node in including its neighbors
                 .count()
@@ -635,47 +1397,93 @@ impl PartialEq for Graph {
 
 impl Eq for Graph {}
 
-impl From<plugins::interface::Graph> for Graph {
-    fn from(mut graph: plugins::interface::Graph) -> Self {
+/// Try to convert from the protobuf representation of a graph
+///
+/// This can fail because the protobuf representation doesn't ensure valid
+/// versions and edges.
+impl TryFrom<plugins::interface::Graph> for Graph {
+    type Error = Error;
+
+    fn try_from(mut graph: plugins::interface::Graph) -> Fallible<Self> {
         let mut graph_converted = Graph::default();
 
         // Convert nodes
+        let mut ids: Vec<ReleaseId> = Vec::with_capacity(graph.get_nodes().len());
         for node in graph.take_nodes().into_iter() {
-            graph_converted
-                .dag
-                .add_node(Release::Concrete(ConcreteRelease {
-                    version: node.version,
+            let version = Version::parse(&node.version)
+                .map_err(|e| format_err!("invalid version '{}': {}", node.version, e))?;
+            ids.push(
+                graph_converted.add_release(Release::Concrete(ConcreteRelease {
+                    version,
                     payload: node.payload,
                     metadata: node.metadata,
-                }));
+                }))?,
+            );
         }
 
-        // Convert edges
-        for edge in graph.take_edges().into_iter() {
-            graph_converted
-                .dag
-                .add_edge(
-                    daggy::NodeIndex::from(edge.from as u32),
-                    daggy::NodeIndex::from(edge.to as u32),
-                    Empty {},
-                )
-                .expect("add_edge");
+        // Convert edges, the conditional ones are kept separately so that
+        // consumers which don't know about risks never take them.
+        let id = |position: u64| {
+            ids.get(position as usize)
+                .ok_or_else(|| format_err!("edge refers to missing node {}", position))
+        };
+        for mut edge in graph.take_edges().into_iter() {
+            if !edge.get_risks().is_empty() {
+                bail!(
+                    "found edge [{}, {}] with risks outside of the conditional edges",
+                    edge.from,
+                    edge.to
+                );
+            }
+            let (from, to) = (id(edge.from)?, id(edge.to)?);
+            graph_converted.add_edge(from, to)?;
+            *graph_converted.get_edge_metadata_as_ref_mut(from, to)? = edge.take_metadata();
+        }
+        for mut edge in graph.take_conditional_edges().into_iter() {
+            if edge.get_risks().is_empty() {
+                bail!(
+                    "found conditional edge [{}, {}] without risks",
+                    edge.from,
+                    edge.to
+                );
+            }
+            let (from, to) = (id(edge.from)?, id(edge.to)?);
+            graph_converted.add_conditional_edge(
+                from,
+                to,
+                edge.take_risks().into_iter().map(Risk::from).collect(),
+            )?;
+            *graph_converted.get_edge_metadata_as_ref_mut(from, to)? = edge.take_metadata();
         }
 
-        graph_converted
+        Ok(graph_converted)
     }
 }
 
-impl From<Graph> for plugins::interface::Graph {
-    fn from(graph: Graph) -> Self {
+/// Try to convert to the protobuf representation of a graph
+///
+/// This fails for graphs with abstract releases, which have no protobuf
+/// representation.
+impl TryFrom<Graph> for plugins::interface::Graph {
+    type Error = Error;
+
+    fn try_from(graph: Graph) -> Fallible<Self> {
+        Self::try_from(&graph)
+    }
+}
+
+impl TryFrom<&Graph> for plugins::interface::Graph {
+    type Error = Error;
+
+    fn try_from(graph: &Graph) -> Fallible<Self> {
         use crate::Release::{Abstract, Concrete};
-        use daggy::petgraph::visit::IntoNeighborsDirected;
-        use daggy::petgraph::Direction;
 
+        let positions = graph.node_positions();
         let mut nodes_converted: Vec<plugins::interface::Graph_Node> =
             std::vec::Vec::with_capacity(graph.dag.node_count());
         let mut edges_converted: Vec<plugins::interface::Graph_Edge> =
             std::vec::Vec::with_capacity(graph.dag.edge_count());
+        let mut conditional_edges_converted: Vec<plugins::interface::Graph_Edge> = vec![];
 
         for node_reference in graph.dag.node_references() {
             let node_index = node_reference.0;
@@ -686,57 +1494,112 @@ impl From<Graph> for plugins::interface::Graph {
             match release {
                 Concrete(concrete_release) => {
                     // TODO(steveeJ): avoid cloning all release content
-                    node_converted.set_version(concrete_release.version.clone());
+                    node_converted.set_version(concrete_release.version.to_string());
                     node_converted.set_metadata(concrete_release.metadata.clone());
                     node_converted.set_payload(concrete_release.payload.clone());
                 }
-                Abstract(_) => panic!("found Abstract release type"),
+                Abstract(abstract_release) => bail!(
+                    "found abstract release {}, which can't be converted to protobuf",
+                    abstract_release.version
+                ),
             }
             nodes_converted.push(node_converted);
 
-            // find neighbors and push edges
-            for neighbor in graph
-                .dag
-                .neighbors_directed(node_index, Direction::Outgoing)
-            {
+            // find outgoing edges and push them
+            for edge in graph.dag.edges_directed(node_index, Direction::Outgoing) {
                 let mut edge_converted = plugins::interface::Graph_Edge::new();
-                edge_converted.set_from(node_index.index() as u64);
-                edge_converted.set_to(neighbor.index() as u64);
-                edges_converted.push(edge_converted);
+                edge_converted.set_from(positions[&node_index] as u64);
+                edge_converted.set_to(positions[&edge.target()] as u64);
+                edge_converted.set_metadata(edge.weight().metadata.clone());
+                edge_converted.set_risks(
+                    edge.weight()
+                        .risks
+                        .iter()
+                        .cloned()
+                        .map(plugins::interface::Graph_Risk::from)
+                        .collect(),
+                );
+                if edge.weight().is_conditional() {
+                    conditional_edges_converted.push(edge_converted);
+                } else {
+                    edges_converted.push(edge_converted);
+                }
             }
         }
 
         let mut graph_converted = plugins::interface::Graph::new();
         graph_converted.set_nodes(nodes_converted.into());
         graph_converted.set_edges(edges_converted.into());
+        graph_converted.set_conditional_edges(conditional_edges_converted.into());
+
+        Ok(graph_converted)
+    }
+}
+
+impl From<plugins::interface::Graph_Risk> for Risk {
+    fn from(mut risk: plugins::interface::Graph_Risk) -> Self {
+        Risk {
+            name: risk.take_name(),
+            url: risk.take_url(),
+            message: risk.take_message(),
+            matching_rules: risk
+                .take_matching_rules()
+                .into_iter()
+                .map(|mut rule| MatchingRule {
+                    parameter: rule.take_parameter(),
+                    values: rule.take_values().into_vec(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<Risk> for plugins::interface::Graph_Risk {
+    fn from(risk: Risk) -> Self {
+        let mut risk_converted = plugins::interface::Graph_Risk::new();
+        risk_converted.set_name(risk.name);
+        risk_converted.set_url(risk.url);
+        risk_converted.set_message(risk.message);
+        risk_converted.set_matching_rules(
+            risk.matching_rules
+                .into_iter()
+                .map(|rule| {
+                    let mut rule_converted = plugins::interface::Graph_MatchingRule::new();
+                    rule_converted.set_parameter(rule.parameter);
+                    rule_converted.set_values(rule.values.into());
+                    rule_converted
+                })
+                .collect(),
+        );
 
-        graph_converted
+        risk_converted
     }
 }
 
 pub mod testing {
     use super::*;
+    use maplit::hashmap;
 
     pub fn generate_graph() -> Graph {
         let mut graph = Graph::default();
-        let v1 = graph.dag.add_node(Release::Concrete(ConcreteRelease {
-            version: String::from("1.0.0"),
+        let v1 = graph.add_node(Release::Concrete(ConcreteRelease {
+            version: Version::new(1, 0, 0),
             payload: String::from("image/1.0.0"),
             metadata: HashMap::new(),
         }));
-        let v2 = graph.dag.add_node(Release::Concrete(ConcreteRelease {
-            version: String::from("2.0.0"),
+        let v2 = graph.add_node(Release::Concrete(ConcreteRelease {
+            version: Version::new(2, 0, 0),
             payload: String::from("image/2.0.0"),
             metadata: HashMap::new(),
         }));
-        let v3 = graph.dag.add_node(Release::Concrete(ConcreteRelease {
-            version: String::from("3.0.0"),
+        let v3 = graph.add_node(Release::Concrete(ConcreteRelease {
+            version: Version::new(3, 0, 0),
             payload: String::from("image/3.0.0"),
             metadata: HashMap::new(),
         }));
-        graph.dag.add_edge(v1, v2, Empty {}).unwrap();
-        graph.dag.add_edge(v2, v3, Empty {}).unwrap();
-        graph.dag.add_edge(v1, v3, Empty {}).unwrap();
+        graph.add_edge(&v1, &v2).unwrap();
+        graph.add_edge(&v2, &v3).unwrap();
+        graph.add_edge(&v1, &v3).unwrap();
 
         graph
     }
@@ -796,54 +1659,34 @@ pub mod testing {
         }
 
         pub fn build(self) -> Graph {
-            let mut graph = Graph::default();
-
-            let nodes: Vec<daggy::NodeIndex> = self
-                .clone()
-                .metadata
-                .into_iter()
-                .map(|(i, mut metadata)| {
-                    let version_unsuffixed = self.version_template.replace("{{i}}", &i.to_string());
-                    let version_suffix = metadata.remove("version_suffix").unwrap_or_default();
-
-                    let version = format!("{}{}", version_unsuffixed, version_suffix);
-                    let payload = format!(
-                        "{}:{}",
-                        &self.image,
-                        if self.enable_payload_suffix {
-                            &version
-                        } else {
-                            &version_unsuffixed
-                        }
-                    );
-
-                    let release = Release::Concrete(ConcreteRelease {
-                        version,
-                        payload,
-                        metadata,
-                    });
-                    graph.dag.add_node(release)
-                })
-                .collect();
-
-            assert_eq!(nodes.len() as u64, graph.releases_count());
+            let mut builder = GraphBuilder::new().require_resolved(true);
+            let mut versions = Vec::with_capacity(self.metadata.len());
+            for (i, mut metadata) in self.metadata {
+                let version_unsuffixed = self.version_template.replace("{{i}}", &i.to_string());
+                let version_suffix = metadata.remove("version_suffix").unwrap_or_default();
+
+                let version = format!("{}{}", version_unsuffixed, version_suffix);
+                let payload = format!(
+                    "{}:{}",
+                    &self.image,
+                    if self.enable_payload_suffix {
+                        &version
+                    } else {
+                        &version_unsuffixed
+                    }
+                );
+                builder = builder.release(version.as_str(), payload, metadata);
+                versions.push(version);
+            }
 
-            if let Some(edges) = self.edges {
-                for (key, value) in &edges {
-                    let one = nodes[*key];
-                    let two = nodes[*value];
-                    graph.dag.add_edge(one, two, Empty {}).unwrap();
-                }
-                assert_eq!(edges.len(), graph.dag.edge_count());
-            } else {
-                for i in 0..(nodes.len() - 1) {
-                    let one = nodes[i];
-                    let two = nodes[i + 1];
-                    graph.dag.add_edge(one, two, Empty {}).unwrap();
-                }
-            };
+            let edges = self
+                .edges
+                .unwrap_or_else(|| (1..versions.len()).map(|i| (i - 1, i)).collect());
+            for (from, to) in edges {
+                builder = builder.edge(versions[from].as_str(), versions[to].as_str());
+            }
 
-            graph
+            builder.build().expect("valid test graph")
         }
     }
 
@@ -858,6 +1701,52 @@ pub mod testing {
             .with_edges(edges)
             .build()
     }
+
+    /// Returns the release with the given version, failing if there is none.
+    pub fn find(graph: &Graph, version: &str) -> Fallible<ReleaseId> {
+        graph
+            .find_by_version(version)
+            .ok_or_else(|| format_err!("couldn't find version {}", version))
+    }
+
+    /// Returns a graph with the edges 1.0.0 -> 2.0.0+arm64 -> 3.0.0 and
+    /// 1.0.0 -> 2.0.0+amd64, with the releases added in the given order.
+    ///
+    /// 3.0.0 is an abstract release and the edge 1.0.0 -> 2.0.0+arm64 carries metadata.
+    pub fn generate_graph_in_order(versions: &[&str]) -> Fallible<Graph> {
+        let mut graph = Graph::default();
+        for version in versions {
+            let release = match *version {
+                "3.0.0" => Release::Abstract(AbstractRelease {
+                    version: Version::parse(version)?,
+                }),
+                _ => Release::Concrete(ConcreteRelease {
+                    version: Version::parse(version)?,
+                    payload: format!("image:{}", version),
+                    metadata: hashmap! {
+                        "b".to_string() => "value".to_string(),
+                        "a".to_string() => version.to_string(),
+                    },
+                }),
+            };
+            graph.add_release(release)?;
+        }
+
+        let id = |version: &str| graph.find_by_version(version).unwrap();
+        let (v1, v2_amd64, v2_arm64, v3) = (
+            id("1.0.0"),
+            id("2.0.0+amd64"),
+            id("2.0.0+arm64"),
+            id("3.0.0"),
+        );
+        graph.add_edge(&v2_arm64, &v3)?;
+        graph.add_edge(&v1, &v2_arm64)?;
+        graph.add_edge(&v1, &v2_amd64)?;
+        graph
+            .get_edge_metadata_as_ref_mut(&v1, &v2_arm64)?
+            .insert("key".to_string(), "value".to_string());
+        Ok(graph)
+    }
 }
 
 #[cfg(test)]
@@ -890,33 +1779,33 @@ mod tests {
     fn test_graph_eq_false_for_unequal_graphs() {
         let graph1 = {
             let mut graph = Graph::default();
-            let v1 = graph.dag.add_node(Release::Concrete(ConcreteRelease {
-                version: String::from("1.0.0"),
+            let v1 = graph.add_node(Release::Concrete(ConcreteRelease {
+                version: Version::new(1, 0, 0),
                 payload: String::from("image/1.0.0"),
                 metadata: HashMap::new(),
             }));
-            let v2 = graph.dag.add_node(Release::Concrete(ConcreteRelease {
-                version: String::from("2.0.0"),
+            let v2 = graph.add_node(Release::Concrete(ConcreteRelease {
+                version: Version::new(2, 0, 0),
                 payload: String::from("image/2.0.0"),
                 metadata: HashMap::new(),
             }));
-            graph.dag.add_edge(v1, v2, Empty {}).unwrap();
+            graph.add_edge(&v1, &v2).unwrap();
 
             graph
         };
         let graph2 = {
             let mut graph = Graph::default();
-            let v3 = graph.dag.add_node(Release::Concrete(ConcreteRelease {
-                version: String::from("3.0.0"),
+            let v3 = graph.add_node(Release::Concrete(ConcreteRelease {
+                version: Version::new(3, 0, 0),
                 payload: String::from("image/3.0.0"),
                 metadata: HashMap::new(),
             }));
-            let v2 = graph.dag.add_node(Release::Concrete(ConcreteRelease {
-                version: String::from("2.0.0"),
+            let v2 = graph.add_node(Release::Concrete(ConcreteRelease {
+                version: Version::new(2, 0, 0),
                 payload: String::from("image/2.0.0"),
                 metadata: HashMap::new(),
             }));
-            graph.dag.add_edge(v2, v3, Empty {}).unwrap();
+            graph.add_edge(&v2, &v3).unwrap();
 
             graph
         };
@@ -931,51 +1820,82 @@ mod tests {
     #[test]
     fn test_graph_eq_is_agnostic_to_node_and_edge_order() {
         let r1 = Release::Concrete(ConcreteRelease {
-            version: String::from("1.0.0"),
+            version: Version::new(1, 0, 0),
             payload: String::from("image/1.0.0"),
             metadata: HashMap::new(),
         });
         let r2 = Release::Concrete(ConcreteRelease {
-            version: String::from("2.0.0"),
+            version: Version::new(2, 0, 0),
             payload: String::from("image/2.0.0"),
             metadata: HashMap::new(),
         });
 
         let r3 = Release::Concrete(ConcreteRelease {
-            version: String::from("3.0.0"),
+            version: Version::new(3, 0, 0),
             payload: String::from("image/3.0.0"),
             metadata: HashMap::new(),
         });
 
         let graph1 = {
             let mut graph = Graph::default();
-            let v1 = graph.dag.add_node(r1.clone());
-            let v2 = graph.dag.add_node(r2.clone());
-            let v3 = graph.dag.add_node(r3.clone());
-            graph.dag.add_edge(v1, v2, Empty {}).unwrap();
-            graph.dag.add_edge(v1, v3, Empty {}).unwrap();
-            graph.dag.add_edge(v2, v3, Empty {}).unwrap();
+            let v1 = graph.add_node(r1.clone());
+            let v2 = graph.add_node(r2.clone());
+            let v3 = graph.add_node(r3.clone());
+            graph.add_edge(&v1, &v2).unwrap();
+            graph.add_edge(&v1, &v3).unwrap();
+            graph.add_edge(&v2, &v3).unwrap();
 
             graph
         };
         let graph2 = {
             let mut graph = Graph::default();
-            let v3 = graph.dag.add_node(r3.clone());
-            let v2 = graph.dag.add_node(r2.clone());
-            let v1 = graph.dag.add_node(r1.clone());
-            graph.dag.add_edge(v2, v3, Empty {}).unwrap();
-            graph.dag.add_edge(v1, v2, Empty {}).unwrap();
-            graph.dag.add_edge(v1, v3, Empty {}).unwrap();
+            let v3 = graph.add_node(r3.clone());
+            let v2 = graph.add_node(r2.clone());
+            let v1 = graph.add_node(r1.clone());
+            graph.add_edge(&v2, &v3).unwrap();
+            graph.add_edge(&v1, &v2).unwrap();
+            graph.add_edge(&v1, &v3).unwrap();
 
             graph
         };
         assert_eq!(graph1, graph2);
     }
 
+    #[test]
+    fn deserialize_rejects_invalid_versions() {
+        let json =
+            r#"{"nodes":[{"version":"1.0","payload":"image/1.0","metadata":{}}],"edges":[]}"#;
+        assert!(serde_json::from_str::<Graph>(json).is_err());
+
+        let mut graph = plugins::interface::Graph::new();
+        let mut node = plugins::interface::Graph_Node::new();
+        node.set_version("1.0".to_string());
+        graph.mut_nodes().push(node);
+        assert!(Graph::try_from(graph).is_err());
+    }
+
+    #[test]
+    fn releases_are_ordered_by_version() {
+        let release = |version: &str| {
+            Release::Abstract(AbstractRelease {
+                version: Version::parse(version).unwrap(),
+            })
+        };
+
+        assert!(release("1.10.0") > release("1.9.0"));
+        assert!(release("1.0.0-rc.1") < release("1.0.0"));
+        assert!(release("1.0.0+amd64") <= release("1.0.0+amd64"));
+        assert_ne!(release("1.0.0+amd64"), release("1.0.0+arm64"));
+        assert_eq!(
+            release("1.0.0+amd64").partial_cmp(&release("1.0.0+arm64")),
+            None
+        );
+    }
+
     #[test]
     fn roundtrip_conversion_from_graph_via_plugin_interface() {
-        let graph_plugin_interface: plugins::interface::Graph = generate_graph().into();
-        let graph_native_converted: Graph = graph_plugin_interface.into();
+        let graph_plugin_interface = plugins::interface::Graph::try_from(generate_graph()).unwrap();
+        let graph_native_converted = Graph::try_from(graph_plugin_interface).unwrap();
 
         assert_eq!(generate_graph(), graph_native_converted);
     }
@@ -1030,16 +1950,16 @@ mod tests {
         let metadata = get_test_metadata_fn_mut("prefix", "suffix");
         let mut graph = generate_custom_graph("image", metadata, Some(vec![]));
 
-        let expected = vec![(0, "0.0.0"), (1, "1.0.0"), (2, "2.0.0"), (3, "3.0.0")]
+        let expected = vec!["0.0.0", "1.0.0", "2.0.0", "3.0.0"]
             .into_iter()
-            .map(|(id, version)| {
+            .map(|version| {
                 (
-                    ReleaseId(daggy::NodeIndex::from(id as u32)),
+                    graph.find_by_version(version).expect("version to exist"),
                     version.to_string(),
                 )
             })
             .collect::<Vec<(ReleaseId, String)>>();
-        let result = graph.find_by_fn_mut(|_| true);
+        let result = graph.find_by_fn_mut(|_| true).unwrap();
         assert_eq!(expected, result);
     }
 
@@ -1049,11 +1969,11 @@ mod tests {
         let metadata = get_test_metadata_fn_mut(&prefix, &suffix);
         let mut graph = generate_custom_graph("image", metadata, Some(vec![]));
 
-        let expected = vec![(0, "0.0.0"), (1, "1.0.0"), (2, "2.0.0"), (3, "3.0.0")]
+        let expected = vec!["0.0.0", "1.0.0", "2.0.0", "3.0.0"]
             .into_iter()
-            .map(|(id, version)| {
+            .map(|version| {
                 (
-                    ReleaseId(daggy::NodeIndex::from(id as u32)),
+                    graph.find_by_version(version).expect("version to exist"),
                     version.to_string(),
                 )
             })
@@ -1062,14 +1982,16 @@ mod tests {
         let metadata_key = format!("{}.{}", &prefix, &suffix);
         let expected_metadata_value = "changed";
 
-        let result = graph.find_by_fn_mut(|release| match release {
-            Release::Concrete(concrete_release) => {
-                *concrete_release.metadata.get_mut(&metadata_key).unwrap() =
-                    expected_metadata_value.to_string();
-                true
-            }
-            _ => true,
-        });
+        let result = graph
+            .find_by_fn_mut(|release| match release {
+                Release::Concrete(concrete_release) => {
+                    *concrete_release.metadata.get_mut(&metadata_key).unwrap() =
+                        expected_metadata_value.to_string();
+                    true
+                }
+                _ => true,
+            })
+            .unwrap();
 
         assert_eq!(expected, result);
 
@@ -1100,9 +2022,8 @@ mod tests {
             (2..=4).map(|i| (i, Default::default())).collect(),
             None,
         )
-        .find_by_fn_mut(|_| true)
-        .into_iter()
-        .map(|(_, version)| version)
+        .iter_releases()
+        .map(|(_, release)| release.version().to_string())
         .collect();
 
         let anchor_version = "1.0.0";
@@ -1112,7 +2033,7 @@ mod tests {
             .ok_or_else(|| format!("couldn't find version {}", anchor_version))?;
 
         let result: HashSet<String> = graph
-            .next_releases(&v3)
+            .next_releases(&v3)?
             .map(|(_, _, r)| r.version())
             .map(ToString::to_string)
             .collect();
@@ -1138,9 +2059,8 @@ mod tests {
             (1..=3).map(|i| (i, Default::default())).collect(),
             None,
         )
-        .find_by_fn_mut(|_| true)
-        .into_iter()
-        .map(|(_, version)| version)
+        .iter_releases()
+        .map(|(_, release)| release.version().to_string())
         .collect();
 
         let anchor_version = "4.0.0";
@@ -1150,7 +2070,7 @@ mod tests {
             .ok_or_else(|| format!("couldn't find version {}", anchor_version))?;
 
         let result: HashSet<String> = graph
-            .previous_releases(&v3)
+            .previous_releases(&v3)?
             .map(|(_, _, r)| r.version())
             .map(ToString::to_string)
             .collect();
@@ -1160,4 +2080,575 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn find_by_version_is_consistent_after_removals() -> TestResult<()> {
+        let n = 6;
+        let mut graph = generate_custom_graph(
+            "image",
+            (0..n).map(|i| (i, Default::default())).collect(),
+            None,
+        );
+
+        let to_remove = ["0.0.0", "3.0.0", "5.0.0"]
+            .iter()
+            .map(|version| {
+                graph
+                    .find_by_version(version)
+                    .ok_or_else(|| format!("couldn't find version {}", version))
+            })
+            .collect::<Result<Vec<ReleaseId>, String>>()?;
+        assert_eq!(graph.remove_releases(to_remove), 3);
+
+        for version in &["0.0.0", "3.0.0", "5.0.0"] {
+            assert_eq!(graph.find_by_version(version), None);
+        }
+
+        for version in &["1.0.0", "2.0.0", "4.0.0"] {
+            let id = graph
+                .find_by_version(version)
+                .ok_or_else(|| format!("couldn't find version {}", version))?;
+            assert_eq!(
+                graph.find_by_releaseid(&id)?.version().to_string(),
+                *version
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn find_by_version_is_consistent_after_pruning() -> TestResult<()> {
+        let mut graph = generate_custom_graph(
+            "image",
+            (0..3).map(|i| (i, Default::default())).collect(),
+            None,
+        );
+
+        let abstract_id = graph.add_release(Release::Abstract(AbstractRelease {
+            version: Version::new(3, 0, 0),
+        }))?;
+        let concrete_id = graph
+            .find_by_version("2.0.0")
+            .ok_or("couldn't find version 2.0.0")?;
+        graph.add_edge(&concrete_id, &abstract_id)?;
+        graph.add_release(Release::Abstract(AbstractRelease {
+            version: Version::new(4, 0, 0),
+        }))?;
+
+        assert_eq!(graph.prune_abstract(), 2);
+        assert_eq!(graph.find_by_version("3.0.0"), None);
+        assert_eq!(graph.find_by_version("4.0.0"), None);
+
+        for version in &["0.0.0", "1.0.0", "2.0.0"] {
+            let id = graph
+                .find_by_version(version)
+                .ok_or_else(|| format!("couldn't find version {}", version))?;
+            assert_eq!(
+                graph.find_by_releaseid(&id)?.version().to_string(),
+                *version
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn find_by_version_follows_mutated_versions() -> TestResult<()> {
+        let mut graph = generate_custom_graph(
+            "image",
+            (0..3).map(|i| (i, Default::default())).collect(),
+            None,
+        );
+
+        graph.iter_releases_mut(|release| {
+            if let Release::Concrete(release) = release {
+                release.version.build = vec![semver::Identifier::AlphaNumeric("amd64".to_string())];
+            }
+            Ok(())
+        })?;
+
+        assert_eq!(graph.find_by_version("1.0.0"), None);
+        let id = graph
+            .find_by_version("1.0.0+amd64")
+            .ok_or("couldn't find version 1.0.0+amd64")?;
+        assert_eq!(
+            graph.find_by_releaseid(&id)?.version().to_string(),
+            "1.0.0+amd64"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn mutated_versions_must_not_collide() -> TestResult<()> {
+        let mut graph = generate_custom_graph(
+            "image",
+            (0..3).map(|i| (i, Default::default())).collect(),
+            None,
+        );
+        let before = graph.clone();
+
+        // Swapping versions doesn't collide.
+        let swapped = graph.find_by_fn_mut(|release| {
+            let version = release.version_mut();
+            match version.major {
+                0 => version.major = 1,
+                1 => version.major = 0,
+                _ => return false,
+            }
+            true
+        })?;
+        assert_eq!(swapped.len(), 2);
+        for version in &["0.0.0", "1.0.0", "2.0.0"] {
+            let id = graph.find_by_version(version).ok_or("missing version")?;
+            assert_eq!(
+                graph.find_by_releaseid(&id)?.version().to_string(),
+                *version
+            );
+        }
+
+        // Changing a version to an existing one fails and restores all versions.
+        let mut graph = before.clone();
+        let result = graph.iter_releases_mut(|release| {
+            let version = release.version_mut();
+            version.major += 1;
+            if version.major == 3 {
+                version.major = 1;
+            }
+            Ok(())
+        });
+        assert!(result.is_err());
+        assert_eq!(graph, before);
+        for (id, release) in graph.iter_releases() {
+            assert_eq!(
+                graph.find_by_version(&release.version().to_string()),
+                Some(id)
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn release_ids_stay_valid_after_removals() -> TestResult<()> {
+        let n = 6;
+        let mut graph = generate_custom_graph(
+            "image",
+            (0..n).map(|i| (i, Default::default())).collect(),
+            None,
+        );
+
+        let ids = (0..n)
+            .map(|i| {
+                let version = format!("{}.0.0", i);
+                graph
+                    .find_by_version(&version)
+                    .ok_or_else(|| format!("couldn't find version {}", version))
+            })
+            .collect::<Result<Vec<ReleaseId>, String>>()?;
+
+        assert_eq!(
+            graph.remove_releases(vec![ids[1].clone(), ids[4].clone()]),
+            2
+        );
+
+        for (i, id) in ids.iter().enumerate() {
+            match i {
+                1 | 4 => {
+                    let err = graph.find_by_releaseid(id).unwrap_err();
+                    assert!(err.downcast_ref::<errors::InvalidReleaseId>().is_some());
+                }
+                _ => assert_eq!(
+                    graph.find_by_releaseid(id)?.version().to_string(),
+                    format!("{}.0.0", i)
+                ),
+            }
+        }
+
+        let next: Vec<String> = graph
+            .next_releases(&ids[2])?
+            .map(|(_, id, release)| {
+                assert_eq!(id, ids[3]);
+                release.version().to_string()
+            })
+            .collect();
+        assert_eq!(next, vec!["3.0.0"]);
+
+        // Removing already removed releases is a no-op.
+        assert_eq!(
+            graph.remove_releases(vec![ids[1].clone(), ids[5].clone()]),
+            1
+        );
+        assert_eq!(graph.releases_count(), 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn release_id_is_invalid_after_its_slot_is_reused() -> TestResult<()> {
+        let mut graph = generate_graph();
+
+        let removed = graph
+            .find_by_version("2.0.0")
+            .ok_or("couldn't find 2.0.0")?;
+        assert_eq!(graph.remove_releases(vec![removed.clone()]), 1);
+
+        let added = graph.add_release(Release::Concrete(ConcreteRelease {
+            version: Version::new(4, 0, 0),
+            payload: String::from("image/4.0.0"),
+            metadata: HashMap::new(),
+        }))?;
+        assert_ne!(removed, added);
+        assert_eq!(
+            graph.find_by_releaseid(&added)?.version().to_string(),
+            "4.0.0"
+        );
+
+        let v1 = graph
+            .find_by_version("1.0.0")
+            .ok_or("couldn't find 1.0.0")?;
+        for err in vec![
+            graph.find_by_releaseid(&removed).map(|_| ()).unwrap_err(),
+            graph.next_releases(&removed).map(|_| ()).unwrap_err(),
+            graph.add_edge(&v1, &removed).unwrap_err(),
+            graph
+                .get_metadata_as_ref_mut(&removed)
+                .map(|_| ())
+                .unwrap_err(),
+        ] {
+            assert!(err.downcast_ref::<errors::InvalidReleaseId>().is_some());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn add_edge_fails_on_cycle() -> TestResult<()> {
+        let mut graph = generate_graph();
+
+        let v1 = graph
+            .find_by_version("1.0.0")
+            .ok_or("couldn't find 1.0.0")?;
+        let v3 = graph
+            .find_by_version("3.0.0")
+            .ok_or("couldn't find 3.0.0")?;
+
+        for (from, to) in vec![(&v3, &v1), (&v1, &v1)] {
+            let err = graph.add_edge(from, to).unwrap_err();
+            assert!(err.downcast_ref::<WouldCycle<Empty>>().is_some());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn serialize_graph_after_removal() -> TestResult<()> {
+        let mut graph = generate_graph();
+
+        let v2 = graph
+            .find_by_version("2.0.0")
+            .ok_or("couldn't find 2.0.0")?;
+        assert_eq!(graph.remove_releases(vec![v2]), 1);
+
+        let json = serde_json::to_string(&graph)?;
+        assert_eq!(
+            json,
+            r#"{"nodes":[{"version":"1.0.0","payload":"image/1.0.0","metadata":{}},{"version":"3.0.0","payload":"image/3.0.0","metadata":{}}],"edges":[[0,1]]}"#
+        );
+        assert_eq!(serde_json::from_str::<Graph>(&json)?, graph);
+
+        Ok(())
+    }
+
+    #[test]
+    fn deserialize_graph_rejects_cycles() {
+        let json = r#"{"nodes":[{"version":"1.0.0","payload":"image/1.0.0","metadata":{}},{"version":"2.0.0","payload":"image/2.0.0","metadata":{}}],"edges":[[0,1],[1,0]]}"#;
+        assert!(serde_json::from_str::<Graph>(json).is_err());
+    }
+
+    #[test]
+    fn deserialize_graph_from_reader() -> TestResult<()> {
+        let json = r#"{"edges":[[0,1],[1,2],[0,2]],"nodes":[{"version":"1.0.0","payload":"image/1.0.0","metadata":{}},{"version":"2.0.0","payload":"image/2.0.0","metadata":{}},{"version":"3.0.0","payload":"image/3.0.0","metadata":{}}]}"#;
+        let limits = GraphLimits {
+            max_bytes: Some(json.len() as u64),
+            max_nodes: Some(3),
+            max_edges: Some(3),
+        };
+        assert_eq!(
+            Graph::from_json_reader(json.as_bytes(), &limits)?,
+            generate_graph()
+        );
+        assert_eq!(
+            Graph::from_json_reader(json.as_bytes(), &GraphLimits::default())?,
+            generate_graph()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn deserialize_graph_from_reader_enforces_limits() {
+        let json = serde_json::to_string(&generate_graph()).unwrap();
+
+        let too_few_nodes = GraphLimits {
+            max_nodes: Some(2),
+            ..Default::default()
+        };
+        let err = Graph::from_json_reader(json.as_bytes(), &too_few_nodes).unwrap_err();
+        assert!(err.to_string().contains("maximum of 2 nodes"), "{}", err);
+
+        let too_few_bytes = GraphLimits {
+            max_bytes: Some(json.len() as u64 - 1),
+            ..Default::default()
+        };
+        let err = Graph::from_json_reader(json.as_bytes(), &too_few_bytes).unwrap_err();
+        assert!(err.to_string().contains("maximum size"), "{}", err);
+
+        let too_few_edges = GraphLimits {
+            max_edges: Some(2),
+            ..Default::default()
+        };
+        let err = Graph::from_json_reader(json.as_bytes(), &too_few_edges).unwrap_err();
+        assert!(err.to_string().contains("maximum of 2 edges"), "{}", err);
+    }
+
+    #[test]
+    fn deserialize_graph_from_reader_counts_conditional_edges() {
+        let json = r#"{"nodes":[{"version":"1.0.0","payload":"image/1.0.0","metadata":{}},{"version":"2.0.0","payload":"image/2.0.0","metadata":{}},{"version":"3.0.0","payload":"image/3.0.0","metadata":{}}],"edges":[[0,1]],"conditionalEdges":[{"from":0,"to":2,"risks":[{"url":"https://example.com/risk","name":"Risk","message":"A risk.","matchingRules":[]}]}]}"#;
+        let limits = GraphLimits {
+            max_edges: Some(1),
+            ..Default::default()
+        };
+        let err = Graph::from_json_reader(json.as_bytes(), &limits).unwrap_err();
+        assert!(err.to_string().contains("maximum of 1 edges"), "{}", err);
+    }
+
+    #[test]
+    fn deserialize_graph_rejects_duplicate_edges() {
+        let json = r#"{"nodes":[{"version":"1.0.0","payload":"image/1.0.0","metadata":{}},{"version":"2.0.0","payload":"image/2.0.0","metadata":{}}],"edges":[[0,1],[0,1]]}"#;
+        let err = serde_json::from_str::<Graph>(json).unwrap_err();
+        assert!(err.to_string().contains("duplicate edge [0, 1]"), "{}", err);
+    }
+
+    #[test]
+    fn deserialize_graph_from_reader_rejects_trailing_data() {
+        let json = format!("{} []", serde_json::to_string(&generate_graph()).unwrap());
+        assert!(Graph::from_json_reader(json.as_bytes(), &GraphLimits::default()).is_err());
+    }
+
+    #[test]
+    fn edge_metadata_roundtrip_via_json() -> TestResult<()> {
+        let mut graph = generate_graph();
+        let v1 = graph
+            .find_by_version("1.0.0")
+            .ok_or("couldn't find 1.0.0")?;
+        let v3 = graph
+            .find_by_version("3.0.0")
+            .ok_or("couldn't find 3.0.0")?;
+        graph
+            .get_edge_metadata_as_ref_mut(&v1, &v3)?
+            .insert("reason".to_string(), "skip".to_string());
+
+        let json = serde_json::to_string(&graph)?;
+        assert_eq!(
+            json,
+            r#"{"nodes":[{"version":"1.0.0","payload":"image/1.0.0","metadata":{}},{"version":"2.0.0","payload":"image/2.0.0","metadata":{}},{"version":"3.0.0","payload":"image/3.0.0","metadata":{}}],"edges":[[0,1],[1,2],[0,2]],"edgeMetadata":[{"from":0,"to":2,"metadata":{"reason":"skip"}}]}"#
+        );
+
+        let de: Graph = serde_json::from_str(&json)?;
+        assert_eq!(de, graph);
+        assert_ne!(de, generate_graph());
+        assert_eq!(
+            de.get_edge_metadata_as_ref(&v1, &v3)?.get("reason"),
+            Some(&"skip".to_string())
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn deserialize_graph_rejects_metadata_of_nonexistent_edges() {
+        let json = r#"{"nodes":[{"version":"1.0.0","payload":"image/1.0.0","metadata":{}},{"version":"2.0.0","payload":"image/2.0.0","metadata":{}}],"edges":[[0,1]],"edgeMetadata":[{"from":1,"to":0,"metadata":{}}]}"#;
+        assert!(serde_json::from_str::<Graph>(json).is_err());
+    }
+
+    #[test]
+    fn edge_metadata_roundtrip_via_plugin_interface() -> TestResult<()> {
+        use protobuf::Message;
+
+        let mut graph = generate_graph();
+        let v1 = graph
+            .find_by_version("1.0.0")
+            .ok_or("couldn't find 1.0.0")?;
+        let v2 = graph
+            .find_by_version("2.0.0")
+            .ok_or("couldn't find 2.0.0")?;
+        graph
+            .get_edge_metadata_as_ref_mut(&v1, &v2)?
+            .insert("key".to_string(), "value".to_string());
+
+        let bytes = plugins::interface::Graph::try_from(&graph)?.write_to_bytes()?;
+        let graph_converted = Graph::try_from(protobuf::parse_from_bytes::<
+            plugins::interface::Graph,
+        >(&bytes)?)?;
+
+        assert_eq!(graph, graph_converted);
+
+        Ok(())
+    }
+
+    #[test]
+    fn to_protobuf_roundtrip() -> TestResult<()> {
+        let graph = generate_graph();
+
+        let bytes = graph.to_protobuf()?;
+        let graph_converted = Graph::try_from(protobuf::parse_from_bytes::<
+            plugins::interface::Graph,
+        >(&bytes)?)?;
+
+        assert_eq!(graph, graph_converted);
+
+        Ok(())
+    }
+
+    #[test]
+    fn edge_metadata_of_nonexistent_edge() -> TestResult<()> {
+        let mut graph = generate_graph();
+        let v1 = graph
+            .find_by_version("1.0.0")
+            .ok_or("couldn't find 1.0.0")?;
+        let v2 = graph
+            .find_by_version("2.0.0")
+            .ok_or("couldn't find 2.0.0")?;
+
+        let err = graph.get_edge_metadata_as_ref_mut(&v2, &v1).unwrap_err();
+        assert!(err.downcast_ref::<errors::EdgeDoesntExist>().is_some());
+
+        Ok(())
+    }
+
+    fn generate_conditional_graph() -> TestResult<(Graph, ReleaseId, ReleaseId)> {
+        let mut graph = generate_graph();
+        let v1 = graph
+            .find_by_version("1.0.0")
+            .ok_or("couldn't find 1.0.0")?;
+        let v3 = graph
+            .find_by_version("3.0.0")
+            .ok_or("couldn't find 3.0.0")?;
+        graph.remove_edge(&v1, &v3)?;
+        graph.add_conditional_edge(
+            &v1,
+            &v3,
+            vec![Risk {
+                name: "SkipRisk".to_string(),
+                url: "https://example.com/skip".to_string(),
+                message: "skipping is risky".to_string(),
+                matching_rules: vec![MatchingRule {
+                    parameter: "arch".to_string(),
+                    values: vec!["arm64".to_string()],
+                }],
+            }],
+        )?;
+
+        Ok((graph, v1, v3))
+    }
+
+    #[test]
+    fn conditional_edges_roundtrip_via_json() -> TestResult<()> {
+        let (graph, v1, v3) = generate_conditional_graph()?;
+
+        let json = serde_json::to_string(&graph)?;
+        assert_eq!(
+            json,
+            r#"{"nodes":[{"version":"1.0.0","payload":"image/1.0.0","metadata":{}},{"version":"2.0.0","payload":"image/2.0.0","metadata":{}},{"version":"3.0.0","payload":"image/3.0.0","metadata":{}}],"edges":[[0,1],[1,2]],"conditionalEdges":[{"from":0,"to":2,"risks":[{"name":"SkipRisk","url":"https://example.com/skip","message":"skipping is risky","matchingRules":[{"parameter":"arch","values":["arm64"]}]}]}]}"#
+        );
+
+        let de: Graph = serde_json::from_str(&json)?;
+        assert_eq!(de, graph);
+        assert_ne!(de, generate_graph());
+        assert_eq!(
+            de.conditional_edges(),
+            vec![(v1, v3, graph.conditional_edges()[0].2)]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn deserialize_graph_rejects_duplicate_conditional_edges() {
+        let json = r#"{"nodes":[{"version":"1.0.0","payload":"image/1.0.0","metadata":{}},{"version":"2.0.0","payload":"image/2.0.0","metadata":{}}],"edges":[[0,1]],"conditionalEdges":[{"from":0,"to":1,"risks":[{"name":"A","url":"https://example.com/a","message":"a","matchingRules":[]}]}]}"#;
+        let err = serde_json::from_str::<Graph>(json).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("duplicate conditional edge [0, 1]"));
+
+        let json = r#"{"nodes":[{"version":"1.0.0","payload":"image/1.0.0","metadata":{}},{"version":"2.0.0","payload":"image/2.0.0","metadata":{}}],"edges":[],"conditionalEdges":[{"from":0,"to":1,"risks":[{"name":"A","url":"https://example.com/a","message":"a","matchingRules":[]}]},{"from":0,"to":1,"risks":[{"name":"B","url":"https://example.com/b","message":"b","matchingRules":[]}]}]}"#;
+        let err = serde_json::from_str::<Graph>(json).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("duplicate conditional edge [0, 1]"));
+    }
+
+    #[test]
+    fn deserialize_graph_rejects_conditional_edges_without_risks() {
+        let json = r#"{"nodes":[{"version":"1.0.0","payload":"image/1.0.0","metadata":{}},{"version":"2.0.0","payload":"image/2.0.0","metadata":{}}],"edges":[],"conditionalEdges":[{"from":0,"to":1,"risks":[]}]}"#;
+        let err = serde_json::from_str::<Graph>(json).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("conditional edge [0, 1] without risks"));
+    }
+
+    #[test]
+    fn conditional_edges_roundtrip_via_plugin_interface() -> TestResult<()> {
+        use protobuf::Message;
+
+        let (graph, _, _) = generate_conditional_graph()?;
+
+        let bytes = plugins::interface::Graph::try_from(&graph)?.write_to_bytes()?;
+        let graph_converted = Graph::try_from(protobuf::parse_from_bytes::<
+            plugins::interface::Graph,
+        >(&bytes)?)?;
+
+        assert_eq!(graph, graph_converted);
+        assert_eq!(graph_converted.conditional_edges().len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn abstract_releases_cant_be_converted_to_protobuf() -> TestResult<()> {
+        let mut graph = generate_graph();
+        graph.add_release(Release::Abstract(AbstractRelease {
+            version: Version::new(4, 0, 0),
+        }))?;
+
+        let err = plugins::interface::Graph::try_from(&graph).unwrap_err();
+        assert!(err.to_string().contains("abstract release 4.0.0"));
+        assert!(graph.to_protobuf().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn conditional_edges_are_separate_in_plugin_interface() -> TestResult<()> {
+        let (graph, _, _) = generate_conditional_graph()?;
+        let edges_count = graph.dag.edge_count();
+
+        let mut graph_converted = plugins::interface::Graph::try_from(graph)?;
+        assert_eq!(graph_converted.get_conditional_edges().len(), 1);
+        assert_eq!(graph_converted.get_edges().len(), edges_count - 1);
+        assert!(graph_converted
+            .get_edges()
+            .iter()
+            .all(|edge| edge.get_risks().is_empty()));
+
+        // Risks on a plain edge would be ignored by older consumers.
+        let conditional_edge = graph_converted.get_conditional_edges()[0].clone();
+        graph_converted.mut_edges().push(conditional_edge);
+        graph_converted.clear_conditional_edges();
+        assert!(Graph::try_from(graph_converted).is_err());
+
+        Ok(())
+    }
 }
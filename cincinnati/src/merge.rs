@@ -0,0 +1,497 @@
+//! Merging of multiple graphs into one
+
+use crate::{errors, Edge, Graph, Release, ReleaseId, Risk};
+use failure::Fallible;
+use petgraph::visit::{EdgeRef, IntoEdgeReferences, IntoNodeReferences};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Rule for resolving conflicting values of releases and edges which exist in both graphs.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeStrategy {
+    /// Keep the value of the graph which is merged into.
+    PreferLeft,
+    /// Take the value of the graph which is merged from.
+    PreferRight,
+    /// Fail the merge.
+    Error,
+}
+
+/// Value which differs between the merged graphs.
+///
+/// Releases are identified by their version, and edges by the versions of
+/// the releases they connect.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeConflict {
+    Payload {
+        version: String,
+        left: String,
+        right: String,
+    },
+    Metadata {
+        version: String,
+        key: String,
+        left: String,
+        right: String,
+    },
+    EdgeMetadata {
+        from: String,
+        to: String,
+        key: String,
+        left: String,
+        right: String,
+    },
+    EdgeRisks {
+        from: String,
+        to: String,
+        left: Vec<String>,
+        right: Vec<String>,
+    },
+}
+
+impl fmt::Display for MergeConflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MergeConflict::Payload {
+                version,
+                left,
+                right,
+            } => write!(
+                f,
+                "release {} has conflicting payloads '{}' and '{}'",
+                version, left, right
+            ),
+            MergeConflict::Metadata {
+                version,
+                key,
+                left,
+                right,
+            } => write!(
+                f,
+                "release {} has conflicting values '{}' and '{}' for metadata key {}",
+                version, left, right, key
+            ),
+            MergeConflict::EdgeMetadata {
+                from,
+                to,
+                key,
+                left,
+                right,
+            } => write!(
+                f,
+                "edge {} -> {} has conflicting values '{}' and '{}' for metadata key {}",
+                from, to, left, right, key
+            ),
+            MergeConflict::EdgeRisks {
+                from,
+                to,
+                left,
+                right,
+            } => write!(
+                f,
+                "edge {} -> {} has conflicting risks [{}] and [{}]",
+                from,
+                to,
+                left.join(", "),
+                right.join(", ")
+            ),
+        }
+    }
+}
+
+impl Graph {
+    /// Merges the `other` graph into this graph.
+    ///
+    /// Releases with the same version are unified, with concrete releases
+    /// replacing abstract ones like in `add_release`. The edges of both graphs
+    /// are united. Payloads, metadata values and the risks of edges which
+    /// differ between both graphs are resolved according to `strategy`, and all
+    /// of these conflicts are returned ordered by their kind and version. The
+    /// `ReleaseId`s of this graph stay valid.
+    ///
+    /// Fails with the `MergeConflicts` error if `strategy` is `MergeStrategy::Error`
+    /// and there are conflicts, and with the `WouldCycle` error if the united
+    /// edges form a cycle. This graph is left unchanged on failure.
+    pub fn merge(
+        &mut self,
+        other: &Graph,
+        strategy: MergeStrategy,
+    ) -> Fallible<Vec<MergeConflict>> {
+        let mut merged = Graph {
+            dag: self.dag.clone(),
+            generations: self.generations.clone(),
+            versions: self.versions.clone(),
+            cycle_state: Default::default(),
+        };
+        let mut conflicts = vec![];
+
+        let mut ids: HashMap<daggy::NodeIndex, ReleaseId> = HashMap::new();
+        for (index, release) in other.dag.node_references() {
+            let id = match merged.find_by_version(&release.version().to_string()) {
+                Some(id) => {
+                    let left = merged
+                        .dag
+                        .node_weight_mut(id.index)
+                        .expect(crate::EXPECT_NODE_WEIGHT);
+                    merge_release(left, release, strategy, &mut conflicts);
+                    id
+                }
+                None => merged.add_node(release.clone()),
+            };
+            ids.insert(index, id);
+        }
+
+        for edge in other.dag.edge_references() {
+            let (from, to) = (&ids[&edge.source()], &ids[&edge.target()]);
+            match merged.find_edge(from, to) {
+                Ok(index) => merge_edge(
+                    &mut merged.dag[index],
+                    edge.weight(),
+                    &other.dag[edge.source()].version().to_string(),
+                    &other.dag[edge.target()].version().to_string(),
+                    strategy,
+                    &mut conflicts,
+                ),
+                Err(_) => {
+                    merged.add_edge(from, to)?;
+                    let index = merged.find_edge(from, to)?;
+                    merged.dag[index] = edge.weight().clone();
+                }
+            }
+        }
+
+        conflicts.sort();
+        if strategy == MergeStrategy::Error && !conflicts.is_empty() {
+            return Err(errors::MergeConflicts { conflicts }.into());
+        }
+
+        *self = merged;
+        Ok(conflicts)
+    }
+}
+
+/// Merges the `right` release into the `left` release with the same version.
+fn merge_release(
+    left: &mut Release,
+    right: &Release,
+    strategy: MergeStrategy,
+    conflicts: &mut Vec<MergeConflict>,
+) {
+    let (left_release, right_release) = match (&mut *left, right) {
+        (Release::Concrete(left_release), Release::Concrete(right_release)) => {
+            (left_release, right_release)
+        }
+        (Release::Abstract(_), Release::Concrete(_)) => {
+            *left = right.clone();
+            return;
+        }
+        (_, Release::Abstract(_)) => return,
+    };
+
+    if left_release.payload != right_release.payload {
+        conflicts.push(MergeConflict::Payload {
+            version: left_release.version.to_string(),
+            left: left_release.payload.clone(),
+            right: right_release.payload.clone(),
+        });
+        if strategy == MergeStrategy::PreferRight {
+            left_release.payload = right_release.payload.clone();
+        }
+    }
+
+    let version = left_release.version.to_string();
+    merge_metadata(
+        &mut left_release.metadata,
+        &right_release.metadata,
+        strategy,
+        |key, left, right| MergeConflict::Metadata {
+            version: version.clone(),
+            key: key.to_string(),
+            left: left.to_string(),
+            right: right.to_string(),
+        },
+        conflicts,
+    );
+}
+
+/// Merges the `right` edge into the `left` edge between the releases `from` and `to`.
+///
+/// Differing sets of risks, including an unconditional and a conditional edge,
+/// are a single conflict which is resolved like differing metadata values.
+fn merge_edge(
+    left: &mut Edge,
+    right: &Edge,
+    from: &str,
+    to: &str,
+    strategy: MergeStrategy,
+    conflicts: &mut Vec<MergeConflict>,
+) {
+    merge_metadata(
+        &mut left.metadata,
+        &right.metadata,
+        strategy,
+        |key, left, right| MergeConflict::EdgeMetadata {
+            from: from.to_string(),
+            to: to.to_string(),
+            key: key.to_string(),
+            left: left.to_string(),
+            right: right.to_string(),
+        },
+        conflicts,
+    );
+
+    let contains_all = |risks: &[Risk], other: &[Risk]| other.iter().all(|r| risks.contains(r));
+    if !contains_all(&left.risks, &right.risks) || !contains_all(&right.risks, &left.risks) {
+        let names = |risks: &[Risk]| risks.iter().map(|risk| risk.name.clone()).collect();
+        conflicts.push(MergeConflict::EdgeRisks {
+            from: from.to_string(),
+            to: to.to_string(),
+            left: names(&left.risks),
+            right: names(&right.risks),
+        });
+        if strategy == MergeStrategy::PreferRight {
+            left.risks = right.risks.clone();
+        }
+    }
+}
+
+/// Adds all keys of `right` to `left`, resolving differing values according to `strategy`.
+fn merge_metadata<F>(
+    left: &mut HashMap<String, String>,
+    right: &HashMap<String, String>,
+    strategy: MergeStrategy,
+    conflict: F,
+    conflicts: &mut Vec<MergeConflict>,
+) where
+    F: Fn(&str, &str, &str) -> MergeConflict,
+{
+    for (key, right_value) in right {
+        match left.get_mut(key) {
+            None => {
+                left.insert(key.to_owned(), right_value.to_owned());
+            }
+            Some(left_value) if left_value != right_value => {
+                conflicts.push(conflict(key, left_value, right_value));
+                if strategy == MergeStrategy::PreferRight {
+                    *left_value = right_value.to_owned();
+                }
+            }
+            Some(_) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{find, generate_custom_graph};
+    use crate::{AbstractRelease, ConcreteRelease};
+    use maplit::hashmap;
+
+    #[test]
+    fn merge_unites_releases_and_edges() -> Fallible<()> {
+        // 0 -> 1 -> 2
+        let mut left =
+            generate_custom_graph("image", (0..3).map(|i| (i, hashmap! {})).collect(), None);
+        // 1 -> 2 -> 3 and 1 -> 3
+        let right = generate_custom_graph(
+            "image",
+            (1..4).map(|i| (i, hashmap! {})).collect(),
+            Some(vec![(0, 1), (1, 2), (0, 2)]),
+        );
+
+        let v0 = find(&left, "0.0.0")?;
+        let conflicts = left.merge(&right, MergeStrategy::Error)?;
+        assert!(conflicts.is_empty());
+
+        let expected = generate_custom_graph(
+            "image",
+            (0..4).map(|i| (i, hashmap! {})).collect(),
+            Some(vec![(0, 1), (1, 2), (2, 3), (1, 3)]),
+        );
+        assert_eq!(left, expected);
+        assert_eq!(left.find_by_releaseid(&v0)?.version().to_string(), "0.0.0");
+
+        Ok(())
+    }
+
+    #[test]
+    fn merge_replaces_abstract_releases() -> Fallible<()> {
+        let mut left = Graph::default();
+        let v1 = left.add_release(Release::Abstract(AbstractRelease {
+            version: semver::Version::new(1, 0, 0),
+        }))?;
+        let right = generate_custom_graph("image", vec![(1, hashmap! {})], None);
+
+        left.merge(&right, MergeStrategy::Error)?;
+        assert_eq!(
+            left.find_by_releaseid(&v1)?,
+            &Release::Concrete(ConcreteRelease {
+                version: semver::Version::new(1, 0, 0),
+                payload: "image:1.0.0".to_string(),
+                metadata: HashMap::new(),
+            })
+        );
+
+        // Abstract releases never replace concrete ones.
+        let mut abstract_graph = Graph::default();
+        abstract_graph.add_release(Release::Abstract(AbstractRelease {
+            version: semver::Version::new(1, 0, 0),
+        }))?;
+        left.merge(&abstract_graph, MergeStrategy::Error)?;
+        assert_eq!(left, right);
+
+        Ok(())
+    }
+
+    #[test]
+    fn merge_resolves_conflicts_by_strategy() -> Fallible<()> {
+        let generate = |image: &str, value: &str| {
+            generate_custom_graph(
+                image,
+                vec![
+                    (0, hashmap! { "key".to_string() => value.to_string() }),
+                    (1, hashmap! {}),
+                ],
+                None,
+            )
+        };
+        let (left, right) = (generate("left", "old"), generate("right", "new"));
+
+        let expected_conflicts = vec![
+            MergeConflict::Payload {
+                version: "0.0.0".to_string(),
+                left: "left:0.0.0".to_string(),
+                right: "right:0.0.0".to_string(),
+            },
+            MergeConflict::Payload {
+                version: "1.0.0".to_string(),
+                left: "left:1.0.0".to_string(),
+                right: "right:1.0.0".to_string(),
+            },
+            MergeConflict::Metadata {
+                version: "0.0.0".to_string(),
+                key: "key".to_string(),
+                left: "old".to_string(),
+                right: "new".to_string(),
+            },
+        ];
+
+        let mut merged = generate("left", "old");
+        assert_eq!(
+            merged.merge(&right, MergeStrategy::PreferLeft)?,
+            expected_conflicts
+        );
+        assert_eq!(merged, left);
+
+        let mut merged = generate("left", "old");
+        assert_eq!(
+            merged.merge(&right, MergeStrategy::PreferRight)?,
+            expected_conflicts
+        );
+        assert_eq!(merged, right);
+
+        let mut merged = generate("left", "old");
+        let err = merged.merge(&right, MergeStrategy::Error).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<errors::MergeConflicts>()
+                .map(errors::MergeConflicts::conflicts),
+            Some(&expected_conflicts[..])
+        );
+        assert_eq!(merged, left);
+
+        Ok(())
+    }
+
+    #[test]
+    fn merge_resolves_risk_conflicts_by_strategy() -> Fallible<()> {
+        let risk = |name: &str| Risk {
+            name: name.to_string(),
+            url: format!("https://example.com/{}", name),
+            message: name.to_string(),
+            matching_rules: vec![],
+        };
+        let generate = |risks: Vec<Risk>| -> Fallible<Graph> {
+            let mut graph =
+                generate_custom_graph("image", (0..2).map(|i| (i, hashmap! {})).collect(), None);
+            let (v0, v1) = (find(&graph, "0.0.0")?, find(&graph, "1.0.0")?);
+            *graph.get_edge_risks_as_ref_mut(&v0, &v1)? = risks;
+            Ok(graph)
+        };
+        let left = generate(vec![risk("A")])?;
+        let right = generate(vec![risk("B"), risk("A")])?;
+
+        let expected_conflicts = vec![MergeConflict::EdgeRisks {
+            from: "0.0.0".to_string(),
+            to: "1.0.0".to_string(),
+            left: vec!["A".to_string()],
+            right: vec!["B".to_string(), "A".to_string()],
+        }];
+
+        let mut merged = generate(vec![risk("A")])?;
+        assert_eq!(
+            merged.merge(&right, MergeStrategy::PreferLeft)?,
+            expected_conflicts
+        );
+        assert_eq!(merged, left);
+
+        let mut merged = generate(vec![risk("A")])?;
+        assert_eq!(
+            merged.merge(&right, MergeStrategy::PreferRight)?,
+            expected_conflicts
+        );
+        assert_eq!(merged, right);
+
+        let mut merged = generate(vec![risk("A")])?;
+        let err = merged.merge(&right, MergeStrategy::Error).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<errors::MergeConflicts>()
+                .map(errors::MergeConflicts::conflicts),
+            Some(&expected_conflicts[..])
+        );
+        assert_eq!(merged, left);
+
+        // The order of the risks doesn't matter.
+        let mut merged = generate(vec![risk("A"), risk("B")])?;
+        assert!(merged.merge(&right, MergeStrategy::Error)?.is_empty());
+
+        // An unconditional edge conflicts with a conditional one.
+        let mut merged = generate(vec![])?;
+        assert_eq!(
+            merged.merge(&left, MergeStrategy::PreferRight)?,
+            vec![MergeConflict::EdgeRisks {
+                from: "0.0.0".to_string(),
+                to: "1.0.0".to_string(),
+                left: vec![],
+                right: vec!["A".to_string()],
+            }]
+        );
+        assert_eq!(merged, left);
+
+        Ok(())
+    }
+
+    #[test]
+    fn merge_fails_on_cycle() -> Fallible<()> {
+        let mut left =
+            generate_custom_graph("image", (0..2).map(|i| (i, hashmap! {})).collect(), None);
+        let right = generate_custom_graph(
+            "image",
+            (0..2).map(|i| (i, hashmap! {})).collect(),
+            Some(vec![(1, 0)]),
+        );
+
+        let err = left.merge(&right, MergeStrategy::PreferLeft).unwrap_err();
+        assert!(err
+            .downcast_ref::<crate::WouldCycle<crate::Empty>>()
+            .is_some());
+        assert_eq!(
+            left,
+            generate_custom_graph("image", (0..2).map(|i| (i, hashmap! {})).collect(), None,)
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,252 @@
+//! Typed access to namespaced release metadata
+//!
+//! Metadata keys which are interpreted by Cincinnati consist of a namespace
+//! and a suffix, e.g. `io.openshift.upgrades.graph` and `release.channels`.
+//! List values are stored comma-separated.
+
+use crate::ConcreteRelease;
+use failure::Fallible;
+use semver::Version;
+use std::collections::HashMap;
+
+/// Namespace of the metadata keys which are interpreted by Cincinnati.
+pub const DEFAULT_NAMESPACE: &str = "io.openshift.upgrades.graph";
+
+/// Returns the full metadata key for `suffix` in `namespace`.
+pub fn key(namespace: &str, suffix: &str) -> String {
+    format!("{}.{}", namespace, suffix)
+}
+
+/// Splits a comma-separated list value into its trimmed, non-empty entries.
+pub fn split_list(value: &str) -> Vec<&str> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .collect()
+}
+
+/// Joins list entries into a comma-separated list value.
+pub fn join_list<I, S>(entries: I) -> String
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    entries
+        .into_iter()
+        .map(|entry| entry.as_ref().trim().to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Read-only view of the metadata keys in one namespace.
+#[derive(Clone, Copy, Debug)]
+pub struct Namespace<'a> {
+    metadata: &'a HashMap<String, String>,
+    namespace: &'a str,
+}
+
+impl<'a> Namespace<'a> {
+    pub fn new(metadata: &'a HashMap<String, String>, namespace: &'a str) -> Self {
+        Namespace {
+            metadata,
+            namespace,
+        }
+    }
+
+    /// Returns the raw value for `suffix`.
+    pub fn get(&self, suffix: &str) -> Option<&'a str> {
+        self.metadata
+            .get(&key(self.namespace, suffix))
+            .map(String::as_str)
+    }
+
+    /// Returns the entries of the list value for `suffix`.
+    pub fn get_list(&self, suffix: &str) -> Option<Vec<&'a str>> {
+        self.get(suffix).map(split_list)
+    }
+
+    /// Returns true if the list value for `suffix` contains `entry`.
+    pub fn list_contains(&self, suffix: &str, entry: &str) -> bool {
+        self.get_list(suffix)
+            .map(|entries| entries.contains(&entry))
+            .unwrap_or(false)
+    }
+
+    /// Returns the value for `suffix` as boolean.
+    ///
+    /// Fails if the value is neither `true` nor `false`.
+    pub fn get_bool(&self, suffix: &str) -> Fallible<Option<bool>> {
+        self.get(suffix)
+            .map(|value| {
+                value.trim().parse().map_err(|_| {
+                    format_err!(
+                        "invalid boolean '{}' for metadata key {}",
+                        value,
+                        key(self.namespace, suffix)
+                    )
+                })
+            })
+            .transpose()
+    }
+
+    /// Returns the value for `suffix` as semantic version.
+    ///
+    /// Fails if the value isn't a valid semantic version.
+    pub fn get_version(&self, suffix: &str) -> Fallible<Option<Version>> {
+        self.get(suffix)
+            .map(|value| {
+                Version::parse(value.trim()).map_err(|e| {
+                    format_err!(
+                        "invalid version '{}' for metadata key {}: {}",
+                        value,
+                        key(self.namespace, suffix),
+                        e
+                    )
+                })
+            })
+            .transpose()
+    }
+}
+
+/// Mutable view of the metadata keys in one namespace.
+#[derive(Debug)]
+pub struct NamespaceMut<'a> {
+    metadata: &'a mut HashMap<String, String>,
+    namespace: &'a str,
+}
+
+impl<'a> NamespaceMut<'a> {
+    pub fn new(metadata: &'a mut HashMap<String, String>, namespace: &'a str) -> Self {
+        NamespaceMut {
+            metadata,
+            namespace,
+        }
+    }
+
+    /// Returns a read-only view of this namespace.
+    pub fn as_namespace(&self) -> Namespace {
+        Namespace::new(self.metadata, self.namespace)
+    }
+
+    /// Sets the raw value for `suffix` and returns the previous one.
+    pub fn set<S>(&mut self, suffix: &str, value: S) -> Option<String>
+    where
+        S: Into<String>,
+    {
+        self.metadata
+            .insert(key(self.namespace, suffix), value.into())
+    }
+
+    /// Sets the list value for `suffix` and returns the previous raw value.
+    pub fn set_list<I, S>(&mut self, suffix: &str, entries: I) -> Option<String>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.set(suffix, join_list(entries))
+    }
+
+    /// Sets the boolean value for `suffix` and returns the previous raw value.
+    pub fn set_bool(&mut self, suffix: &str, value: bool) -> Option<String> {
+        self.set(suffix, value.to_string())
+    }
+
+    /// Removes the value for `suffix` and returns it.
+    pub fn remove(&mut self, suffix: &str) -> Option<String> {
+        self.metadata.remove(&key(self.namespace, suffix))
+    }
+}
+
+impl ConcreteRelease {
+    /// Returns a read-only view of the metadata keys in `namespace`.
+    pub fn namespace<'a>(&'a self, namespace: &'a str) -> Namespace<'a> {
+        Namespace::new(&self.metadata, namespace)
+    }
+
+    /// Returns a mutable view of the metadata keys in `namespace`.
+    pub fn namespace_mut<'a>(&'a mut self, namespace: &'a str) -> NamespaceMut<'a> {
+        NamespaceMut::new(&mut self.metadata, namespace)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use maplit::hashmap;
+
+    fn generate_release() -> ConcreteRelease {
+        ConcreteRelease {
+            version: Version::new(1, 0, 0),
+            payload: "image:1.0.0".to_string(),
+            metadata: hashmap! {
+                key(DEFAULT_NAMESPACE, "release.channels") => " a, b,,c ".to_string(),
+                key(DEFAULT_NAMESPACE, "release.remove") => "true".to_string(),
+                key(DEFAULT_NAMESPACE, "release.invalid") => "yes".to_string(),
+                key(DEFAULT_NAMESPACE, "release.threshold") => "4.2.0-rc.0".to_string(),
+                "other.release.channels".to_string() => "d".to_string(),
+            },
+        }
+    }
+
+    #[test]
+    fn namespace_getters_parse_values() -> Fallible<()> {
+        let release = generate_release();
+        let namespace = release.namespace(DEFAULT_NAMESPACE);
+
+        assert_eq!(namespace.get("release.channels"), Some(" a, b,,c "));
+        assert_eq!(
+            namespace.get_list("release.channels"),
+            Some(vec!["a", "b", "c"])
+        );
+        assert!(namespace.list_contains("release.channels", "b"));
+        assert!(!namespace.list_contains("release.channels", "d"));
+        assert!(!namespace.list_contains("release.missing", "a"));
+        assert_eq!(
+            release.namespace("other").get_list("release.channels"),
+            Some(vec!["d"])
+        );
+
+        assert_eq!(namespace.get_bool("release.remove")?, Some(true));
+        assert_eq!(namespace.get_bool("release.missing")?, None);
+        assert!(namespace.get_bool("release.invalid").is_err());
+
+        assert_eq!(
+            namespace.get_version("release.threshold")?,
+            Some(Version::parse("4.2.0-rc.0")?)
+        );
+        assert!(namespace.get_version("release.invalid").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn namespace_setters_format_values() {
+        let mut release = generate_release();
+        let mut namespace = release.namespace_mut(DEFAULT_NAMESPACE);
+
+        assert_eq!(
+            namespace.set_list("release.channels", vec!["x", " y "]),
+            Some(" a, b,,c ".to_string())
+        );
+        assert_eq!(
+            namespace.set_bool("release.remove", false),
+            Some("true".to_string())
+        );
+        assert_eq!(namespace.remove("release.invalid"), Some("yes".to_string()));
+        assert_eq!(
+            namespace.as_namespace().get_list("release.channels"),
+            Some(vec!["x", "y"])
+        );
+
+        assert_eq!(
+            release.metadata,
+            hashmap! {
+                key(DEFAULT_NAMESPACE, "release.channels") => "x,y".to_string(),
+                key(DEFAULT_NAMESPACE, "release.remove") => "false".to_string(),
+                key(DEFAULT_NAMESPACE, "release.threshold") => "4.2.0-rc.0".to_string(),
+                "other.release.channels".to_string() => "d".to_string(),
+            }
+        );
+    }
+}
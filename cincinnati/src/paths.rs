@@ -0,0 +1,347 @@
+//! Computation of upgrade paths through a graph
+
+use crate::{version_key, Graph, ReleaseId, VersionKey};
+use failure::Fallible;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Sequence of releases, starting at the current and ending at the target release.
+pub type UpgradePath = Vec<ReleaseId>;
+
+impl Graph {
+    /// Returns all releases which can be reached from the given release by one or more upgrades.
+    pub fn reachable_releases(&self, from: &ReleaseId) -> Fallible<HashSet<ReleaseId>> {
+        self.transitive_releases(from, |id| {
+            Ok(self.next_releases(id)?.map(|(_, next, _)| next))
+        })
+    }
+
+    /// Returns all releases from which the given release can be reached by one or more upgrades.
+    pub fn reaching_releases(&self, to: &ReleaseId) -> Fallible<HashSet<ReleaseId>> {
+        self.transitive_releases(to, |id| {
+            Ok(self.previous_releases(id)?.map(|(_, previous, _)| previous))
+        })
+    }
+
+    /// Returns up to `limit` upgrade paths from `from` to `to` with the least number of upgrades.
+    ///
+    /// The number of shortest paths can grow exponentially with the size of
+    /// the graph, hence the result is bounded by `limit`. The paths are ordered
+    /// by the versions of their releases, and the first `limit` of them are
+    /// returned. The result is empty if `to` can't be reached from `from`.
+    pub fn shortest_paths(
+        &self,
+        from: &ReleaseId,
+        to: &ReleaseId,
+        limit: usize,
+    ) -> Fallible<Vec<UpgradePath>> {
+        self.find_by_releaseid(to)?;
+
+        // Breadth-first search which records all predecessors on shortest paths.
+        // All releases of a given depth are visited before the first release
+        // of the following depth, hence the search can stop at the target.
+        let mut depths: HashMap<ReleaseId, usize> = HashMap::new();
+        let mut predecessors: HashMap<ReleaseId, Vec<ReleaseId>> = HashMap::new();
+        let mut queue = VecDeque::new();
+        depths.insert(from.clone(), 0);
+        queue.push_back(from.clone());
+
+        while let Some(current) = queue.pop_front() {
+            if current == *to {
+                break;
+            }
+
+            let depth = depths[&current] + 1;
+            for (_, next, _) in self.next_releases(&current)? {
+                match depths.get(&next) {
+                    None => {
+                        depths.insert(next.clone(), depth);
+                        predecessors.insert(next.clone(), vec![current.clone()]);
+                        queue.push_back(next);
+                    }
+                    Some(next_depth) if *next_depth == depth => predecessors
+                        .get_mut(&next)
+                        .expect("predecessors to be recorded with the depth")
+                        .push(current.clone()),
+                    Some(_) => {}
+                }
+            }
+        }
+
+        if !depths.contains_key(to) {
+            return Ok(vec![]);
+        }
+
+        // Walk the recorded predecessors back from the target to find the
+        // successors of every release which lies on a shortest path.
+        let mut successors: HashMap<ReleaseId, Vec<ReleaseId>> = HashMap::new();
+        let mut visited = HashSet::new();
+        let mut to_visit = vec![to.clone()];
+        while let Some(current) = to_visit.pop() {
+            if current == *from || !visited.insert(current.clone()) {
+                continue;
+            }
+            for predecessor in &predecessors[&current] {
+                successors
+                    .entry(predecessor.clone())
+                    .or_default()
+                    .push(current.clone());
+                to_visit.push(predecessor.clone());
+            }
+        }
+
+        for releases in successors.values_mut() {
+            let mut keyed = releases
+                .drain(..)
+                .map(|id| Ok((version_key(self.find_by_releaseid(&id)?.version()), id)))
+                .collect::<Fallible<Vec<(VersionKey, ReleaseId)>>>()?;
+            keyed.sort_by(|(a, _), (b, _)| a.cmp(b));
+            releases.extend(keyed.into_iter().map(|(_, id)| id));
+        }
+
+        // Depth-first search which visits the successors in order of their
+        // versions, hence it finds the paths in order.
+        let mut paths = vec![];
+        let mut partial_paths = vec![vec![from.clone()]];
+        while paths.len() < limit {
+            let path = match partial_paths.pop() {
+                Some(path) => path,
+                None => break,
+            };
+            let last = path.last().expect("partial paths to be non-empty");
+            if last == to {
+                paths.push(path);
+                continue;
+            }
+
+            for successor in successors[last].iter().rev() {
+                let mut partial_path = path.clone();
+                partial_path.push(successor.clone());
+                partial_paths.push(partial_path);
+            }
+        }
+
+        Ok(paths)
+    }
+
+    /// Returns the recommended upgrade path from `from` to `to`.
+    ///
+    /// Out of all next releases from which `to` is still reachable, each hop
+    /// chooses the newest one by semantic version. This matches the Cluster
+    /// Version Operator, which always updates to the newest available release.
+    ///
+    /// Returns `None` if `to` can't be reached from `from`.
+    pub fn recommended_path(
+        &self,
+        from: &ReleaseId,
+        to: &ReleaseId,
+    ) -> Fallible<Option<UpgradePath>> {
+        self.find_by_releaseid(from)?;
+
+        let reaching = self.reaching_releases(to)?;
+        if from != to && !reaching.contains(from) {
+            return Ok(None);
+        }
+
+        let mut path = vec![from.clone()];
+        while path.last() != Some(to) {
+            let current = path.last().expect("path to be non-empty");
+            let next = self
+                .next_releases(current)?
+                .filter(|(_, next, _)| next == to || reaching.contains(next))
+                .max_by(|(_, _, a), (_, _, b)| a.version().cmp(b.version()))
+                .map(|(_, next, _)| next)
+                .ok_or_else(|| format_err!("could not find next release on path to {:?}", to))?;
+            path.push(next);
+        }
+
+        Ok(Some(path))
+    }
+
+    /// Collects all releases which can be reached from `start` by repeatedly
+    /// following the releases returned by `neighbors`.
+    fn transitive_releases<F, I>(
+        &self,
+        start: &ReleaseId,
+        neighbors: F,
+    ) -> Fallible<HashSet<ReleaseId>>
+    where
+        F: Fn(&ReleaseId) -> Fallible<I>,
+        I: Iterator<Item = ReleaseId>,
+    {
+        let mut found = HashSet::new();
+        let mut to_visit = vec![start.clone()];
+
+        while let Some(current) = to_visit.pop() {
+            for neighbor in neighbors(&current)? {
+                if found.insert(neighbor.clone()) {
+                    to_visit.push(neighbor);
+                }
+            }
+        }
+
+        Ok(found)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{find, generate_custom_graph};
+
+    /// Returns a graph with the following edges:
+    ///
+    /// 0 -> 1 -> 3 -> 4
+    /// 0 -> 2 -> 3
+    /// 0 -> 5
+    /// 1 -> 4
+    fn generate_test_graph() -> Graph {
+        generate_custom_graph(
+            "image",
+            (0..6).map(|i| (i, Default::default())).collect(),
+            Some(vec![(0, 1), (0, 2), (0, 5), (1, 3), (1, 4), (2, 3), (3, 4)]),
+        )
+    }
+
+    /// Returns the versions of the releases on the given path.
+    fn path_versions(graph: &Graph, path: &[ReleaseId]) -> Fallible<Vec<String>> {
+        path.iter()
+            .map(|id| Ok(graph.find_by_releaseid(id)?.version().to_string()))
+            .collect()
+    }
+
+    fn versions(graph: &Graph, paths: Vec<UpgradePath>) -> Fallible<Vec<Vec<String>>> {
+        paths
+            .iter()
+            .map(|path| path_versions(graph, path))
+            .collect()
+    }
+
+    #[test]
+    fn reachable_and_reaching_releases() -> Fallible<()> {
+        let graph = generate_test_graph();
+
+        let to_versions = |ids: HashSet<ReleaseId>| -> Fallible<HashSet<String>> {
+            ids.iter()
+                .map(|id| Ok(graph.find_by_releaseid(id)?.version().to_string()))
+                .collect()
+        };
+
+        assert_eq!(
+            to_versions(graph.reachable_releases(&find(&graph, "1.0.0")?)?)?,
+            ["3.0.0", "4.0.0"].iter().map(ToString::to_string).collect()
+        );
+        assert_eq!(
+            to_versions(graph.reaching_releases(&find(&graph, "3.0.0")?)?)?,
+            ["0.0.0", "1.0.0", "2.0.0"]
+                .iter()
+                .map(ToString::to_string)
+                .collect()
+        );
+        assert!(graph
+            .reachable_releases(&find(&graph, "4.0.0")?)?
+            .is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn shortest_paths_yields_all_shortest_paths() -> Fallible<()> {
+        let graph = generate_test_graph();
+        let v0 = find(&graph, "0.0.0")?;
+
+        assert_eq!(
+            versions(
+                &graph,
+                graph.shortest_paths(&v0, &find(&graph, "3.0.0")?, 10)?
+            )?,
+            vec![
+                vec!["0.0.0", "1.0.0", "3.0.0"],
+                vec!["0.0.0", "2.0.0", "3.0.0"],
+            ]
+        );
+        assert_eq!(
+            versions(
+                &graph,
+                graph.shortest_paths(&v0, &find(&graph, "4.0.0")?, 10)?
+            )?,
+            vec![vec!["0.0.0", "1.0.0", "4.0.0"]]
+        );
+        assert_eq!(
+            versions(&graph, graph.shortest_paths(&v0, &v0, 10)?)?,
+            vec![vec!["0.0.0"]]
+        );
+        assert!(graph
+            .shortest_paths(&find(&graph, "5.0.0")?, &find(&graph, "4.0.0")?, 10)?
+            .is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn shortest_paths_are_ordered_by_semantic_version() -> Fallible<()> {
+        let graph = crate::GraphBuilder::new()
+            .edge("1.0.0", "9.0.0")
+            .edge("1.0.0", "10.0.0")
+            .edge("1.0.0", "1.10.0")
+            .edge("1.0.0", "1.9.0")
+            .edge("9.0.0", "11.0.0")
+            .edge("10.0.0", "11.0.0")
+            .edge("1.10.0", "11.0.0")
+            .edge("1.9.0", "11.0.0")
+            .build()?;
+
+        let (v1, v11) = (find(&graph, "1.0.0")?, find(&graph, "11.0.0")?);
+
+        assert_eq!(
+            versions(&graph, graph.shortest_paths(&v1, &v11, 10)?)?,
+            vec![
+                vec!["1.0.0", "1.9.0", "11.0.0"],
+                vec!["1.0.0", "1.10.0", "11.0.0"],
+                vec!["1.0.0", "9.0.0", "11.0.0"],
+                vec!["1.0.0", "10.0.0", "11.0.0"],
+            ]
+        );
+        assert_eq!(
+            versions(&graph, graph.shortest_paths(&v1, &v11, 2)?)?,
+            vec![
+                vec!["1.0.0", "1.9.0", "11.0.0"],
+                vec!["1.0.0", "1.10.0", "11.0.0"],
+            ]
+        );
+        assert!(graph.shortest_paths(&v1, &v11, 0)?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn recommended_path_prefers_newest_versions() -> Fallible<()> {
+        let graph = generate_test_graph();
+        let (v0, v4) = (find(&graph, "0.0.0")?, find(&graph, "4.0.0")?);
+
+        let path = graph
+            .recommended_path(&v0, &v4)?
+            .ok_or_else(|| format_err!("expected a path"))?;
+        assert_eq!(
+            path_versions(&graph, &path)?,
+            vec!["0.0.0", "2.0.0", "3.0.0", "4.0.0"]
+        );
+
+        assert_eq!(graph.recommended_path(&v4, &v4)?, Some(vec![v4.clone()]));
+        assert_eq!(graph.recommended_path(&find(&graph, "5.0.0")?, &v4)?, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn paths_fail_on_removed_releases() -> Fallible<()> {
+        let mut graph = generate_test_graph();
+        let (v0, v4) = (find(&graph, "0.0.0")?, find(&graph, "4.0.0")?);
+        assert_eq!(graph.remove_releases(vec![v4.clone()]), 1);
+
+        assert!(graph.shortest_paths(&v0, &v4, 10).is_err());
+        assert!(graph.recommended_path(&v0, &v4).is_err());
+        assert!(graph.reachable_releases(&v4).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,239 @@
+//! Typed references to release payload images
+//!
+//! Payloads are container image references of the form
+//! `[host[:port]/]repository[:tag][@digest]`, e.g.
+//! `quay.io/openshift-release-dev/ocp-release:4.2.0` or
+//! `localhost:5000/ocp/release@sha256:0123...`.
+
+use crate::ConcreteRelease;
+use failure::{Error, Fallible};
+use regex::Regex;
+use std::fmt;
+use std::str::FromStr;
+
+lazy_static! {
+    static ref REPOSITORY_COMPONENT_REGEX: Regex =
+        Regex::new(r"^[a-z0-9]+(?:(?:[._]|__|-+)[a-z0-9]+)*$").unwrap();
+    static ref TAG_REGEX: Regex = Regex::new(r"^\w[\w.-]{0,127}$").unwrap();
+    static ref DIGEST_REGEX: Regex =
+        Regex::new(r"^[a-z0-9]+(?:[.+_-][a-z0-9]+)*:[a-zA-Z0-9=_-]+$").unwrap();
+}
+
+/// Parsed reference to a container image.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ImageReference {
+    /// Registry host, e.g. `quay.io`.
+    pub host: Option<String>,
+    /// Registry port, only set together with the host.
+    pub port: Option<u16>,
+    /// Repository path, which may consist of several components separated by `/`.
+    pub repository: String,
+    pub tag: Option<String>,
+    /// Content digest, e.g. `sha256:0123...`.
+    pub digest: Option<String>,
+}
+
+impl FromStr for ImageReference {
+    type Err = Error;
+
+    fn from_str(reference: &str) -> Fallible<Self> {
+        let (name, digest) = match reference.find('@') {
+            Some(index) => (&reference[..index], Some(&reference[index + 1..])),
+            None => (reference, None),
+        };
+        if let Some(digest) = digest {
+            ensure!(
+                DIGEST_REGEX.is_match(digest),
+                "invalid digest '{}' in image reference '{}'",
+                digest,
+                reference
+            );
+        }
+
+        // A colon after the last slash separates the tag, any other one the port.
+        let (name, tag) = match name.rfind(':') {
+            Some(index) if !name[index + 1..].contains('/') => {
+                (&name[..index], Some(&name[index + 1..]))
+            }
+            _ => (name, None),
+        };
+        if let Some(tag) = tag {
+            ensure!(
+                TAG_REGEX.is_match(tag),
+                "invalid tag '{}' in image reference '{}'",
+                tag,
+                reference
+            );
+        }
+
+        // The first component is only a registry if it can't be part of a repository.
+        let (host, port, repository) = match name.find('/') {
+            Some(index) if is_registry(&name[..index]) => {
+                let (host, port) = parse_registry(&name[..index])
+                    .map_err(|e| format_err!("{} in image reference '{}'", e, reference))?;
+                (Some(host), port, &name[index + 1..])
+            }
+            _ => (None, None, name),
+        };
+        ensure!(
+            repository
+                .split('/')
+                .all(|component| REPOSITORY_COMPONENT_REGEX.is_match(component)),
+            "invalid repository '{}' in image reference '{}'",
+            repository,
+            reference
+        );
+
+        Ok(ImageReference {
+            host,
+            port,
+            repository: repository.to_string(),
+            tag: tag.map(str::to_string),
+            digest: digest.map(str::to_string),
+        })
+    }
+}
+
+impl fmt::Display for ImageReference {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(host) = &self.host {
+            write!(f, "{}", host)?;
+            if let Some(port) = self.port {
+                write!(f, ":{}", port)?;
+            }
+            write!(f, "/")?;
+        }
+        write!(f, "{}", self.repository)?;
+        if let Some(tag) = &self.tag {
+            write!(f, ":{}", tag)?;
+        }
+        if let Some(digest) = &self.digest {
+            write!(f, "@{}", digest)?;
+        }
+        Ok(())
+    }
+}
+
+impl ConcreteRelease {
+    /// Returns the payload of this release as parsed image reference.
+    pub fn payload_reference(&self) -> Fallible<ImageReference> {
+        self.payload.parse()
+    }
+}
+
+fn is_registry(component: &str) -> bool {
+    component.contains('.') || component.contains(':') || component == "localhost"
+}
+
+fn parse_registry(registry: &str) -> Fallible<(String, Option<u16>)> {
+    let mut parts = registry.splitn(2, ':');
+    let host = parts.next().unwrap_or_default();
+    ensure!(!host.is_empty(), "missing registry host");
+
+    let port = parts
+        .next()
+        .map(|port| {
+            port.parse::<u16>()
+                .map_err(|e| format_err!("invalid registry port '{}': {}", port, e))
+        })
+        .transpose()?;
+
+    Ok((host.to_string(), port))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reference(
+        host: Option<&str>,
+        port: Option<u16>,
+        repository: &str,
+        tag: Option<&str>,
+        digest: Option<&str>,
+    ) -> ImageReference {
+        ImageReference {
+            host: host.map(str::to_string),
+            port,
+            repository: repository.to_string(),
+            tag: tag.map(str::to_string),
+            digest: digest.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn parse_and_format_image_references() -> Fallible<()> {
+        let digest = "sha256:0f3a9d6eb2c74cde3b9f7e2c1fa1c0d2c6b0b1d8c59e3f4c4c1b0d7a2e6f5a9b";
+
+        for (input, expected) in vec![
+            ("busybox", reference(None, None, "busybox", None, None)),
+            (
+                "library/busybox:1.31",
+                reference(None, None, "library/busybox", Some("1.31"), None),
+            ),
+            (
+                "quay.io/openshift-release-dev/ocp-release:4.2.0-rc.0",
+                reference(
+                    Some("quay.io"),
+                    None,
+                    "openshift-release-dev/ocp-release",
+                    Some("4.2.0-rc.0"),
+                    None,
+                ),
+            ),
+            (
+                "localhost:5000/a/b/c:latest",
+                reference(Some("localhost"), Some(5000), "a/b/c", Some("latest"), None),
+            ),
+            (
+                "localhost/repo",
+                reference(Some("localhost"), None, "repo", None, None),
+            ),
+            (
+                &format!("registry.example.com:8443/ocp/release@{}", digest),
+                reference(
+                    Some("registry.example.com"),
+                    Some(8443),
+                    "ocp/release",
+                    None,
+                    Some(digest),
+                ),
+            ),
+            (
+                &format!("quay.io/ocp/release:4.1.0@{}", digest),
+                reference(
+                    Some("quay.io"),
+                    None,
+                    "ocp/release",
+                    Some("4.1.0"),
+                    Some(digest),
+                ),
+            ),
+        ] {
+            let parsed: ImageReference = input.parse()?;
+            assert_eq!(parsed, expected, "input: {}", input);
+            assert_eq!(parsed.to_string(), input);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_invalid_image_references() {
+        for input in &[
+            "",
+            "quay.io/",
+            "quay.io//repo",
+            "Quay/Repo",
+            "quay.io/repo:",
+            "quay.io/repo:-tag",
+            "quay.io:port/repo",
+            "quay.io:99999/repo",
+            ":5000/repo",
+            "quay.io/repo@sha256",
+            "quay.io/repo@:abc",
+        ] {
+            assert!(input.parse::<ImageReference>().is_err(), "input: {}", input);
+        }
+    }
+}
@@ -3,9 +3,12 @@
 //! This catalog relies on a static list of all available plugins,
 //! referenced by name. It is used for configuration purposes.
 
+use super::external::subprocess::SubprocessPlugin;
+use super::external::web::WebPluginClient;
 use super::internal::arch_filter::ArchFilterPlugin;
 use super::internal::channel_filter::ChannelFilterPlugin;
 use super::internal::cincinnati_graph_fetch::CincinnatiGraphFetchPlugin;
+use super::internal::conditional_edges::ConditionalEdgesPlugin;
 use super::internal::edge_add_remove::EdgeAddRemovePlugin;
 use super::internal::metadata_fetch_quay::QuayMetadataFetchPlugin;
 use super::internal::node_remove::NodeRemovePlugin;
@@ -40,6 +43,9 @@ pub fn deserialize_config(cfg: toml::Value) -> Fallible<Box<dyn PluginSettings>>
             CincinnatiGraphFetchPlugin::deserialize_config(cfg)
         }
         ArchFilterPlugin::PLUGIN_NAME => ArchFilterPlugin::deserialize_config(cfg),
+        ConditionalEdgesPlugin::PLUGIN_NAME => ConditionalEdgesPlugin::deserialize_config(cfg),
+        WebPluginClient::PLUGIN_NAME => WebPluginClient::deserialize_config(cfg),
+        SubprocessPlugin::PLUGIN_NAME => SubprocessPlugin::deserialize_config(cfg),
         x => bail!("unknown plugin '{}'", x),
     }
 }
@@ -81,5 +87,18 @@ mod tests {
         let quay_metadata_repo: toml::Value = toml::from_str(cfg).unwrap();
         let qm_settings = deserialize_config(quay_metadata_repo).unwrap();
         qm_settings.build_plugin(None).unwrap();
+
+        let cfg = r#"
+            name = "web"
+            url = "http://localhost:8080/plugin"
+            timeout_secs = 10
+            max_response_bytes = 1048576
+        "#;
+        let web: toml::Value = toml::from_str(cfg).unwrap();
+        let web_settings = deserialize_config(web).unwrap();
+        web_settings.build_plugin(None).unwrap();
+
+        let web_no_url: toml::Value = toml::from_str("name = 'web'").unwrap();
+        deserialize_config(web_no_url).unwrap_err();
     }
 }
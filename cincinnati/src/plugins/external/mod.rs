@@ -1,3 +1,5 @@
 //! This module references the available external plugins
 
+pub mod subprocess;
 pub mod web;
+pub mod web_server;
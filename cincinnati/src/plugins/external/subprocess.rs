@@ -0,0 +1,307 @@
+//! External plugin which runs an executable
+//!
+//! The serialized `PluginExchange` is written to the stdin of the executable.
+//! On success, the executable writes the resulting `PluginExchange` to its
+//! stdout and exits with status 0. Otherwise it may write a `PluginError` to
+//! its stdout before exiting with a non-zero status. Its stderr is logged, at
+//! warning level if it fails or times out.
+
+use crate::plugins::interface::{PluginError, PluginExchange};
+use crate::plugins::{
+    AsyncIO, BoxedPlugin, ExternalIO, ExternalPlugin, ExternalPluginWrapper, PluginResult,
+    PluginSettings,
+};
+use failure::{Fallible, ResultExt};
+use futures::sync::oneshot;
+use futures::Future;
+use prometheus::Registry;
+use std::convert::{TryFrom, TryInto};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Default timeout for running the executable, in seconds.
+pub const DEFAULT_TIMEOUT_SECS: u64 = 30;
+
+/// Interval in which the executable is checked for having exited.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Plugin settings.
+#[derive(Clone, Debug, Deserialize, SmartDefault)]
+#[serde(default)]
+struct SubprocessPluginSettings {
+    command: PathBuf,
+    args: Vec<String>,
+
+    #[default(DEFAULT_TIMEOUT_SECS)]
+    timeout_secs: u64,
+}
+
+impl PluginSettings for SubprocessPluginSettings {
+    fn build_plugin(&self, _: Option<&Registry>) -> Fallible<BoxedPlugin> {
+        let cfg = self.clone();
+        let plugin = SubprocessPlugin {
+            command: cfg.command,
+            args: cfg.args,
+            timeout: Duration::from_secs(cfg.timeout_secs),
+        };
+        Ok(new_plugin!(ExternalPluginWrapper(plugin)))
+    }
+}
+
+/// Plugin which runs an executable for every request.
+#[derive(Debug)]
+pub struct SubprocessPlugin {
+    /// Path to the executable
+    pub command: PathBuf,
+
+    /// Arguments to pass to the executable
+    pub args: Vec<String>,
+
+    /// Time after which the executable is killed
+    pub timeout: Duration,
+}
+
+impl SubprocessPlugin {
+    /// Plugin name, for configuration.
+    pub const PLUGIN_NAME: &'static str = "subprocess";
+
+    /// Validate plugin configuration and fill in defaults.
+    pub fn deserialize_config(cfg: toml::Value) -> Fallible<Box<dyn PluginSettings>> {
+        let settings: SubprocessPluginSettings = cfg.try_into()?;
+
+        ensure!(!settings.command.as_os_str().is_empty(), "empty command");
+        ensure!(settings.timeout_secs > 0, "zero timeout_secs");
+
+        Ok(Box::new(settings))
+    }
+}
+
+impl ExternalPlugin for SubprocessPlugin {
+    fn run_external(self: &Self, io: ExternalIO) -> AsyncIO<ExternalIO> {
+        let (command, args, timeout) = (self.command.clone(), self.args.clone(), self.timeout);
+
+        trace!("running subprocess plugin {}", command.display());
+
+        // The executable is run on a separate thread to not block the executor.
+        let (tx, rx) = oneshot::channel();
+        thread::spawn(move || {
+            let _ = tx.send(run(&command, &args, timeout, io.bytes));
+        });
+
+        Box::new(rx.then(|result| match result {
+            Ok(result) => result,
+            Err(_) => Err(format_err!("subprocess plugin terminated unexpectedly")),
+        }))
+    }
+}
+
+/// Runs the executable on the given input and returns its output.
+fn run(command: &Path, args: &[String], timeout: Duration, input: Vec<u8>) -> Fallible<ExternalIO> {
+    let spawned = Command::new(command)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn();
+    if let Err(e) = &spawned {
+        error!("failed to spawn {}: {}", command.display(), e);
+    }
+    let mut child = spawned.context(format!("failed to spawn {}", command.display()))?;
+
+    // Write and read on separate threads, so that full pipes can't block the executable.
+    let mut stdin = child.stdin.take().expect("stdin to be piped");
+    let writer = thread::spawn(move || stdin.write_all(&input));
+    let stdout = read_to_end(child.stdout.take().expect("stdout to be piped"));
+    let stderr = read_to_end(child.stderr.take().expect("stderr to be piped"));
+
+    // The pipes are closed once the executable has exited or was killed, so
+    // its output is collected on every path before returning.
+    let status = wait(&mut child, timeout);
+    if let Ok(Err(e)) = writer.join() {
+        debug!("failed to write to stdin of {}: {}", command.display(), e);
+    }
+    let stdout = join(stdout);
+    let failed = status
+        .as_ref()
+        .map(|status| !status.success())
+        .unwrap_or(true);
+    match join(stderr) {
+        Ok(stderr) => log_stderr(command, &stderr, failed),
+        Err(e) => warn!("failed to read stderr of {}: {}", command.display(), e),
+    }
+
+    let status = status.map_err(|e| format_err!("failed to run {}: {}", command.display(), e))?;
+    let stdout = stdout?;
+
+    let output = ExternalIO { bytes: stdout };
+    let result = if status.success() {
+        let exchange: PluginExchange = output.try_into()?;
+        PluginResult::PluginExchange(exchange)
+    } else if !output.bytes.is_empty() {
+        let error = PluginError::try_from(output).context(format!(
+            "{} exited with {}",
+            command.display(),
+            status
+        ))?;
+        PluginResult::PluginError(error)
+    } else {
+        bail!("{} exited with {}", command.display(), status);
+    };
+
+    match result {
+        PluginResult::PluginExchange(exchange) => exchange.try_into(),
+        PluginResult::PluginError(error) => error.into(),
+    }
+}
+
+/// Waits for the child to exit and kills it once the timeout has passed.
+fn wait(child: &mut Child, timeout: Duration) -> Fallible<ExitStatus> {
+    let start = Instant::now();
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(status);
+        }
+        if start.elapsed() >= timeout {
+            child.kill()?;
+            child.wait()?;
+            bail!("timed out after {:?}", timeout);
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Logs the stderr of the executable, at warning level if it failed.
+fn log_stderr(command: &Path, stderr: &[u8], failed: bool) {
+    let level = if failed {
+        log::Level::Warn
+    } else {
+        log::Level::Info
+    };
+    for line in String::from_utf8_lossy(stderr).lines() {
+        log!(level, "[{}] {}", command.display(), line);
+    }
+}
+
+fn read_to_end<R>(mut reader: R) -> thread::JoinHandle<std::io::Result<Vec<u8>>>
+where
+    R: Read + Send + 'static,
+{
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf).map(|_| buf)
+    })
+}
+
+fn join(handle: thread::JoinHandle<std::io::Result<Vec<u8>>>) -> Fallible<Vec<u8>> {
+    Ok(handle
+        .join()
+        .map_err(|_| format_err!("reader thread panicked"))??)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugins::{interface, InternalIO};
+    use crate::testing::generate_graph;
+    use commons::testing::init_runtime;
+
+    /// Returns a plugin which runs the test executable built from
+    /// `examples/subprocess_plugin.rs`.
+    fn test_plugin(timeout: Duration) -> Fallible<SubprocessPlugin> {
+        let mut command = std::env::current_exe()?;
+        command.pop();
+        if command.ends_with("deps") {
+            command.pop();
+        }
+        command.push("examples");
+        command.push(format!("subprocess_plugin{}", std::env::consts::EXE_SUFFIX));
+        ensure!(
+            command.exists(),
+            "{} not found, build it with `cargo build --examples`",
+            command.display()
+        );
+
+        Ok(SubprocessPlugin {
+            command,
+            args: vec![],
+            timeout,
+        })
+    }
+
+    fn test_input(action: Option<&str>) -> InternalIO {
+        InternalIO {
+            graph: generate_graph(),
+            parameters: action
+                .map(|action| ("action".to_string(), action.to_string()))
+                .into_iter()
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn subprocess_plugin_success() -> Fallible<()> {
+        let mut runtime = init_runtime()?;
+        let plugin = test_plugin(Duration::from_secs(10))?;
+
+        for action in &[None, Some("log")] {
+            let input = test_input(*action);
+            let output = runtime.block_on(plugin.run_external(input.clone().try_into()?))?;
+            assert_eq!(InternalIO::try_from(output)?, input);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn subprocess_plugin_error() -> Fallible<()> {
+        let mut runtime = init_runtime()?;
+        let plugin = test_plugin(Duration::from_secs(10))?;
+
+        let output = runtime.block_on(plugin.run_external(test_input(Some("fail")).try_into()?));
+        match PluginResult::try_from(output)? {
+            PluginResult::PluginError(error) => {
+                assert_eq!(error.get_kind(), interface::PluginError_Kind::INVALID_PARAM);
+                assert_eq!(error.get_value(), "failed on purpose");
+            }
+            result => bail!("expected a plugin error, got {:?}", result),
+        }
+
+        let output = runtime.block_on(plugin.run_external(test_input(Some("exit")).try_into()?));
+        assert!(output.is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn subprocess_plugin_timeout() -> Fallible<()> {
+        let mut runtime = init_runtime()?;
+        let plugin = test_plugin(Duration::from_millis(200))?;
+
+        let start = Instant::now();
+        let output = runtime.block_on(plugin.run_external(test_input(Some("sleep")).try_into()?));
+        let err = output.unwrap_err();
+        assert!(err.to_string().contains("timed out"), "{}", err);
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        Ok(())
+    }
+
+    #[test]
+    fn subprocess_plugin_missing_command() -> Fallible<()> {
+        let mut runtime = init_runtime()?;
+        let plugin = SubprocessPlugin {
+            command: PathBuf::from("/nonexistent/plugin"),
+            args: vec![],
+            timeout: Duration::from_secs(10),
+        };
+
+        assert!(runtime
+            .block_on(plugin.run_external(test_input(None).try_into()?))
+            .is_err());
+
+        Ok(())
+    }
+}
@@ -1,27 +1,229 @@
 //! The web module can be used to talk to webservice which expose an endpoint
 //! according to the protobuf scheme
 
+use crate::plugins::interface::{PluginError, PluginExchange};
+use crate::plugins::{
+    AsyncIO, BoxedPlugin, ExternalIO, ExternalPlugin, ExternalPluginWrapper, PluginResult,
+    PluginSettings,
+};
+use crate::PROTOBUF_CONTENT_TYPE;
+use failure::{Fallible, ResultExt};
+use futures::{Future, Stream};
+use prometheus::Registry;
+use reqwest::header::{HeaderValue, ACCEPT, CONTENT_TYPE};
+use reqwest::r#async::Client;
+use std::convert::TryInto;
 use std::path::PathBuf;
+use std::time::Duration;
 use url::Url;
 
+/// Default timeout for requests to the plugin, in seconds.
+pub const DEFAULT_TIMEOUT_SECS: u64 = 30;
+
+/// Default maximum size of the plugin's response body in bytes.
+pub const DEFAULT_MAX_RESPONSE_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Plugin settings.
+#[derive(Clone, CustomDebug, Deserialize, SmartDefault)]
+#[serde(default)]
+struct WebPluginSettings {
+    url: String,
+
+    #[default(DEFAULT_TIMEOUT_SECS)]
+    timeout_secs: u64,
+
+    #[default(DEFAULT_MAX_RESPONSE_BYTES)]
+    max_response_bytes: u64,
+
+    /// PEM-encoded CA certificate to verify the plugin's certificate with.
+    ca_cert_path: Option<PathBuf>,
+
+    /// PKCS#12 archive with the client certificate and key to authenticate with.
+    client_cert_path: Option<PathBuf>,
+
+    #[debug(skip)]
+    client_cert_password: String,
+}
+
+impl PluginSettings for WebPluginSettings {
+    fn build_plugin(&self, _: Option<&Registry>) -> Fallible<BoxedPlugin> {
+        let cfg = self.clone();
+        let plugin = WebPluginClient::try_new(
+            Url::parse(&cfg.url)?,
+            Duration::from_secs(cfg.timeout_secs),
+            cfg.max_response_bytes,
+            cfg.ca_cert_path,
+            cfg.client_cert_path,
+            &cfg.client_cert_password,
+        )?;
+        Ok(new_plugin!(ExternalPluginWrapper(plugin)))
+    }
+}
+
 /// Struct for implementing the client side of a web plugin
+///
+/// The client POSTs the protobuf `PluginExchange` to the plugin's URL.
+/// A successful response carries the resulting `PluginExchange`, any other
+/// response with a protobuf body carries a `PluginError`.
 #[derive(Debug)]
-pub struct _WebPluginClient {
+pub struct WebPluginClient {
     pub url: Url,
     pub timeout: std::time::Duration,
+    pub max_response_bytes: u64,
     pub ca_cert_path: Option<PathBuf>,
     pub client_cert_path: Option<PathBuf>,
+    client: Client,
+}
+
+impl WebPluginClient {
+    /// Plugin name, for configuration.
+    pub const PLUGIN_NAME: &'static str = "web";
+
+    /// Validate plugin configuration and fill in defaults.
+    pub fn deserialize_config(cfg: toml::Value) -> Fallible<Box<dyn PluginSettings>> {
+        let settings: WebPluginSettings = cfg.try_into()?;
+
+        ensure!(!settings.url.is_empty(), "empty url");
+        Url::parse(&settings.url).context(format!("invalid url '{}'", settings.url))?;
+        ensure!(settings.timeout_secs > 0, "zero timeout_secs");
+        ensure!(settings.max_response_bytes > 0, "zero max_response_bytes");
+
+        Ok(Box::new(settings))
+    }
+
+    /// Creates a client for the web plugin at the given URL.
+    ///
+    /// Responses with a body larger than `max_response_bytes` are rejected.
+    /// The client certificate is read from a PKCS#12 archive which is
+    /// protected by the given password.
+    pub fn try_new(
+        url: Url,
+        timeout: Duration,
+        max_response_bytes: u64,
+        ca_cert_path: Option<PathBuf>,
+        client_cert_path: Option<PathBuf>,
+        client_cert_password: &str,
+    ) -> Fallible<Self> {
+        let mut builder = Client::builder().timeout(timeout);
+
+        if let Some(path) = &ca_cert_path {
+            let pem = std::fs::read(path)
+                .context(format!("could not read CA certificate {}", path.display()))?;
+            builder = builder.add_root_certificate(reqwest::Certificate::from_pem(&pem)?);
+        }
+
+        if let Some(path) = &client_cert_path {
+            let der = std::fs::read(path).context(format!(
+                "could not read client certificate {}",
+                path.display()
+            ))?;
+            builder = builder.identity(reqwest::Identity::from_pkcs12_der(
+                &der,
+                client_cert_password,
+            )?);
+        }
+
+        Ok(Self {
+            url,
+            timeout,
+            max_response_bytes,
+            ca_cert_path,
+            client_cert_path,
+            client: builder.build()?,
+        })
+    }
+}
+
+impl ExternalPlugin for WebPluginClient {
+    fn run_external(self: &Self, io: ExternalIO) -> AsyncIO<ExternalIO> {
+        let url = self.url.clone();
+        let max_response_bytes = self.max_response_bytes;
+
+        trace!("running web plugin at {}", url);
+
+        let future_io = self
+            .client
+            .post(self.url.clone())
+            .header(
+                CONTENT_TYPE,
+                HeaderValue::from_static(PROTOBUF_CONTENT_TYPE),
+            )
+            .header(ACCEPT, HeaderValue::from_static(PROTOBUF_CONTENT_TYPE))
+            .body(io.bytes)
+            .send()
+            .map_err(failure::Error::from)
+            .and_then(move |res| {
+                let status = res.status();
+                let is_protobuf = res
+                    .headers()
+                    .get(CONTENT_TYPE)
+                    .and_then(|value| value.to_str().ok())
+                    .map(is_protobuf_media_type)
+                    .unwrap_or(false);
+                res.into_body()
+                    .map_err(failure::Error::from)
+                    .fold(Vec::new(), move |mut body, chunk| -> Fallible<Vec<u8>> {
+                        // Stop reading as soon as the body gets too large.
+                        ensure!(
+                            (body.len() + chunk.len()) as u64 <= max_response_bytes,
+                            "response body exceeds the maximum size of {} bytes",
+                            max_response_bytes
+                        );
+                        body.extend_from_slice(&chunk);
+                        Ok(body)
+                    })
+                    .map(move |body| (status, is_protobuf, body))
+            })
+            .map_err(move |e| format_err!("request to web plugin at {} failed: {}", url, e))
+            .and_then(|(status, is_protobuf, bytes)| -> Fallible<ExternalIO> {
+                let io = ExternalIO { bytes };
+                let result = if status.is_success() {
+                    let exchange: PluginExchange = io.try_into()?;
+                    PluginResult::PluginExchange(exchange)
+                } else if is_protobuf {
+                    let error: PluginError = io.try_into()?;
+                    PluginResult::PluginError(error)
+                } else {
+                    bail!("web plugin responded with status {}", status);
+                };
+
+                match result {
+                    PluginResult::PluginExchange(exchange) => exchange.try_into(),
+                    PluginResult::PluginError(error) => error.into(),
+                }
+            });
+
+        Box::new(future_io)
+    }
+}
+
+/// Whether the given `Content-Type` value denotes protobuf, ignoring any parameters.
+fn is_protobuf_media_type(content_type: &str) -> bool {
+    content_type
+        .split(';')
+        .next()
+        .map(|media_type| {
+            media_type
+                .trim()
+                .eq_ignore_ascii_case(PROTOBUF_CONTENT_TYPE)
+        })
+        .unwrap_or(false)
 }
 
 #[cfg(test)]
 mod tests {
+    use super::*;
     use crate as cincinnati;
     use crate::plugins::AsyncIO;
-    use crate::plugins::{interface, ExternalIO, ExternalPlugin, InternalIO, PluginResult};
+    use crate::plugins::{
+        interface, ExternalError, ExternalIO, ExternalPlugin, InternalIO, PluginResult,
+    };
     use crate::testing::generate_graph;
+    use actix_web::{web, App, HttpResponse, HttpServer};
     use commons::testing::init_runtime;
     use failure::Fallible;
-    use std::convert::TryInto;
+    use protobuf::Message;
+    use std::convert::{TryFrom, TryInto};
 
     struct DummyWebClient {
         callback: Box<dyn Fn(interface::PluginExchange) -> PluginResult + Send + Sync>,
@@ -53,7 +255,7 @@ mod tests {
         let mut runtime = init_runtime().unwrap();
 
         fn callback(mut input: interface::PluginExchange) -> PluginResult {
-            let graph: cincinnati::Graph = input.take_graph().into();
+            let graph = cincinnati::Graph::try_from(input.take_graph()).expect("valid graph");
 
             trace!(
                 "[external passthrough plugin] got graph with {} nodes",
@@ -61,7 +263,7 @@ mod tests {
             );
 
             let mut exchange: interface::PluginExchange = interface::PluginExchange::new();
-            exchange.set_graph(graph.into());
+            exchange.set_graph(interface::Graph::try_from(graph).expect("concrete graph"));
             exchange.set_parameters(input.get_parameters().to_owned());
 
             PluginResult::PluginExchange(exchange)
@@ -122,4 +324,231 @@ mod tests {
 
         assert_eq!(expected_result, output_result);
     }
+
+    /// Starts a local web server which handles POST requests to `/` with the
+    /// given handler, and returns its URL.
+    fn start_server<F>(handler: F) -> Fallible<Url>
+    where
+        F: Fn(web::Bytes) -> HttpResponse + Clone + Send + 'static,
+    {
+        let (tx, rx) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            let server =
+                HttpServer::new(move || App::new().route("/", web::post().to(handler.clone())))
+                    .workers(1)
+                    .bind("127.0.0.1:0")
+                    .expect("the server to bind");
+            tx.send(server.addrs()[0])
+                .expect("to send the server address");
+            server.run().expect("the server to run");
+        });
+
+        Ok(Url::parse(&format!("http://{}/", rx.recv()?))?)
+    }
+
+    fn test_input() -> InternalIO {
+        InternalIO {
+            graph: generate_graph(),
+            parameters: [("hello".to_string(), "plugin".to_string())]
+                .iter()
+                .cloned()
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn web_plugin_success() -> Fallible<()> {
+        let mut runtime = init_runtime()?;
+
+        let url = start_server(|body: web::Bytes| {
+            HttpResponse::Ok()
+                .content_type(PROTOBUF_CONTENT_TYPE)
+                .body(body)
+        })?;
+        let plugin = WebPluginClient::try_new(
+            url,
+            Duration::from_secs(10),
+            DEFAULT_MAX_RESPONSE_BYTES,
+            None,
+            None,
+            "",
+        )?;
+
+        let input_internal = test_input();
+        let output_external =
+            runtime.block_on(plugin.run_external(input_internal.clone().try_into()?))?;
+        let output_internal: InternalIO = output_external.try_into()?;
+
+        assert_eq!(output_internal, input_internal);
+
+        Ok(())
+    }
+
+    #[test]
+    fn web_plugin_error() -> Fallible<()> {
+        let mut runtime = init_runtime()?;
+
+        let mut expected_error = interface::PluginError::new();
+        expected_error.set_kind(interface::PluginError_Kind::INVALID_PARAM);
+        expected_error.set_value("test succeeds on error".to_string());
+
+        let error_bytes = expected_error.write_to_bytes()?;
+        let url = start_server(move |_: web::Bytes| {
+            HttpResponse::BadRequest()
+                .content_type(PROTOBUF_CONTENT_TYPE)
+                .body(error_bytes.clone())
+        })?;
+        let plugin = WebPluginClient::try_new(
+            url,
+            Duration::from_secs(10),
+            DEFAULT_MAX_RESPONSE_BYTES,
+            None,
+            None,
+            "",
+        )?;
+
+        let output_result_external =
+            runtime.block_on(plugin.run_external(test_input().try_into()?));
+        let output_result: PluginResult = output_result_external.try_into()?;
+
+        assert_eq!(PluginResult::PluginError(expected_error), output_result);
+
+        Ok(())
+    }
+
+    #[test]
+    fn web_plugin_error_with_content_type_parameters() -> Fallible<()> {
+        let mut runtime = init_runtime()?;
+
+        let mut expected_error = interface::PluginError::new();
+        expected_error.set_kind(interface::PluginError_Kind::INVALID_PARAM);
+        expected_error.set_value("test succeeds on error".to_string());
+
+        let error_bytes = expected_error.write_to_bytes()?;
+        let url = start_server(move |_: web::Bytes| {
+            HttpResponse::BadRequest()
+                .content_type(format!("{}; charset=binary", PROTOBUF_CONTENT_TYPE))
+                .body(error_bytes.clone())
+        })?;
+        let plugin = WebPluginClient::try_new(
+            url,
+            Duration::from_secs(10),
+            DEFAULT_MAX_RESPONSE_BYTES,
+            None,
+            None,
+            "",
+        )?;
+
+        let output_result_external =
+            runtime.block_on(plugin.run_external(test_input().try_into()?));
+        let output_result: PluginResult = output_result_external.try_into()?;
+
+        assert_eq!(PluginResult::PluginError(expected_error), output_result);
+
+        Ok(())
+    }
+
+    #[test]
+    fn web_plugin_response_too_large() -> Fallible<()> {
+        let mut runtime = init_runtime()?;
+
+        let url = start_server(|body: web::Bytes| {
+            HttpResponse::Ok()
+                .content_type(PROTOBUF_CONTENT_TYPE)
+                .body(body)
+        })?;
+        let input: ExternalIO = test_input().try_into()?;
+        let max_response_bytes = input.bytes.len() as u64 - 1;
+        let plugin = WebPluginClient::try_new(
+            url,
+            Duration::from_secs(10),
+            max_response_bytes,
+            None,
+            None,
+            "",
+        )?;
+
+        let err = runtime.block_on(plugin.run_external(input)).unwrap_err();
+        assert!(err.to_string().contains("maximum size"), "{}", err);
+
+        Ok(())
+    }
+
+    #[test]
+    fn protobuf_media_type() {
+        assert!(is_protobuf_media_type(PROTOBUF_CONTENT_TYPE));
+        assert!(is_protobuf_media_type(
+            "Application/X-Protobuf; charset=binary"
+        ));
+        assert!(!is_protobuf_media_type("application/json"));
+        assert!(!is_protobuf_media_type("application/x-protobuf-text"));
+    }
+
+    #[test]
+    fn web_plugin_unexpected_response() -> Fallible<()> {
+        let mut runtime = init_runtime()?;
+
+        let url = start_server(|_: web::Bytes| HttpResponse::NotFound().body("NOT_FOUND"))?;
+        let plugin = WebPluginClient::try_new(
+            url,
+            Duration::from_secs(10),
+            DEFAULT_MAX_RESPONSE_BYTES,
+            None,
+            None,
+            "",
+        )?;
+
+        let output_result_external =
+            runtime.block_on(plugin.run_external(test_input().try_into()?));
+        let err = output_result_external.unwrap_err();
+        assert!(err.downcast_ref::<ExternalError>().is_none());
+        assert!(err.to_string().contains("404"), "{}", err);
+
+        Ok(())
+    }
+
+    #[test]
+    fn web_plugin_timeout() -> Fallible<()> {
+        let mut runtime = init_runtime()?;
+
+        let url = start_server(|body: web::Bytes| {
+            std::thread::sleep(Duration::from_secs(2));
+            HttpResponse::Ok()
+                .content_type(PROTOBUF_CONTENT_TYPE)
+                .body(body)
+        })?;
+        let plugin = WebPluginClient::try_new(
+            url,
+            Duration::from_millis(100),
+            DEFAULT_MAX_RESPONSE_BYTES,
+            None,
+            None,
+            "",
+        )?;
+
+        assert!(runtime
+            .block_on(plugin.run_external(test_input().try_into()?))
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn web_plugin_missing_certificates() {
+        let url = Url::parse("https://localhost/").unwrap();
+        for (ca_cert_path, client_cert_path) in vec![
+            (Some(PathBuf::from("/nonexistent/ca.pem")), None),
+            (None, Some(PathBuf::from("/nonexistent/client.p12"))),
+        ] {
+            assert!(WebPluginClient::try_new(
+                url.clone(),
+                Duration::from_secs(10),
+                DEFAULT_MAX_RESPONSE_BYTES,
+                ca_cert_path,
+                client_cert_path,
+                ""
+            )
+            .is_err());
+        }
+    }
 }
@@ -0,0 +1,414 @@
+//! Server side of web plugins
+//!
+//! This serves any `InternalPlugin` as an external web plugin, which can be
+//! called with `WebPluginClient`. Besides the plugin endpoint, the server
+//! exposes liveness, readiness and metrics endpoints, so that a plugin can be
+//! deployed as a sidecar.
+
+use crate::plugins::interface::{PluginError, PluginError_Kind};
+use crate::plugins::{ExternalError, ExternalIO, InternalIO, InternalPlugin};
+use crate::PROTOBUF_CONTENT_TYPE;
+use actix_web::http::StatusCode;
+use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer};
+use commons::metrics::{self, HasRegistry};
+use commons::GraphError;
+use failure::Fallible;
+use futures::{future, Future};
+use prometheus::{Counter, CounterVec, Opts, Registry};
+use std::convert::{TryFrom, TryInto};
+use std::sync::Arc;
+
+/// Path of the plugin endpoint.
+pub static PLUGIN_PATH: &str = "/v1/plugin";
+
+/// Default maximum size of the request body of the plugin endpoint in bytes.
+pub const DEFAULT_MAX_BODY_BYTES: usize = 256 * 1024;
+
+/// Shared state of a web plugin server.
+#[derive(Clone, CustomDebug)]
+pub struct WebPluginServer {
+    #[debug(skip)]
+    plugin: Arc<dyn InternalPlugin + Send + Sync>,
+
+    #[debug(skip)]
+    registry: &'static Registry,
+
+    max_body_bytes: usize,
+
+    #[debug(skip)]
+    requests_total: Counter,
+
+    #[debug(skip)]
+    errors_total: CounterVec,
+}
+
+impl HasRegistry for WebPluginServer {
+    fn registry(&self) -> &'static Registry {
+        self.registry
+    }
+}
+
+impl WebPluginServer {
+    /// Creates a server for the given plugin and registers its metrics.
+    pub fn try_new<P>(plugin: P, registry: &'static Registry) -> Fallible<Self>
+    where
+        P: InternalPlugin + Send + Sync + 'static,
+    {
+        let requests_total = Counter::new(
+            "web_plugin_requests_total",
+            "Total number of requests to the web plugin",
+        )?;
+        let errors_total = CounterVec::new(
+            Opts::new(
+                "web_plugin_errors_total",
+                "Total number of failed requests to the web plugin, by error kind",
+            ),
+            &["kind"],
+        )?;
+
+        registry.register(Box::new(requests_total.clone()))?;
+        registry.register(Box::new(errors_total.clone()))?;
+
+        Ok(Self {
+            plugin: Arc::new(plugin),
+            registry,
+            max_body_bytes: DEFAULT_MAX_BODY_BYTES,
+            requests_total,
+            errors_total,
+        })
+    }
+
+    /// Sets the maximum size of the request body of the plugin endpoint.
+    ///
+    /// Larger requests are rejected before they reach the plugin.
+    pub fn max_body_bytes(mut self, max_body_bytes: usize) -> Self {
+        self.max_body_bytes = max_body_bytes;
+        self
+    }
+
+    /// Returns the resource serving the plugin endpoint at `PLUGIN_PATH`.
+    pub fn plugin_resource(&self) -> actix_web::Resource {
+        web::resource(PLUGIN_PATH)
+            .data(web::PayloadConfig::new(self.max_body_bytes))
+            .route(web::post().to(serve_plugin))
+    }
+
+    /// Serves the plugin on the given address until the server is stopped.
+    ///
+    /// The plugin endpoint is served at `PLUGIN_PATH`, the status endpoints
+    /// at `/liveness`, `/readiness` and `/metrics`.
+    pub fn run<A>(self, address: A) -> Fallible<()>
+    where
+        A: std::net::ToSocketAddrs,
+    {
+        HttpServer::new(move || {
+            App::new()
+                .register_data(web::Data::new(self.clone()))
+                .service(self.plugin_resource())
+                .service(web::resource("/liveness").route(web::get().to(serve_status)))
+                .service(
+                    web::resource("/metrics")
+                        .route(web::get().to(metrics::serve::<WebPluginServer>)),
+                )
+                .service(web::resource("/readiness").route(web::get().to(serve_status)))
+        })
+        .bind(address)?
+        .run()?;
+
+        Ok(())
+    }
+
+    /// Returns the response for the given error and counts it.
+    fn error_response(&self, error: PluginError) -> HttpResponse {
+        use protobuf::Message;
+
+        let kind = error.get_kind();
+        self.errors_total
+            .with_label_values(&[&format!("{:?}", kind).to_lowercase()])
+            .inc();
+
+        match error.write_to_bytes() {
+            Ok(bytes) => HttpResponse::build(status_code(kind))
+                .content_type(PROTOBUF_CONTENT_TYPE)
+                .body(bytes),
+            Err(e) => {
+                error!("failed to serialize plugin error: {}", e);
+                HttpResponse::InternalServerError().finish()
+            }
+        }
+    }
+}
+
+/// Run the plugin on the `PluginExchange` in the request body.
+///
+/// Status:
+///  * 200: the body contains the resulting `PluginExchange`.
+///  * Any other code: the body contains a `PluginError`, see `status_code`.
+pub fn serve_plugin(
+    req: HttpRequest,
+    body: web::Bytes,
+) -> Box<dyn Future<Item = HttpResponse, Error = failure::Error>> {
+    let server = req
+        .app_data::<WebPluginServer>()
+        .expect(commons::MISSING_APPSTATE_PANIC_MSG)
+        .clone();
+    server.requests_total.inc();
+
+    let input = ExternalIO {
+        bytes: body.to_vec(),
+    };
+    let input: InternalIO = match input.try_into() {
+        Ok(input) => input,
+        Err(e) => {
+            let mut error = PluginError::new();
+            error.set_kind(PluginError_Kind::INVALID_GRAPH);
+            error.set_value(e.to_string());
+            return Box::new(future::ok(server.error_response(error)));
+        }
+    };
+
+    let resp = server
+        .plugin
+        .run_internal(input)
+        .and_then(ExternalIO::try_from)
+        .then(move |result| -> Fallible<HttpResponse> {
+            match result {
+                Ok(output) => Ok(HttpResponse::Ok()
+                    .content_type(PROTOBUF_CONTENT_TYPE)
+                    .body(output.bytes)),
+                Err(e) => Ok(server.error_response(plugin_error(e))),
+            }
+        });
+    Box::new(resp)
+}
+
+/// Expose liveness and readiness status.
+///
+/// Status:
+///  * Live and ready (200 code): the server is able to handle requests.
+pub fn serve_status(
+    _: HttpRequest,
+) -> Box<dyn Future<Item = HttpResponse, Error = failure::Error>> {
+    Box::new(future::ok(HttpResponse::Ok().finish()))
+}
+
+/// Converts an error of the plugin to a `PluginError`.
+///
+/// Errors which already are a `PluginError` are passed through, `GraphError`s
+/// are mapped to the corresponding kind and any other error is generic.
+fn plugin_error(error: failure::Error) -> PluginError {
+    let error = match error.downcast::<ExternalError>() {
+        Ok(ExternalError::PluginError(error)) => return error,
+        Err(error) => error,
+    };
+
+    let (kind, value) = match error.downcast_ref::<GraphError>() {
+        Some(graph_error) => {
+            let kind = match graph_error {
+                GraphError::MissingParams(_) | GraphError::InvalidParams(_) => {
+                    PluginError_Kind::INVALID_PARAM
+                }
+                GraphError::FailedUpstreamFetch(_) | GraphError::FailedUpstreamRequest(_) => {
+                    PluginError_Kind::FAILED_DEPENDENCY
+                }
+                _ => PluginError_Kind::INTERNAL_FAILURE,
+            };
+            (kind, graph_error.value())
+        }
+        None => (PluginError_Kind::GENERIC, error.to_string()),
+    };
+
+    let mut plugin_error = PluginError::new();
+    plugin_error.set_kind(kind);
+    plugin_error.set_value(value);
+    plugin_error
+}
+
+/// Returns the HTTP status code for the given kind of error.
+fn status_code(kind: PluginError_Kind) -> StatusCode {
+    match kind {
+        PluginError_Kind::INVALID_GRAPH | PluginError_Kind::INVALID_PARAM => {
+            StatusCode::BAD_REQUEST
+        }
+        PluginError_Kind::FAILED_DEPENDENCY => StatusCode::FAILED_DEPENDENCY,
+        PluginError_Kind::GENERIC | PluginError_Kind::INTERNAL_FAILURE => {
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugins::AsyncIO;
+    use crate::testing::generate_graph;
+    use actix_web::test::TestRequest;
+    use commons::testing::init_runtime;
+
+    /// Plugin which passes its input through, or fails as requested by the
+    /// `fail` parameter.
+    #[derive(Debug)]
+    struct TestPlugin;
+
+    impl InternalPlugin for TestPlugin {
+        fn run_internal(self: &Self, io: InternalIO) -> AsyncIO<InternalIO> {
+            let result = match io.parameters.get("fail").map(String::as_str) {
+                None => Ok(io),
+                Some("params") => Err(GraphError::InvalidParams("fail".to_string()).into()),
+                Some(other) => Err(format_err!("failed on purpose: {}", other)),
+            };
+            Box::new(future::result(result))
+        }
+    }
+
+    fn test_server() -> Fallible<WebPluginServer> {
+        let registry: &'static Registry =
+            Box::leak(Box::new(metrics::new_registry(Some("test".to_string()))?));
+        WebPluginServer::try_new(TestPlugin, registry)
+    }
+
+    fn test_input(fail: Option<&str>) -> InternalIO {
+        InternalIO {
+            graph: generate_graph(),
+            parameters: fail
+                .map(|fail| ("fail".to_string(), fail.to_string()))
+                .into_iter()
+                .collect(),
+        }
+    }
+
+    fn body_bytes(resp: &HttpResponse) -> Fallible<Vec<u8>> {
+        match resp.body() {
+            actix_web::body::ResponseBody::Body(actix_web::body::Body::Bytes(bytes)) => {
+                Ok(bytes.to_vec())
+            }
+            _ => bail!("expected bytes in body"),
+        }
+    }
+
+    fn run_plugin(server: &WebPluginServer, body: Vec<u8>) -> Fallible<HttpResponse> {
+        let mut runtime = init_runtime()?;
+        let req = TestRequest::post().data(server.clone()).to_http_request();
+        runtime.block_on(serve_plugin(req, web::Bytes::from(body)))
+    }
+
+    #[test]
+    fn serve_plugin_success() -> Fallible<()> {
+        let server = test_server()?;
+        let input = test_input(None);
+
+        let resp = run_plugin(&server, ExternalIO::try_from(input.clone())?.bytes)?;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let output: InternalIO = ExternalIO {
+            bytes: body_bytes(&resp)?,
+        }
+        .try_into()?;
+        assert_eq!(output, input);
+        assert_eq!(server.requests_total.get() as u64, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn serve_plugin_errors() -> Fallible<()> {
+        let server = test_server()?;
+
+        for (input, expected_status, expected_kind) in vec![
+            (
+                ExternalIO::try_from(test_input(Some("params")))?.bytes,
+                StatusCode::BAD_REQUEST,
+                PluginError_Kind::INVALID_PARAM,
+            ),
+            (
+                ExternalIO::try_from(test_input(Some("other")))?.bytes,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                PluginError_Kind::GENERIC,
+            ),
+            (
+                b"not a plugin exchange".to_vec(),
+                StatusCode::BAD_REQUEST,
+                PluginError_Kind::INVALID_GRAPH,
+            ),
+        ] {
+            let resp = run_plugin(&server, input)?;
+            assert_eq!(resp.status(), expected_status);
+
+            let error: PluginError = protobuf::parse_from_bytes(&body_bytes(&resp)?)?;
+            assert_eq!(error.get_kind(), expected_kind);
+        }
+        assert_eq!(server.requests_total.get() as u64, 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn serve_plugin_rejects_large_bodies() -> Fallible<()> {
+        for (server, body_bytes) in vec![
+            (test_server()?, DEFAULT_MAX_BODY_BYTES + 1),
+            (test_server()?.max_body_bytes(16), 17),
+        ] {
+            let mut app = actix_web::test::init_service(
+                App::new()
+                    .register_data(web::Data::new(server.clone()))
+                    .service(server.plugin_resource()),
+            );
+            let req = TestRequest::post()
+                .uri(PLUGIN_PATH)
+                .set_payload(vec![0; body_bytes])
+                .to_request();
+
+            let resp = actix_web::test::call_service(&mut app, req);
+            assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+            assert_eq!(server.requests_total.get() as u64, 0);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn plugin_errors_pass_through() {
+        let mut error = PluginError::new();
+        error.set_kind(PluginError_Kind::FAILED_DEPENDENCY);
+        error.set_value("upstream unavailable".to_string());
+
+        let external: Fallible<ExternalIO> = error.clone().into();
+        assert_eq!(plugin_error(external.unwrap_err()), error);
+        assert_eq!(
+            plugin_error(GraphError::FailedUpstreamFetch("404".to_string()).into()).get_kind(),
+            PluginError_Kind::FAILED_DEPENDENCY
+        );
+    }
+
+    #[test]
+    fn serve_status_and_metrics() -> Fallible<()> {
+        let mut runtime = init_runtime()?;
+        let server = test_server()?;
+        run_plugin(
+            &server,
+            ExternalIO::try_from(test_input(Some("params")))?.bytes,
+        )?;
+
+        let req = TestRequest::default().to_http_request();
+        assert_eq!(
+            runtime.block_on(serve_status(req))?.status(),
+            StatusCode::OK
+        );
+
+        let req = TestRequest::default().data(server).to_http_request();
+        let resp = runtime.block_on(metrics::serve::<WebPluginServer>(req))?;
+        let metrics = String::from_utf8(body_bytes(&resp)?)?;
+        assert!(
+            metrics.contains("test_web_plugin_requests_total 1\n"),
+            "{}",
+            metrics
+        );
+        assert!(
+            metrics.contains(r#"test_web_plugin_errors_total{kind="invalid_param"} 1"#),
+            "{}",
+            metrics
+        );
+
+        Ok(())
+    }
+}
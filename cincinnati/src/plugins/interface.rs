@@ -31,6 +31,7 @@ pub struct Graph {
     // message fields
     pub nodes: ::protobuf::RepeatedField<Graph_Node>,
     pub edges: ::protobuf::RepeatedField<Graph_Edge>,
+    pub conditional_edges: ::protobuf::RepeatedField<Graph_Edge>,
     // special fields
     pub unknown_fields: ::protobuf::UnknownFields,
     pub cached_size: ::protobuf::CachedSize,
@@ -96,6 +97,31 @@ impl Graph {
     pub fn take_edges(&mut self) -> ::protobuf::RepeatedField<Graph_Edge> {
         ::std::mem::replace(&mut self.edges, ::protobuf::RepeatedField::new())
     }
+
+    // repeated .Graph.Edge conditional_edges = 3;
+
+
+    pub fn get_conditional_edges(&self) -> &[Graph_Edge] {
+        &self.conditional_edges
+    }
+    pub fn clear_conditional_edges(&mut self) {
+        self.conditional_edges.clear();
+    }
+
+    // Param is passed by value, moved
+    pub fn set_conditional_edges(&mut self, v: ::protobuf::RepeatedField<Graph_Edge>) {
+        self.conditional_edges = v;
+    }
+
+    // Mutable pointer to the field.
+    pub fn mut_conditional_edges(&mut self) -> &mut ::protobuf::RepeatedField<Graph_Edge> {
+        &mut self.conditional_edges
+    }
+
+    // Take field
+    pub fn take_conditional_edges(&mut self) -> ::protobuf::RepeatedField<Graph_Edge> {
+        ::std::mem::replace(&mut self.conditional_edges, ::protobuf::RepeatedField::new())
+    }
 }
 
 impl ::protobuf::Message for Graph {
@@ -110,6 +136,11 @@ impl ::protobuf::Message for Graph {
                 return false;
             }
         };
+        for v in &self.conditional_edges {
+            if !v.is_initialized() {
+                return false;
+            }
+        };
         true
     }
 
@@ -123,6 +154,9 @@ impl ::protobuf::Message for Graph {
                 2 => {
                     ::protobuf::rt::read_repeated_message_into(wire_type, is, &mut self.edges)?;
                 },
+                3 => {
+                    ::protobuf::rt::read_repeated_message_into(wire_type, is, &mut self.conditional_edges)?;
+                },
                 _ => {
                     ::protobuf::rt::read_unknown_or_skip_group(field_number, wire_type, is, self.mut_unknown_fields())?;
                 },
@@ -143,6 +177,10 @@ impl ::protobuf::Message for Graph {
             let len = value.compute_size();
             my_size += 1 + ::protobuf::rt::compute_raw_varint32_size(len) + len;
         };
+        for value in &self.conditional_edges {
+            let len = value.compute_size();
+            my_size += 1 + ::protobuf::rt::compute_raw_varint32_size(len) + len;
+        };
         my_size += ::protobuf::rt::unknown_fields_size(self.get_unknown_fields());
         self.cached_size.set(my_size);
         my_size
@@ -159,6 +197,11 @@ impl ::protobuf::Message for Graph {
             os.write_raw_varint32(v.get_cached_size())?;
             v.write_to_with_cached_sizes(os)?;
         };
+        for v in &self.conditional_edges {
+            os.write_tag(3, ::protobuf::wire_format::WireTypeLengthDelimited)?;
+            os.write_raw_varint32(v.get_cached_size())?;
+            v.write_to_with_cached_sizes(os)?;
+        };
         os.write_unknown_fields(self.get_unknown_fields())?;
         ::std::result::Result::Ok(())
     }
@@ -211,6 +254,11 @@ impl ::protobuf::Message for Graph {
                     |m: &Graph| { &m.edges },
                     |m: &mut Graph| { &mut m.edges },
                 ));
+                fields.push(::protobuf::reflect::accessor::make_repeated_field_accessor::<_, ::protobuf::types::ProtobufTypeMessage<Graph_Edge>>(
+                    "conditional_edges",
+                    |m: &Graph| { &m.conditional_edges },
+                    |m: &mut Graph| { &mut m.conditional_edges },
+                ));
                 ::protobuf::reflect::MessageDescriptor::new::<Graph>(
                     "Graph",
                     fields,
@@ -235,6 +283,7 @@ impl ::protobuf::Clear for Graph {
     fn clear(&mut self) {
         self.nodes.clear();
         self.edges.clear();
+        self.conditional_edges.clear();
         self.unknown_fields.clear();
     }
 }
@@ -504,6 +553,8 @@ pub struct Graph_Edge {
     // message fields
     pub from: u64,
     pub to: u64,
+    pub metadata: ::std::collections::HashMap<::std::string::String, ::std::string::String>,
+    pub risks: ::protobuf::RepeatedField<Graph_Risk>,
     // special fields
     pub unknown_fields: ::protobuf::UnknownFields,
     pub cached_size: ::protobuf::CachedSize,
@@ -549,10 +600,65 @@ impl Graph_Edge {
     pub fn set_to(&mut self, v: u64) {
         self.to = v;
     }
+
+    // repeated .Graph.Edge.MetadataEntry metadata = 3;
+
+
+    pub fn get_metadata(&self) -> &::std::collections::HashMap<::std::string::String, ::std::string::String> {
+        &self.metadata
+    }
+    pub fn clear_metadata(&mut self) {
+        self.metadata.clear();
+    }
+
+    // Param is passed by value, moved
+    pub fn set_metadata(&mut self, v: ::std::collections::HashMap<::std::string::String, ::std::string::String>) {
+        self.metadata = v;
+    }
+
+    // Mutable pointer to the field.
+    pub fn mut_metadata(&mut self) -> &mut ::std::collections::HashMap<::std::string::String, ::std::string::String> {
+        &mut self.metadata
+    }
+
+    // Take field
+    pub fn take_metadata(&mut self) -> ::std::collections::HashMap<::std::string::String, ::std::string::String> {
+        ::std::mem::replace(&mut self.metadata, ::std::collections::HashMap::new())
+    }
+
+    // repeated .Graph.Risk risks = 4;
+
+
+    pub fn get_risks(&self) -> &[Graph_Risk] {
+        &self.risks
+    }
+    pub fn clear_risks(&mut self) {
+        self.risks.clear();
+    }
+
+    // Param is passed by value, moved
+    pub fn set_risks(&mut self, v: ::protobuf::RepeatedField<Graph_Risk>) {
+        self.risks = v;
+    }
+
+    // Mutable pointer to the field.
+    pub fn mut_risks(&mut self) -> &mut ::protobuf::RepeatedField<Graph_Risk> {
+        &mut self.risks
+    }
+
+    // Take field
+    pub fn take_risks(&mut self) -> ::protobuf::RepeatedField<Graph_Risk> {
+        ::std::mem::replace(&mut self.risks, ::protobuf::RepeatedField::new())
+    }
 }
 
 impl ::protobuf::Message for Graph_Edge {
     fn is_initialized(&self) -> bool {
+        for v in &self.risks {
+            if !v.is_initialized() {
+                return false;
+            }
+        };
         true
     }
 
@@ -574,6 +680,12 @@ impl ::protobuf::Message for Graph_Edge {
                     let tmp = is.read_uint64()?;
                     self.to = tmp;
                 },
+                3 => {
+                    ::protobuf::rt::read_map_into::<::protobuf::types::ProtobufTypeString, ::protobuf::types::ProtobufTypeString>(wire_type, is, &mut self.metadata)?;
+                },
+                4 => {
+                    ::protobuf::rt::read_repeated_message_into(wire_type, is, &mut self.risks)?;
+                },
                 _ => {
                     ::protobuf::rt::read_unknown_or_skip_group(field_number, wire_type, is, self.mut_unknown_fields())?;
                 },
@@ -592,6 +704,11 @@ impl ::protobuf::Message for Graph_Edge {
         if self.to != 0 {
             my_size += ::protobuf::rt::value_size(2, self.to, ::protobuf::wire_format::WireTypeVarint);
         }
+        my_size += ::protobuf::rt::compute_map_size::<::protobuf::types::ProtobufTypeString, ::protobuf::types::ProtobufTypeString>(3, &self.metadata);
+        for value in &self.risks {
+            let len = value.compute_size();
+            my_size += 1 + ::protobuf::rt::compute_raw_varint32_size(len) + len;
+        };
         my_size += ::protobuf::rt::unknown_fields_size(self.get_unknown_fields());
         self.cached_size.set(my_size);
         my_size
@@ -604,6 +721,12 @@ impl ::protobuf::Message for Graph_Edge {
         if self.to != 0 {
             os.write_uint64(2, self.to)?;
         }
+        ::protobuf::rt::write_map_with_cached_sizes::<::protobuf::types::ProtobufTypeString, ::protobuf::types::ProtobufTypeString>(3, &self.metadata, os)?;
+        for v in &self.risks {
+            os.write_tag(4, ::protobuf::wire_format::WireTypeLengthDelimited)?;
+            os.write_raw_varint32(v.get_cached_size())?;
+            v.write_to_with_cached_sizes(os)?;
+        };
         os.write_unknown_fields(self.get_unknown_fields())?;
         ::std::result::Result::Ok(())
     }
@@ -656,6 +779,16 @@ impl ::protobuf::Message for Graph_Edge {
                     |m: &Graph_Edge| { &m.to },
                     |m: &mut Graph_Edge| { &mut m.to },
                 ));
+                fields.push(::protobuf::reflect::accessor::make_map_accessor::<_, ::protobuf::types::ProtobufTypeString, ::protobuf::types::ProtobufTypeString>(
+                    "metadata",
+                    |m: &Graph_Edge| { &m.metadata },
+                    |m: &mut Graph_Edge| { &mut m.metadata },
+                ));
+                fields.push(::protobuf::reflect::accessor::make_repeated_field_accessor::<_, ::protobuf::types::ProtobufTypeMessage<Graph_Risk>>(
+                    "risks",
+                    |m: &Graph_Edge| { &m.risks },
+                    |m: &mut Graph_Edge| { &mut m.risks },
+                ));
                 ::protobuf::reflect::MessageDescriptor::new::<Graph_Edge>(
                     "Graph_Edge",
                     fields,
@@ -680,6 +813,8 @@ impl ::protobuf::Clear for Graph_Edge {
     fn clear(&mut self) {
         self.from = 0;
         self.to = 0;
+        self.metadata.clear();
+        self.risks.clear();
         self.unknown_fields.clear();
     }
 }
@@ -696,6 +831,518 @@ impl ::protobuf::reflect::ProtobufValue for Graph_Edge {
     }
 }
 
+#[derive(PartialEq,Clone,Default)]
+pub struct Graph_Risk {
+    // message fields
+    pub name: ::std::string::String,
+    pub url: ::std::string::String,
+    pub message: ::std::string::String,
+    pub matching_rules: ::protobuf::RepeatedField<Graph_MatchingRule>,
+    // special fields
+    pub unknown_fields: ::protobuf::UnknownFields,
+    pub cached_size: ::protobuf::CachedSize,
+}
+
+impl<'a> ::std::default::Default for &'a Graph_Risk {
+    fn default() -> &'a Graph_Risk {
+        <Graph_Risk as ::protobuf::Message>::default_instance()
+    }
+}
+
+impl Graph_Risk {
+    pub fn new() -> Graph_Risk {
+        ::std::default::Default::default()
+    }
+
+    // string name = 1;
+
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+    pub fn clear_name(&mut self) {
+        self.name.clear();
+    }
+
+    // Param is passed by value, moved
+    pub fn set_name(&mut self, v: ::std::string::String) {
+        self.name = v;
+    }
+
+    // Mutable pointer to the field.
+    // If field is not initialized, it is initialized with default value first.
+    pub fn mut_name(&mut self) -> &mut ::std::string::String {
+        &mut self.name
+    }
+
+    // Take field
+    pub fn take_name(&mut self) -> ::std::string::String {
+        ::std::mem::replace(&mut self.name, ::std::string::String::new())
+    }
+
+    // string url = 2;
+
+
+    pub fn get_url(&self) -> &str {
+        &self.url
+    }
+    pub fn clear_url(&mut self) {
+        self.url.clear();
+    }
+
+    // Param is passed by value, moved
+    pub fn set_url(&mut self, v: ::std::string::String) {
+        self.url = v;
+    }
+
+    // Mutable pointer to the field.
+    // If field is not initialized, it is initialized with default value first.
+    pub fn mut_url(&mut self) -> &mut ::std::string::String {
+        &mut self.url
+    }
+
+    // Take field
+    pub fn take_url(&mut self) -> ::std::string::String {
+        ::std::mem::replace(&mut self.url, ::std::string::String::new())
+    }
+
+    // string message = 3;
+
+
+    pub fn get_message(&self) -> &str {
+        &self.message
+    }
+    pub fn clear_message(&mut self) {
+        self.message.clear();
+    }
+
+    // Param is passed by value, moved
+    pub fn set_message(&mut self, v: ::std::string::String) {
+        self.message = v;
+    }
+
+    // Mutable pointer to the field.
+    // If field is not initialized, it is initialized with default value first.
+    pub fn mut_message(&mut self) -> &mut ::std::string::String {
+        &mut self.message
+    }
+
+    // Take field
+    pub fn take_message(&mut self) -> ::std::string::String {
+        ::std::mem::replace(&mut self.message, ::std::string::String::new())
+    }
+
+    // repeated .Graph.MatchingRule matching_rules = 4;
+
+
+    pub fn get_matching_rules(&self) -> &[Graph_MatchingRule] {
+        &self.matching_rules
+    }
+    pub fn clear_matching_rules(&mut self) {
+        self.matching_rules.clear();
+    }
+
+    // Param is passed by value, moved
+    pub fn set_matching_rules(&mut self, v: ::protobuf::RepeatedField<Graph_MatchingRule>) {
+        self.matching_rules = v;
+    }
+
+    // Mutable pointer to the field.
+    pub fn mut_matching_rules(&mut self) -> &mut ::protobuf::RepeatedField<Graph_MatchingRule> {
+        &mut self.matching_rules
+    }
+
+    // Take field
+    pub fn take_matching_rules(&mut self) -> ::protobuf::RepeatedField<Graph_MatchingRule> {
+        ::std::mem::replace(&mut self.matching_rules, ::protobuf::RepeatedField::new())
+    }
+}
+
+impl ::protobuf::Message for Graph_Risk {
+    fn is_initialized(&self) -> bool {
+        for v in &self.matching_rules {
+            if !v.is_initialized() {
+                return false;
+            }
+        };
+        true
+    }
+
+    fn merge_from(&mut self, is: &mut ::protobuf::CodedInputStream) -> ::protobuf::ProtobufResult<()> {
+        while !is.eof()? {
+            let (field_number, wire_type) = is.read_tag_unpack()?;
+            match field_number {
+                1 => {
+                    ::protobuf::rt::read_singular_proto3_string_into(wire_type, is, &mut self.name)?;
+                },
+                2 => {
+                    ::protobuf::rt::read_singular_proto3_string_into(wire_type, is, &mut self.url)?;
+                },
+                3 => {
+                    ::protobuf::rt::read_singular_proto3_string_into(wire_type, is, &mut self.message)?;
+                },
+                4 => {
+                    ::protobuf::rt::read_repeated_message_into(wire_type, is, &mut self.matching_rules)?;
+                },
+                _ => {
+                    ::protobuf::rt::read_unknown_or_skip_group(field_number, wire_type, is, self.mut_unknown_fields())?;
+                },
+            };
+        }
+        ::std::result::Result::Ok(())
+    }
+
+    // Compute sizes of nested messages
+    #[allow(unused_variables)]
+    fn compute_size(&self) -> u32 {
+        let mut my_size = 0;
+        if !self.name.is_empty() {
+            my_size += ::protobuf::rt::string_size(1, &self.name);
+        }
+        if !self.url.is_empty() {
+            my_size += ::protobuf::rt::string_size(2, &self.url);
+        }
+        if !self.message.is_empty() {
+            my_size += ::protobuf::rt::string_size(3, &self.message);
+        }
+        for value in &self.matching_rules {
+            let len = value.compute_size();
+            my_size += 1 + ::protobuf::rt::compute_raw_varint32_size(len) + len;
+        };
+        my_size += ::protobuf::rt::unknown_fields_size(self.get_unknown_fields());
+        self.cached_size.set(my_size);
+        my_size
+    }
+
+    fn write_to_with_cached_sizes(&self, os: &mut ::protobuf::CodedOutputStream) -> ::protobuf::ProtobufResult<()> {
+        if !self.name.is_empty() {
+            os.write_string(1, &self.name)?;
+        }
+        if !self.url.is_empty() {
+            os.write_string(2, &self.url)?;
+        }
+        if !self.message.is_empty() {
+            os.write_string(3, &self.message)?;
+        }
+        for v in &self.matching_rules {
+            os.write_tag(4, ::protobuf::wire_format::WireTypeLengthDelimited)?;
+            os.write_raw_varint32(v.get_cached_size())?;
+            v.write_to_with_cached_sizes(os)?;
+        };
+        os.write_unknown_fields(self.get_unknown_fields())?;
+        ::std::result::Result::Ok(())
+    }
+
+    fn get_cached_size(&self) -> u32 {
+        self.cached_size.get()
+    }
+
+    fn get_unknown_fields(&self) -> &::protobuf::UnknownFields {
+        &self.unknown_fields
+    }
+
+    fn mut_unknown_fields(&mut self) -> &mut ::protobuf::UnknownFields {
+        &mut self.unknown_fields
+    }
+
+    fn as_any(&self) -> &dyn (::std::any::Any) {
+        self as &dyn (::std::any::Any)
+    }
+    fn as_any_mut(&mut self) -> &mut dyn (::std::any::Any) {
+        self as &mut dyn (::std::any::Any)
+    }
+    fn into_any(self: Box<Self>) -> ::std::boxed::Box<dyn (::std::any::Any)> {
+        self
+    }
+
+    fn descriptor(&self) -> &'static ::protobuf::reflect::MessageDescriptor {
+        Self::descriptor_static()
+    }
+
+    fn new() -> Graph_Risk {
+        Graph_Risk::new()
+    }
+
+    fn descriptor_static() -> &'static ::protobuf::reflect::MessageDescriptor {
+        static mut descriptor: ::protobuf::lazy::Lazy<::protobuf::reflect::MessageDescriptor> = ::protobuf::lazy::Lazy {
+            lock: ::protobuf::lazy::ONCE_INIT,
+            ptr: 0 as *const ::protobuf::reflect::MessageDescriptor,
+        };
+        unsafe {
+            descriptor.get(|| {
+                let mut fields = ::std::vec::Vec::new();
+                fields.push(::protobuf::reflect::accessor::make_simple_field_accessor::<_, ::protobuf::types::ProtobufTypeString>(
+                    "name",
+                    |m: &Graph_Risk| { &m.name },
+                    |m: &mut Graph_Risk| { &mut m.name },
+                ));
+                fields.push(::protobuf::reflect::accessor::make_simple_field_accessor::<_, ::protobuf::types::ProtobufTypeString>(
+                    "url",
+                    |m: &Graph_Risk| { &m.url },
+                    |m: &mut Graph_Risk| { &mut m.url },
+                ));
+                fields.push(::protobuf::reflect::accessor::make_simple_field_accessor::<_, ::protobuf::types::ProtobufTypeString>(
+                    "message",
+                    |m: &Graph_Risk| { &m.message },
+                    |m: &mut Graph_Risk| { &mut m.message },
+                ));
+                fields.push(::protobuf::reflect::accessor::make_repeated_field_accessor::<_, ::protobuf::types::ProtobufTypeMessage<Graph_MatchingRule>>(
+                    "matching_rules",
+                    |m: &Graph_Risk| { &m.matching_rules },
+                    |m: &mut Graph_Risk| { &mut m.matching_rules },
+                ));
+                ::protobuf::reflect::MessageDescriptor::new::<Graph_Risk>(
+                    "Graph_Risk",
+                    fields,
+                    file_descriptor_proto()
+                )
+            })
+        }
+    }
+
+    fn default_instance() -> &'static Graph_Risk {
+        static mut instance: ::protobuf::lazy::Lazy<Graph_Risk> = ::protobuf::lazy::Lazy {
+            lock: ::protobuf::lazy::ONCE_INIT,
+            ptr: 0 as *const Graph_Risk,
+        };
+        unsafe {
+            instance.get(Graph_Risk::new)
+        }
+    }
+}
+
+impl ::protobuf::Clear for Graph_Risk {
+    fn clear(&mut self) {
+        self.name.clear();
+        self.url.clear();
+        self.message.clear();
+        self.matching_rules.clear();
+        self.unknown_fields.clear();
+    }
+}
+
+impl ::std::fmt::Debug for Graph_Risk {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        ::protobuf::text_format::fmt(self, f)
+    }
+}
+
+impl ::protobuf::reflect::ProtobufValue for Graph_Risk {
+    fn as_ref(&self) -> ::protobuf::reflect::ProtobufValueRef {
+        ::protobuf::reflect::ProtobufValueRef::Message(self)
+    }
+}
+
+#[derive(PartialEq,Clone,Default)]
+pub struct Graph_MatchingRule {
+    // message fields
+    pub parameter: ::std::string::String,
+    pub values: ::protobuf::RepeatedField<::std::string::String>,
+    // special fields
+    pub unknown_fields: ::protobuf::UnknownFields,
+    pub cached_size: ::protobuf::CachedSize,
+}
+
+impl<'a> ::std::default::Default for &'a Graph_MatchingRule {
+    fn default() -> &'a Graph_MatchingRule {
+        <Graph_MatchingRule as ::protobuf::Message>::default_instance()
+    }
+}
+
+impl Graph_MatchingRule {
+    pub fn new() -> Graph_MatchingRule {
+        ::std::default::Default::default()
+    }
+
+    // string parameter = 1;
+
+
+    pub fn get_parameter(&self) -> &str {
+        &self.parameter
+    }
+    pub fn clear_parameter(&mut self) {
+        self.parameter.clear();
+    }
+
+    // Param is passed by value, moved
+    pub fn set_parameter(&mut self, v: ::std::string::String) {
+        self.parameter = v;
+    }
+
+    // Mutable pointer to the field.
+    // If field is not initialized, it is initialized with default value first.
+    pub fn mut_parameter(&mut self) -> &mut ::std::string::String {
+        &mut self.parameter
+    }
+
+    // Take field
+    pub fn take_parameter(&mut self) -> ::std::string::String {
+        ::std::mem::replace(&mut self.parameter, ::std::string::String::new())
+    }
+
+    // repeated string values = 2;
+
+
+    pub fn get_values(&self) -> &[::std::string::String] {
+        &self.values
+    }
+    pub fn clear_values(&mut self) {
+        self.values.clear();
+    }
+
+    // Param is passed by value, moved
+    pub fn set_values(&mut self, v: ::protobuf::RepeatedField<::std::string::String>) {
+        self.values = v;
+    }
+
+    // Mutable pointer to the field.
+    pub fn mut_values(&mut self) -> &mut ::protobuf::RepeatedField<::std::string::String> {
+        &mut self.values
+    }
+
+    // Take field
+    pub fn take_values(&mut self) -> ::protobuf::RepeatedField<::std::string::String> {
+        ::std::mem::replace(&mut self.values, ::protobuf::RepeatedField::new())
+    }
+}
+
+impl ::protobuf::Message for Graph_MatchingRule {
+    fn is_initialized(&self) -> bool {
+        true
+    }
+
+    fn merge_from(&mut self, is: &mut ::protobuf::CodedInputStream) -> ::protobuf::ProtobufResult<()> {
+        while !is.eof()? {
+            let (field_number, wire_type) = is.read_tag_unpack()?;
+            match field_number {
+                1 => {
+                    ::protobuf::rt::read_singular_proto3_string_into(wire_type, is, &mut self.parameter)?;
+                },
+                2 => {
+                    ::protobuf::rt::read_repeated_string_into(wire_type, is, &mut self.values)?;
+                },
+                _ => {
+                    ::protobuf::rt::read_unknown_or_skip_group(field_number, wire_type, is, self.mut_unknown_fields())?;
+                },
+            };
+        }
+        ::std::result::Result::Ok(())
+    }
+
+    // Compute sizes of nested messages
+    #[allow(unused_variables)]
+    fn compute_size(&self) -> u32 {
+        let mut my_size = 0;
+        if !self.parameter.is_empty() {
+            my_size += ::protobuf::rt::string_size(1, &self.parameter);
+        }
+        for value in &self.values {
+            my_size += ::protobuf::rt::string_size(2, &value);
+        };
+        my_size += ::protobuf::rt::unknown_fields_size(self.get_unknown_fields());
+        self.cached_size.set(my_size);
+        my_size
+    }
+
+    fn write_to_with_cached_sizes(&self, os: &mut ::protobuf::CodedOutputStream) -> ::protobuf::ProtobufResult<()> {
+        if !self.parameter.is_empty() {
+            os.write_string(1, &self.parameter)?;
+        }
+        for v in &self.values {
+            os.write_string(2, &v)?;
+        };
+        os.write_unknown_fields(self.get_unknown_fields())?;
+        ::std::result::Result::Ok(())
+    }
+
+    fn get_cached_size(&self) -> u32 {
+        self.cached_size.get()
+    }
+
+    fn get_unknown_fields(&self) -> &::protobuf::UnknownFields {
+        &self.unknown_fields
+    }
+
+    fn mut_unknown_fields(&mut self) -> &mut ::protobuf::UnknownFields {
+        &mut self.unknown_fields
+    }
+
+    fn as_any(&self) -> &dyn (::std::any::Any) {
+        self as &dyn (::std::any::Any)
+    }
+    fn as_any_mut(&mut self) -> &mut dyn (::std::any::Any) {
+        self as &mut dyn (::std::any::Any)
+    }
+    fn into_any(self: Box<Self>) -> ::std::boxed::Box<dyn (::std::any::Any)> {
+        self
+    }
+
+    fn descriptor(&self) -> &'static ::protobuf::reflect::MessageDescriptor {
+        Self::descriptor_static()
+    }
+
+    fn new() -> Graph_MatchingRule {
+        Graph_MatchingRule::new()
+    }
+
+    fn descriptor_static() -> &'static ::protobuf::reflect::MessageDescriptor {
+        static mut descriptor: ::protobuf::lazy::Lazy<::protobuf::reflect::MessageDescriptor> = ::protobuf::lazy::Lazy {
+            lock: ::protobuf::lazy::ONCE_INIT,
+            ptr: 0 as *const ::protobuf::reflect::MessageDescriptor,
+        };
+        unsafe {
+            descriptor.get(|| {
+                let mut fields = ::std::vec::Vec::new();
+                fields.push(::protobuf::reflect::accessor::make_simple_field_accessor::<_, ::protobuf::types::ProtobufTypeString>(
+                    "parameter",
+                    |m: &Graph_MatchingRule| { &m.parameter },
+                    |m: &mut Graph_MatchingRule| { &mut m.parameter },
+                ));
+                fields.push(::protobuf::reflect::accessor::make_repeated_field_accessor::<_, ::protobuf::types::ProtobufTypeString>(
+                    "values",
+                    |m: &Graph_MatchingRule| { &m.values },
+                    |m: &mut Graph_MatchingRule| { &mut m.values },
+                ));
+                ::protobuf::reflect::MessageDescriptor::new::<Graph_MatchingRule>(
+                    "Graph_MatchingRule",
+                    fields,
+                    file_descriptor_proto()
+                )
+            })
+        }
+    }
+
+    fn default_instance() -> &'static Graph_MatchingRule {
+        static mut instance: ::protobuf::lazy::Lazy<Graph_MatchingRule> = ::protobuf::lazy::Lazy {
+            lock: ::protobuf::lazy::ONCE_INIT,
+            ptr: 0 as *const Graph_MatchingRule,
+        };
+        unsafe {
+            instance.get(Graph_MatchingRule::new)
+        }
+    }
+}
+
+impl ::protobuf::Clear for Graph_MatchingRule {
+    fn clear(&mut self) {
+        self.parameter.clear();
+        self.values.clear();
+        self.unknown_fields.clear();
+    }
+}
+
+impl ::std::fmt::Debug for Graph_MatchingRule {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        ::protobuf::text_format::fmt(self, f)
+    }
+}
+
+impl ::protobuf::reflect::ProtobufValue for Graph_MatchingRule {
+    fn as_ref(&self) -> ::protobuf::reflect::ProtobufValueRef {
+        ::protobuf::reflect::ProtobufValueRef::Message(self)
+    }
+}
+
 #[derive(PartialEq,Clone,Default)]
 pub struct PluginExchange {
     // message fields
@@ -1182,24 +1829,35 @@ impl ::protobuf::reflect::ProtobufValue for PluginError_Kind {
 }
 
 static file_descriptor_proto_data: &'static [u8] = b"\
-    \n\x1bsrc/plugins/interface.proto\"\xaa\x02\n\x05Graph\x12!\n\x05nodes\
+    \n\x1bsrc/plugins/interface.proto\"\xc7\x05\n\x05Graph\x12!\n\x05nodes\
     \x18\x01\x20\x03(\x0b2\x0b.Graph.NodeR\x05nodes\x12!\n\x05edges\x18\x02\
-    \x20\x03(\x0b2\x0b.Graph.EdgeR\x05edges\x1a\xae\x01\n\x04Node\x12\x18\n\
-    \x07version\x18\x01\x20\x01(\tR\x07version\x12\x18\n\x07payload\x18\x02\
-    \x20\x01(\tR\x07payload\x125\n\x08metadata\x18\x03\x20\x03(\x0b2\x19.Gra\
-    ph.Node.MetadataEntryR\x08metadata\x1a;\n\rMetadataEntry\x12\x10\n\x03ke\
-    y\x18\x01\x20\x01(\tR\x03key\x12\x14\n\x05value\x18\x02\x20\x01(\tR\x05v\
-    alue:\x028\x01\x1a*\n\x04Edge\x12\x12\n\x04from\x18\x01\x20\x01(\x04R\
-    \x04from\x12\x0e\n\x02to\x18\x02\x20\x01(\x04R\x02to\"\xae\x01\n\x0ePlug\
-    inExchange\x12\x1c\n\x05graph\x18\x01\x20\x01(\x0b2\x06.GraphR\x05graph\
-    \x12?\n\nparameters\x18\x02\x20\x03(\x0b2\x1f.PluginExchange.ParametersE\
-    ntryR\nparameters\x1a=\n\x0fParametersEntry\x12\x10\n\x03key\x18\x01\x20\
-    \x01(\tR\x03key\x12\x14\n\x05value\x18\x02\x20\x01(\tR\x05value:\x028\
-    \x01\"\xb2\x01\n\x0bPluginError\x12%\n\x04kind\x18\x01\x20\x01(\x0e2\x11\
-    .PluginError.KindR\x04kind\x12\x14\n\x05value\x18\x02\x20\x01(\tR\x05val\
-    ue\"f\n\x04Kind\x12\x0b\n\x07GENERIC\x10\0\x12\x11\n\rINVALID_GRAPH\x10\
-    \x01\x12\x11\n\rINVALID_PARAM\x10\x02\x12\x15\n\x11FAILED_DEPENDENCY\x10\
-    \x03\x12\x14\n\x10INTERNAL_FAILURE\x10\x04b\x06proto3\
+    \x20\x03(\x0b2\x0b.Graph.EdgeR\x05edges\x128\n\x11conditional_edges\x18\
+    \x03\x20\x03(\x0b2\x0b.Graph.EdgeR\x10conditionalEdges\x1a\xae\x01\n\x04\
+    Node\x12\x18\n\x07version\x18\x01\x20\x01(\tR\x07version\x12\x18\n\x07pa\
+    yload\x18\x02\x20\x01(\tR\x07payload\x125\n\x08metadata\x18\x03\x20\x03(\
+    \x0b2\x19.Graph.Node.MetadataEntryR\x08metadata\x1a;\n\rMetadataEntry\
+    \x12\x10\n\x03key\x18\x01\x20\x01(\tR\x03key\x12\x14\n\x05value\x18\x02\
+    \x20\x01(\tR\x05value:\x028\x01\x1a\xc1\x01\n\x04Edge\x12\x12\n\x04from\
+    \x18\x01\x20\x01(\x04R\x04from\x12\x0e\n\x02to\x18\x02\x20\x01(\x04R\x02\
+    to\x125\n\x08metadata\x18\x03\x20\x03(\x0b2\x19.Graph.Edge.MetadataEntry\
+    R\x08metadata\x12!\n\x05risks\x18\x04\x20\x03(\x0b2\x0b.Graph.RiskR\x05r\
+    isks\x1a;\n\rMetadataEntry\x12\x10\n\x03key\x18\x01\x20\x01(\tR\x03key\
+    \x12\x14\n\x05value\x18\x02\x20\x01(\tR\x05value:\x028\x01\x1a\x82\x01\n\
+    \x04Risk\x12\x12\n\x04name\x18\x01\x20\x01(\tR\x04name\x12\x10\n\x03url\
+    \x18\x02\x20\x01(\tR\x03url\x12\x18\n\x07message\x18\x03\x20\x01(\tR\x07\
+    message\x12:\n\x0ematching_rules\x18\x04\x20\x03(\x0b2\x13.Graph.Matchin\
+    gRuleR\rmatchingRules\x1aD\n\x0cMatchingRule\x12\x1c\n\tparameter\x18\
+    \x01\x20\x01(\tR\tparameter\x12\x16\n\x06values\x18\x02\x20\x03(\tR\x06v\
+    alues\"\xae\x01\n\x0ePluginExchange\x12\x1c\n\x05graph\x18\x01\x20\x01(\
+    \x0b2\x06.GraphR\x05graph\x12?\n\nparameters\x18\x02\x20\x03(\x0b2\x1f.P\
+    luginExchange.ParametersEntryR\nparameters\x1a=\n\x0fParametersEntry\x12\
+    \x10\n\x03key\x18\x01\x20\x01(\tR\x03key\x12\x14\n\x05value\x18\x02\x20\
+    \x01(\tR\x05value:\x028\x01\"\xb2\x01\n\x0bPluginError\x12%\n\x04kind\
+    \x18\x01\x20\x01(\x0e2\x11.PluginError.KindR\x04kind\x12\x14\n\x05value\
+    \x18\x02\x20\x01(\tR\x05value\"f\n\x04Kind\x12\x0b\n\x07GENERIC\x10\0\
+    \x12\x11\n\rINVALID_GRAPH\x10\x01\x12\x11\n\rINVALID_PARAM\x10\x02\x12\
+    \x15\n\x11FAILED_DEPENDENCY\x10\x03\x12\x14\n\x10INTERNAL_FAILURE\x10\
+    \x04b\x06proto3\
 ";
 
 static mut file_descriptor_proto_lazy: ::protobuf::lazy::Lazy<::protobuf::descriptor::FileDescriptorProto> = ::protobuf::lazy::Lazy {
@@ -9,7 +9,7 @@ use crate::plugins::{
     AsyncIO, BoxedPlugin, InternalIO, InternalPlugin, InternalPluginWrapper, PluginSettings,
 };
 use commons::GraphError;
-use failure::{Fallible, ResultExt};
+use failure::Fallible;
 use futures::Future;
 use prometheus::Registry;
 
@@ -147,21 +147,20 @@ impl InternalPlugin for ArchFilterPlugin {
                     graph
                         .find_by_fn_mut(|release| {
                             match release {
-                                crate::Release::Concrete(concrete_release) => concrete_release
-                                    .metadata
-                                    .get_mut(&format!("{}.{}", key_prefix, key_suffix))
-                                    .map_or(true, |values| {
-                                        if values.split(',').any(|value| value.trim() == arch) {
-                                            *values = arch.clone();
-                                            false
-                                        } else {
-                                            true
-                                        }
-                                    }),
+                                crate::Release::Concrete(concrete_release) => {
+                                    let mut namespace = concrete_release.namespace_mut(&key_prefix);
+                                    if namespace.as_namespace().list_contains(&key_suffix, &arch) {
+                                        namespace.set(&key_suffix, arch.as_str());
+                                        false
+                                    } else {
+                                        true
+                                    }
+                                }
                                 // remove if it's not a ConcreteRelease
                                 _ => true,
                             }
                         })
+                        .map_err(|e| GraphError::ArchVersionError(e.to_string()))?
                         .into_iter()
                         .map(|(release_id, version)| {
                             trace!("queuing '{}' for removal", version);
@@ -177,23 +176,15 @@ impl InternalPlugin for ArchFilterPlugin {
 
                 // remove the build suffix from the version
                 graph
-                    .iter_releases_mut(|mut release| {
-                        let version = {
-                            let release_version = release.version().to_owned();
-
-                            semver::Version::parse(&release_version)
-                                .context(release_version.clone())
-                                .map(|mut version| {
-                                    version.build.retain(|elem| elem.to_string() != arch);
-                                    trace!("rewriting version {} ->  {}", release_version, version);
-                                    version.to_string()
-                                })?
+                    .iter_releases_mut(|release| {
+                        let version = match release {
+                            crate::Release::Abstract(release) => &mut release.version,
+                            crate::Release::Concrete(release) => &mut release.version,
                         };
 
-                        match &mut release {
-                            crate::Release::Abstract(release) => release.version = version,
-                            crate::Release::Concrete(release) => release.version = version,
-                        };
+                        let release_version = version.to_string();
+                        version.build.retain(|elem| elem.to_string() != arch);
+                        trace!("rewriting version {} ->  {}", release_version, version);
 
                         Ok(())
                     })
@@ -76,16 +76,14 @@ impl InternalPlugin for ChannelFilterPlugin {
                 graph
                     .find_by_fn_mut(|release| {
                         match release {
-                            crate::Release::Concrete(concrete_release) => concrete_release
-                                .metadata
-                                .get_mut(&format!("{}.{}", key_prefix, key_suffix))
-                                .map_or(true, |values| {
-                                    !values.split(',').any(|value| value.trim() == channel)
-                                }),
+                            crate::Release::Concrete(concrete_release) => !concrete_release
+                                .namespace(&key_prefix)
+                                .list_contains(&key_suffix, &channel),
                             // remove if it's not a ConcreteRelease
                             _ => true,
                         }
                     })
+                    .map_err(|e| GraphError::FailedPluginExecution(e.to_string()))?
                     .into_iter()
                     .map(|(release_id, version)| {
                         trace!("queuing '{}' for removal", version);
@@ -6,23 +6,58 @@
 use crate::plugins::{
     AsyncIO, BoxedPlugin, InternalIO, InternalPlugin, InternalPluginWrapper, PluginSettings,
 };
-use crate::CONTENT_TYPE;
+use crate::{Graph, GraphLimits, CONTENT_TYPE};
 use commons::GraphError;
 use failure::Fallible;
-use futures::{future, Future, Stream};
+use futures::sync::{mpsc, oneshot};
+use futures::{future, Future, Sink, Stream};
+use futures_cpupool::{Builder, CpuPool};
 use prometheus::{Counter, Registry};
 use reqwest;
 use reqwest::header::{HeaderValue, ACCEPT};
+use std::io::{self, Read};
 
 /// Default URL to upstream graph provider.
 pub static DEFAULT_UPSTREAM_URL: &str = "http://localhost:8080/v1/graph";
 
+/// Default maximum size of the upstream response body in bytes.
+pub const DEFAULT_MAX_BODY_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Default maximum number of releases in the upstream graph.
+pub const DEFAULT_MAX_NODES: usize = 100_000;
+
+/// Default maximum number of edges in the upstream graph.
+pub const DEFAULT_MAX_EDGES: usize = 1_000_000;
+
+/// Number of response body chunks buffered ahead of the deserializer.
+const BODY_CHUNKS_BUFFERED: usize = 16;
+
+/// Number of threads deserializing fetched graphs, further fetches wait for a free one.
+const DESERIALIZER_THREADS: usize = 4;
+
+lazy_static! {
+    /// Threads on which the fetched graphs are deserialized, shared by all plugin instances.
+    static ref DESERIALIZER_POOL: CpuPool = Builder::new()
+        .pool_size(DESERIALIZER_THREADS)
+        .name_prefix("graph-fetch-")
+        .create();
+}
+
 /// Plugin settings.
 #[derive(Clone, CustomDebug, Deserialize, SmartDefault)]
 #[serde(default)]
 struct CincinnatiGraphFetchSettings {
     #[default(DEFAULT_UPSTREAM_URL.to_string())]
     upstream: String,
+
+    #[default(DEFAULT_MAX_BODY_BYTES)]
+    max_body_bytes: u64,
+
+    #[default(DEFAULT_MAX_NODES)]
+    max_nodes: usize,
+
+    #[default(DEFAULT_MAX_EDGES)]
+    max_edges: usize,
 }
 
 /// Graph fetcher for Cincinnati `/v1/graph` endpoints.
@@ -31,6 +66,9 @@ pub struct CincinnatiGraphFetchPlugin {
     /// The upstream from which to fetch the graph
     pub upstream: String,
 
+    /// The limits for the size of the fetched graph
+    pub limits: GraphLimits,
+
     /// The optional metric for counting upstream requests
     #[debug(skip)]
     pub http_upstream_reqs: Counter,
@@ -43,7 +81,12 @@ pub struct CincinnatiGraphFetchPlugin {
 impl PluginSettings for CincinnatiGraphFetchSettings {
     fn build_plugin(&self, registry: Option<&Registry>) -> Fallible<BoxedPlugin> {
         let cfg = self.clone();
-        let plugin = CincinnatiGraphFetchPlugin::try_new(cfg.upstream, registry)?;
+        let limits = GraphLimits {
+            max_bytes: Some(cfg.max_body_bytes),
+            max_nodes: Some(cfg.max_nodes),
+            max_edges: Some(cfg.max_edges),
+        };
+        let plugin = CincinnatiGraphFetchPlugin::try_new(cfg.upstream, limits, registry)?;
         Ok(new_plugin!(InternalPluginWrapper(plugin)))
     }
 }
@@ -57,12 +100,16 @@ impl CincinnatiGraphFetchPlugin {
         let settings: CincinnatiGraphFetchSettings = cfg.try_into()?;
 
         ensure!(!settings.upstream.is_empty(), "empty upstream");
+        ensure!(settings.max_body_bytes > 0, "zero max_body_bytes");
+        ensure!(settings.max_nodes > 0, "zero max_nodes");
+        ensure!(settings.max_edges > 0, "zero max_edges");
 
         Ok(Box::new(settings))
     }
 
     fn try_new(
         upstream: String,
+        limits: GraphLimits,
         prometheus_registry: Option<&prometheus::Registry>,
     ) -> Fallible<Self> {
         let http_upstream_reqs = Counter::new(
@@ -82,6 +129,7 @@ impl CincinnatiGraphFetchPlugin {
 
         Ok(Self {
             upstream,
+            limits,
             http_upstream_reqs,
             http_upstream_errors_total,
         })
@@ -91,6 +139,7 @@ impl CincinnatiGraphFetchPlugin {
 impl InternalPlugin for CincinnatiGraphFetchPlugin {
     fn run_internal(self: &Self, io: InternalIO) -> AsyncIO<InternalIO> {
         let upstream = self.upstream.to_owned();
+        let limits = self.limits;
         let http_upstream_errors_total = self.http_upstream_errors_total.clone();
 
         trace!("getting graph from upstream at {}", upstream);
@@ -116,13 +165,38 @@ impl InternalPlugin for CincinnatiGraphFetchPlugin {
             }
         })
         .and_then(move |res| {
-            res.into_body()
+            // The body is deserialized on a pooled thread while it is
+            // received, so it never has to be held in memory as a whole.
+            let (chunk_tx, chunk_rx) = mpsc::channel(BODY_CHUNKS_BUFFERED);
+            let (graph_tx, graph_rx) = oneshot::channel();
+            DESERIALIZER_POOL
+                .spawn_fn(move || {
+                    let reader = BodyReader {
+                        chunks: chunk_rx.wait(),
+                        current: Default::default(),
+                    };
+                    let _ = graph_tx.send(Graph::from_json_reader(reader, &limits));
+                    Ok::<(), ()>(())
+                })
+                .forget();
+
+            let forward_body = res
+                .into_body()
                 // TODO(steveeJ): find a way to make this fail in a test
-                .concat2()
-                .map_err(move |e| GraphError::FailedUpstreamFetch(e.to_string()))
-        })
-        .and_then(move |body| {
-            serde_json::from_slice(&body).map_err(|e| GraphError::FailedJsonIn(e.to_string()))
+                .map_err(|e| Some(GraphError::FailedUpstreamFetch(e.to_string())))
+                .forward(chunk_tx.sink_map_err(|_| None))
+                .then(|result| match result {
+                    // The deserializer stopped reading early, its result tells why.
+                    Ok(_) | Err(None) => Ok(()),
+                    Err(Some(e)) => Err(e),
+                });
+            let graph = graph_rx.map_err(|_| {
+                GraphError::FailedJsonIn("graph deserialization was aborted".to_string())
+            });
+
+            forward_body
+                .join(graph)
+                .and_then(|(_, graph)| graph.map_err(|e| GraphError::FailedJsonIn(e.to_string())))
         })
         .map(|graph| InternalIO {
             graph,
@@ -138,6 +212,31 @@ impl InternalPlugin for CincinnatiGraphFetchPlugin {
     }
 }
 
+/// Blocking reader over the chunks of a response body.
+struct BodyReader<I, C> {
+    chunks: I,
+    current: io::Cursor<C>,
+}
+
+impl<I, C> Read for BodyReader<I, C>
+where
+    I: Iterator<Item = Result<C, ()>>,
+    C: AsRef<[u8]>,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let read = self.current.read(buf)?;
+            if read > 0 || buf.is_empty() {
+                return Ok(read);
+            }
+            match self.chunks.next() {
+                Some(Ok(chunk)) => self.current = io::Cursor::new(chunk),
+                Some(Err(())) | None => return Ok(0),
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -167,7 +266,11 @@ mod tests {
                     .with_body($mock_body.to_string())
                     .create();
 
-                let plugin = CincinnatiGraphFetchPlugin::try_new(mockito::server_url(), None)?;
+                let plugin = CincinnatiGraphFetchPlugin::try_new(
+                    mockito::server_url(),
+                    Default::default(),
+                    None,
+                )?;
                 let http_upstream_reqs = plugin.http_upstream_reqs.clone();
                 let http_upstream_errors_total = plugin.http_upstream_errors_total.clone();
 
@@ -233,7 +336,11 @@ mod tests {
                     .with_body($mock_body.to_string())
                     .create();
 
-                let plugin = CincinnatiGraphFetchPlugin::try_new($upstream.to_string(), None)?;
+                let plugin = CincinnatiGraphFetchPlugin::try_new(
+                    $upstream.to_string(),
+                    Default::default(),
+                    None,
+                )?;
                 let http_upstream_reqs = plugin.http_upstream_reqs.clone();
                 let http_upstream_errors_total = plugin.http_upstream_errors_total.clone();
 
@@ -289,6 +396,58 @@ mod tests {
         mock_body: "{not a valid graph}",
     );
 
+    #[test]
+    fn body_reader_reads_across_chunks() -> Fallible<()> {
+        let chunks = vec![r#"{"nodes":[],"#, "", r#""edges":[]}"#]
+            .into_iter()
+            .map(|chunk| Ok(chunk.as_bytes()));
+        let mut reader = BodyReader {
+            chunks,
+            current: Default::default(),
+        };
+
+        let mut body = String::new();
+        reader.read_to_string(&mut body)?;
+        assert_eq!(body, r#"{"nodes":[],"edges":[]}"#);
+
+        Ok(())
+    }
+
+    #[test]
+    fn fetch_fail_too_many_edges() -> Fallible<()> {
+        let mut runtime = init_runtime()?;
+
+        let graph = generate_custom_graph(
+            "image",
+            (0..3)
+                .into_iter()
+                .map(|i| (i, Default::default()))
+                .collect(),
+            Some(vec![(0, 1), (1, 2)]),
+        );
+        let _m = mockito::mock("GET", "/")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(serde_json::to_string(&graph)?)
+            .create();
+
+        let limits = GraphLimits {
+            max_edges: Some(1),
+            ..Default::default()
+        };
+        let plugin = CincinnatiGraphFetchPlugin::try_new(mockito::server_url(), limits, None)?;
+
+        let future_result = plugin.run_internal(InternalIO {
+            graph: Default::default(),
+            parameters: Default::default(),
+        });
+        let err = runtime.block_on(future_result).unwrap_err();
+        assert!(err.to_string().contains("maximum of 1 edges"), "{}", err);
+        assert_eq!(1, plugin.http_upstream_errors_total.get() as u64);
+
+        Ok(())
+    }
+
     #[test]
     fn register_metrics() -> Fallible<()> {
         let mut rt = testing::init_runtime()?;
@@ -298,7 +457,11 @@ mod tests {
             metrics_prefix.clone(),
         ))?));
 
-        let _ = CincinnatiGraphFetchPlugin::try_new(mockito::server_url(), Some(registry))?;
+        let _ = CincinnatiGraphFetchPlugin::try_new(
+            mockito::server_url(),
+            Default::default(),
+            Some(registry),
+        )?;
 
         let http_req = TestRequest::default()
             .data(RegistryWrapper(registry))
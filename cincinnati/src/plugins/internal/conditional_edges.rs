@@ -0,0 +1,211 @@
+//! This plugin resolves the conditional edges of a graph against the request parameters.
+//! Risks which don't apply to the requesting client are dropped, which turns edges
+//! without any remaining risks into unconditional edges.
+//! Edges with applicable risks are kept as conditional edges, unless
+//! `remove_risky_edges` is set, in which case they are removed from the graph.
+
+use crate::plugins::{
+    AsyncIO, BoxedPlugin, InternalIO, InternalPlugin, InternalPluginWrapper, PluginSettings,
+};
+use crate::{ReleaseId, Risk};
+use failure::Fallible;
+use prometheus::Registry;
+
+#[derive(Clone, Debug, Deserialize, SmartDefault)]
+#[serde(default)]
+pub struct ConditionalEdgesPlugin {
+    pub remove_risky_edges: bool,
+}
+
+impl PluginSettings for ConditionalEdgesPlugin {
+    fn build_plugin(&self, _: Option<&Registry>) -> Fallible<BoxedPlugin> {
+        Ok(new_plugin!(InternalPluginWrapper(self.clone())))
+    }
+}
+
+impl ConditionalEdgesPlugin {
+    /// Plugin name, for configuration.
+    pub const PLUGIN_NAME: &'static str = "conditional-edges";
+
+    /// Validate plugin configuration and fill in defaults.
+    pub fn deserialize_config(cfg: toml::Value) -> Fallible<Box<dyn PluginSettings>> {
+        let plugin: Self = cfg.try_into()?;
+
+        Ok(Box::new(plugin))
+    }
+}
+
+impl InternalPlugin for ConditionalEdgesPlugin {
+    fn run_internal(self: &Self, io: InternalIO) -> AsyncIO<InternalIO> {
+        let closure = || -> Fallible<InternalIO> {
+            let InternalIO {
+                mut graph,
+                parameters,
+            } = io;
+
+            let conditional_edges: Vec<(ReleaseId, ReleaseId, Vec<Risk>)> = graph
+                .conditional_edges()
+                .into_iter()
+                .map(|(from, to, risks)| (from, to, risks.to_vec()))
+                .collect();
+
+            for (from, to, risks) in conditional_edges {
+                let applicable: Vec<Risk> = risks
+                    .into_iter()
+                    .filter(|risk| risk.applies_to(&parameters))
+                    .collect();
+
+                if self.remove_risky_edges && !applicable.is_empty() {
+                    trace!(
+                        "removing edge from {:?} to {:?} with risks {:?}",
+                        from,
+                        to,
+                        applicable
+                    );
+                    graph.remove_edge(&from, &to)?;
+                } else {
+                    *graph.get_edge_risks_as_ref_mut(&from, &to)? = applicable;
+                }
+            }
+
+            Ok(InternalIO { graph, parameters })
+        };
+
+        Box::new(futures::future::result(closure()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate as cincinnati;
+    use crate::testing::generate_custom_graph;
+    use crate::MatchingRule;
+    use commons::testing::init_runtime;
+    use failure::ResultExt;
+    use maplit::hashmap;
+    use std::collections::HashMap;
+
+    fn risk(name: &str, rule: Option<(&str, &str)>) -> Risk {
+        Risk {
+            name: name.to_string(),
+            url: format!("https://example.com/{}", name),
+            message: format!("{} is risky", name),
+            matching_rules: rule
+                .into_iter()
+                .map(|(parameter, value)| MatchingRule {
+                    parameter: parameter.to_string(),
+                    values: vec![value.to_string()],
+                })
+                .collect(),
+        }
+    }
+
+    /// Returns a graph with the edges 0 -> 1 -> 2 and a conditional edge 0 -> 2
+    /// with a risk for arch=arm64 and a risk for platform=metal.
+    fn generate_test_graph() -> Fallible<cincinnati::Graph> {
+        let mut graph =
+            generate_custom_graph("image", (0..3).map(|i| (i, hashmap! {})).collect(), None);
+        let v0 = graph
+            .find_by_version("0.0.0")
+            .ok_or_else(|| format_err!("couldn't find 0.0.0"))?;
+        let v2 = graph
+            .find_by_version("2.0.0")
+            .ok_or_else(|| format_err!("couldn't find 2.0.0"))?;
+        graph.add_conditional_edge(
+            &v0,
+            &v2,
+            vec![
+                risk("ArmRisk", Some(("arch", "arm64"))),
+                risk("MetalRisk", Some(("platform", "metal"))),
+            ],
+        )?;
+
+        Ok(graph)
+    }
+
+    fn run(
+        plugin: ConditionalEdgesPlugin,
+        graph: cincinnati::Graph,
+        parameters: HashMap<String, String>,
+    ) -> Fallible<cincinnati::Graph> {
+        let mut runtime = init_runtime()?;
+
+        let future_processed_graph = plugin.run_internal(InternalIO { graph, parameters });
+
+        Ok(runtime
+            .block_on(future_processed_graph)
+            .context("plugin run failed")?
+            .graph)
+    }
+
+    #[test]
+    fn ensure_unaffected_clients_get_unconditional_edges() -> Fallible<()> {
+        let processed_graph = run(
+            ConditionalEdgesPlugin::default(),
+            generate_test_graph()?,
+            hashmap! {
+                "arch".to_string() => "amd64".to_string(),
+                "platform".to_string() => "aws".to_string(),
+            },
+        )?;
+
+        let expected_graph = generate_custom_graph(
+            "image",
+            (0..3).map(|i| (i, hashmap! {})).collect(),
+            Some(vec![(0, 1), (1, 2), (0, 2)]),
+        );
+        assert_eq!(expected_graph, processed_graph);
+        assert!(processed_graph.conditional_edges().is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_applicable_risks_are_kept() -> Fallible<()> {
+        // The platform is unknown, hence the platform risk can't be ruled out.
+        let processed_graph = run(
+            ConditionalEdgesPlugin::default(),
+            generate_test_graph()?,
+            hashmap! { "arch".to_string() => "amd64".to_string() },
+        )?;
+
+        let conditional_edges = processed_graph.conditional_edges();
+        assert_eq!(conditional_edges.len(), 1);
+        assert_eq!(
+            conditional_edges[0].2,
+            &[risk("MetalRisk", Some(("platform", "metal")))][..]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_risky_edges_are_removed() -> Fallible<()> {
+        let processed_graph = run(
+            ConditionalEdgesPlugin {
+                remove_risky_edges: true,
+            },
+            generate_test_graph()?,
+            hashmap! {
+                "arch".to_string() => "arm64".to_string(),
+                "platform".to_string() => "aws".to_string(),
+            },
+        )?;
+
+        let expected_graph =
+            generate_custom_graph("image", (0..3).map(|i| (i, hashmap! {})).collect(), None);
+        assert_eq!(expected_graph, processed_graph);
+
+        Ok(())
+    }
+
+    #[test]
+    fn risk_without_rules_applies_to_everyone() {
+        let parameters = hashmap! { "arch".to_string() => "amd64".to_string() };
+
+        assert!(risk("Everyone", None).applies_to(&parameters));
+        assert!(!risk("ArmRisk", Some(("arch", "arm64"))).applies_to(&parameters));
+        assert!(risk("MetalRisk", Some(("platform", "metal"))).applies_to(&parameters));
+    }
+}
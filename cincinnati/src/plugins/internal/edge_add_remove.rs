@@ -1,6 +1,7 @@
 //! This plugin adds and removes Edges from Nodes based on metadata labels.
 
 use crate as cincinnati;
+use crate::metadata;
 use crate::plugins::BoxedPlugin;
 use crate::plugins::{AsyncIO, InternalIO, InternalPlugin, InternalPluginWrapper, PluginSettings};
 use crate::ReleaseId;
@@ -97,13 +98,13 @@ impl EdgeAddRemovePlugin {
         }
 
         graph
-            .find_by_metadata_key(&format!("{}.{}", self.key_prefix, "previous.remove"))
+            .find_by_metadata_key(&metadata::key(&self.key_prefix, "previous.remove"))
             .into_iter()
             .try_for_each(
                 |(to, to_version, from_csv): (ReleaseId, String, String)| -> Fallible<()> {
                     if from_csv.trim() == self.remove_all_edges_value {
                         let parents: Vec<daggy::EdgeIndex> = graph
-                            .previous_releases(&to)
+                            .previous_releases(&to)?
                             .map(|(edge_index, _, _)| edge_index)
                             .collect();
 
@@ -111,9 +112,9 @@ impl EdgeAddRemovePlugin {
                         return graph.remove_edges_by_index(&parents);
                     }
 
-                    for from_version in from_csv.split(',').map(str::trim) {
+                    for from_version in metadata::split_list(&from_csv) {
                         let from_version =
-                            try_annotate_semver_build(&mut graph, from_version, &to)?;
+                            self.try_annotate_semver_build(&graph, from_version, &to)?;
 
                         if let Some(from) = graph.find_by_version(&from_version) {
                             info!("[{}]: removing previous {}", from_version, to_version,);
@@ -130,12 +131,13 @@ impl EdgeAddRemovePlugin {
             )?;
 
         graph
-            .find_by_metadata_key(&format!("{}.{}", self.key_prefix, "next.remove"))
+            .find_by_metadata_key(&metadata::key(&self.key_prefix, "next.remove"))
             .into_iter()
             .try_for_each(
                 |(from, from_version, to_csv): (ReleaseId, String, String)| -> Fallible<()> {
-                    for to_version in to_csv.split(',').map(str::trim) {
-                        let to_version = try_annotate_semver_build(&mut graph, to_version, &from)?;
+                    for to_version in metadata::split_list(&to_csv) {
+                        let to_version =
+                            self.try_annotate_semver_build(&graph, to_version, &from)?;
                         if let Some(to) = graph.find_by_version(&to_version) {
                             info!("[{}]: removing next {}", from_version, to_version);
                             handle_remove_edge!(from, to)
@@ -170,12 +172,12 @@ impl EdgeAddRemovePlugin {
         }
 
         graph
-            .find_by_metadata_key(&format!("{}.{}", self.key_prefix, "previous.add"))
+            .find_by_metadata_key(&metadata::key(&self.key_prefix, "previous.add"))
             .into_iter()
             .try_for_each(|(to, to_version, from_csv)| -> Fallible<()> {
-                for from_version in from_csv.split(',').map(str::trim) {
+                for from_version in metadata::split_list(&from_csv) {
                     let from_version_annotated =
-                        try_annotate_semver_build(&mut graph, from_version, &to)?;
+                        self.try_annotate_semver_build(&graph, from_version, &to)?;
 
                     if let Some(from) = graph.find_by_version(&from_version_annotated) {
                         info!(
@@ -194,12 +196,12 @@ impl EdgeAddRemovePlugin {
             })?;
 
         graph
-            .find_by_metadata_key(&format!("{}.{}", self.key_prefix, "next.add"))
+            .find_by_metadata_key(&metadata::key(&self.key_prefix, "next.add"))
             .into_iter()
             .try_for_each(|(from, from_version, to_csv)| -> Fallible<()> {
-                for to_version in to_csv.split(',').map(str::trim) {
+                for to_version in metadata::split_list(&to_csv) {
                     let to_version_annotated =
-                        try_annotate_semver_build(&mut graph, to_version, &from)?;
+                        self.try_annotate_semver_build(&graph, to_version, &from)?;
 
                     if let Some(to) = graph.find_by_version(&to_version_annotated) {
                         info!(
@@ -219,29 +221,34 @@ impl EdgeAddRemovePlugin {
 
         Ok(())
     }
-}
 
-/// Try to find the architecture metadata and add it to the version String assuming SemVer.
-///
-/// If the referenced ReleaseId doesn't have the arch metadata, the version
-/// string will be passed through unchanged.
-fn try_annotate_semver_build(
-    graph: &mut cincinnati::Graph,
-    version: &str,
-    arch_reference: &ReleaseId,
-) -> Fallible<String> {
-    let version = if let Some(arch) = graph
-        .get_metadata_as_ref_mut(arch_reference)?
-        .get("io.openshift.upgrades.graph.release.arch")
-    {
-        let mut version = semver::Version::parse(version)?;
-        version.build = vec![semver::Identifier::AlphaNumeric(arch.to_string())];
-        version.to_string()
-    } else {
-        version.to_string()
-    };
-
-    Ok(version)
+    /// Try to find the architecture metadata and add it to the version String assuming SemVer.
+    ///
+    /// If the referenced ReleaseId doesn't have the arch metadata, the version
+    /// string will be passed through unchanged.
+    fn try_annotate_semver_build(
+        &self,
+        graph: &cincinnati::Graph,
+        version: &str,
+        arch_reference: &ReleaseId,
+    ) -> Fallible<String> {
+        let arch = match graph.find_by_releaseid(arch_reference)? {
+            cincinnati::Release::Concrete(release) => {
+                release.namespace(&self.key_prefix).get("release.arch")
+            }
+            cincinnati::Release::Abstract(_) => None,
+        };
+
+        let version = if let Some(arch) = arch {
+            let mut version = semver::Version::parse(version)?;
+            version.build = vec![semver::Identifier::AlphaNumeric(arch.to_string())];
+            version.to_string()
+        } else {
+            version.to_string()
+        };
+
+        Ok(version)
+    }
 }
 
 #[cfg(test)]
@@ -705,5 +712,54 @@ mod tests {
         expected_edges: Some(vec![]),
     );
 
+    #[test]
+    fn annotate_semver_build_uses_key_prefix() -> Fallible<()> {
+        let graph = generate_custom_graph(
+            "image",
+            vec![
+                (
+                    0,
+                    [(format!("{}.release.arch", KEY_PREFIX), "arm64".to_string())]
+                        .iter()
+                        .cloned()
+                        .collect(),
+                ),
+                (
+                    1,
+                    [(
+                        format!("{}.release.arch", DEFAULT_KEY_FILTER),
+                        "arm64".to_string(),
+                    )]
+                    .iter()
+                    .cloned()
+                    .collect(),
+                ),
+            ],
+            None,
+        );
+        let plugin = EdgeAddRemovePlugin {
+            key_prefix: KEY_PREFIX.to_string(),
+            ..Default::default()
+        };
+
+        let v0 = graph
+            .find_by_version("0.0.0")
+            .ok_or_else(|| format_err!("couldn't find 0.0.0"))?;
+        assert_eq!(
+            plugin.try_annotate_semver_build(&graph, "2.0.0", &v0)?,
+            "2.0.0+arm64"
+        );
+
+        let v1 = graph
+            .find_by_version("1.0.0")
+            .ok_or_else(|| format_err!("couldn't find 1.0.0"))?;
+        assert_eq!(
+            plugin.try_annotate_semver_build(&graph, "2.0.0", &v1)?,
+            "2.0.0"
+        );
+
+        Ok(())
+    }
+
     // TODO(steveeJ): add multiarch tests once design is settled
 }
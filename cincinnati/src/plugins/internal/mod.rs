@@ -3,6 +3,7 @@
 pub mod arch_filter;
 pub mod channel_filter;
 pub mod cincinnati_graph_fetch;
+pub mod conditional_edges;
 pub mod edge_add_remove;
 pub mod metadata_fetch_quay;
 pub mod node_remove;
@@ -1,8 +1,9 @@
-//! This plugin removes releases according to its metadata
+//! This plugin removes releases according to its metadata, or which match a query
 
 use crate::plugins::{
     AsyncIO, BoxedPlugin, InternalIO, InternalPlugin, InternalPluginWrapper, PluginSettings,
 };
+use crate::query::Query;
 use failure::Fallible;
 use prometheus::Registry;
 
@@ -13,6 +14,9 @@ static DEFAULT_KEY_FILTER: &str = "io.openshift.upgrades.graph";
 pub struct NodeRemovePlugin {
     #[default(DEFAULT_KEY_FILTER.to_string())]
     pub key_prefix: String,
+
+    /// Releases which match this query are removed as well, see `crate::query`.
+    pub query: Option<Query>,
 }
 
 impl PluginSettings for NodeRemovePlugin {
@@ -41,16 +45,28 @@ impl InternalPlugin for NodeRemovePlugin {
             let mut graph = io.graph;
             let key_suffix = "release.remove";
 
-            let to_remove = {
-                graph
-                    .find_by_metadata_pair(&format!("{}.{}", self.key_prefix, key_suffix), "true")
-                    .into_iter()
-                    .map(|(release_id, version)| {
-                        trace!("queuing '{}' for removal", version);
-                        release_id
-                    })
-                    .collect()
-            };
+            let to_remove = graph
+                .iter_releases()
+                .filter(|(_, release)| {
+                    let marked = match release {
+                        crate::Release::Concrete(release) => {
+                            match release.namespace(&self.key_prefix).get_bool(key_suffix) {
+                                Ok(remove) => remove.unwrap_or(false),
+                                Err(e) => {
+                                    warn!("{}", e);
+                                    false
+                                }
+                            }
+                        }
+                        crate::Release::Abstract(_) => false,
+                    };
+                    marked || self.query.iter().any(|query| query.matches(release))
+                })
+                .map(|(release_id, release)| {
+                    trace!("queuing '{}' for removal", release.version());
+                    release_id
+                })
+                .collect();
 
             // remove all matches from the Graph
             let removed = graph.remove_releases(to_remove);
@@ -104,11 +120,51 @@ mod tests {
             generate_custom_graph("image", metadata, None)
         };
 
-        let future_processed_graph =
-            Box::new(NodeRemovePlugin { key_prefix }).run_internal(InternalIO {
-                graph: input_graph.clone(),
-                parameters: Default::default(),
-            });
+        let future_processed_graph = Box::new(NodeRemovePlugin {
+            key_prefix,
+            query: None,
+        })
+        .run_internal(InternalIO {
+            graph: input_graph.clone(),
+            parameters: Default::default(),
+        });
+
+        let processed_graph = runtime
+            .block_on(future_processed_graph)
+            .context("plugin run failed")?
+            .graph;
+
+        assert_eq!(expected_graph, processed_graph);
+
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_release_remove_by_query() -> Fallible<()> {
+        let mut runtime = init_runtime()?;
+
+        let metadata: TestMetadata = vec![
+            (0, hashmap! {}),
+            (1, hashmap! { "kind".to_string() => "nightly".to_string() }),
+            (2, hashmap! {}),
+            (3, hashmap! {}),
+        ];
+        let input_graph = generate_custom_graph("image", metadata, None);
+
+        let expected_graph: cincinnati::Graph =
+            generate_custom_graph("image", vec![(2, hashmap! {})], None);
+
+        let settings: toml::Value = toml::from_str(
+            r#"
+                name = "node-remove"
+                query = "version == 0.0.0 || version >= 3.0.0 || metadata.kind == nightly"
+            "#,
+        )?;
+        let plugin: NodeRemovePlugin = settings.try_into()?;
+        let future_processed_graph = Box::new(plugin).run_internal(InternalIO {
+            graph: input_graph,
+            parameters: Default::default(),
+        });
 
         let processed_graph = runtime
             .block_on(future_processed_graph)
@@ -208,23 +208,25 @@ impl TryFrom<ExternalIO> for InternalIO {
         let mut plugin_exchange: PluginExchange = external_io.try_into()?;
 
         Ok(Self {
-            graph: plugin_exchange.take_graph().into(),
+            graph: crate::Graph::try_from(plugin_exchange.take_graph())?,
             parameters: plugin_exchange.take_parameters(),
         })
     }
 }
 
-/// Convert from an InternalIO to a PluginExchange
+/// Try to convert from an InternalIO to a PluginExchange
 ///
-/// This conversion cannot fail.
-impl From<InternalIO> for interface::PluginExchange {
-    fn from(internal_io: InternalIO) -> Self {
+/// This can fail because abstract releases can't be converted.
+impl TryFrom<InternalIO> for interface::PluginExchange {
+    type Error = Error;
+
+    fn try_from(internal_io: InternalIO) -> Fallible<Self> {
         let mut plugin_exchange = Self::new();
 
-        plugin_exchange.set_graph(internal_io.graph.into());
+        plugin_exchange.set_graph(internal_io.graph.try_into()?);
         plugin_exchange.set_parameters(internal_io.parameters);
 
-        plugin_exchange
+        Ok(plugin_exchange)
     }
 }
 
@@ -235,7 +237,7 @@ impl TryFrom<InternalIO> for ExternalIO {
     type Error = Error;
 
     fn try_from(internal_io: InternalIO) -> Fallible<Self> {
-        let exchange: PluginExchange = internal_io.into();
+        let exchange: PluginExchange = internal_io.try_into()?;
         exchange.try_into()
     }
 }
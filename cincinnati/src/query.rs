@@ -0,0 +1,495 @@
+//! Query language for selecting releases
+//!
+//! A query consists of predicates combined with `&&`, `||`, `!` and
+//! parentheses, where `!` binds strongest and `||` weakest, e.g.
+//! `version >= 4.2.0 && channels contains fast-4.2`.
+//!
+//! The following predicates are supported:
+//!
+//! * `version <op> <version>` with `<op>` being one of `==`, `!=`, `<`, `<=`, `>`, `>=`
+//! * `version matches "<range>"`, e.g. `version matches ">=4.2.0, <4.3.0"`
+//! * `payload <op> <value>` with `<op>` being one of `==`, `!=`, `=~`
+//! * `metadata.<key> <op> <value>` with `<op>` being one of `==`, `!=`, `=~`, `contains`
+//! * `channels <op> <value>` as shorthand for the channels metadata key
+//!
+//! `=~` matches the value against a regular expression and `contains`
+//! checks for an entry in a comma-separated list value.
+//! Values containing whitespace or operator characters must be enclosed
+//! in double quotes.
+//!
+//! Version comparisons ignore build metadata. Predicates on the payload or
+//! metadata never match abstract releases or releases missing the key.
+
+use crate::metadata::{self, DEFAULT_NAMESPACE};
+use crate::{Graph, Release, ReleaseId};
+use failure::{Error, Fallible};
+use petgraph::visit::IntoNodeReferences;
+use regex::Regex;
+use semver::{Version, VersionReq};
+use serde::de::{self, Deserialize, Deserializer};
+use std::cmp::Ordering;
+use std::str::FromStr;
+
+/// Prefix of the fields which refer to a metadata key.
+const METADATA_FIELD_PREFIX: &str = "metadata.";
+
+/// Query which selects releases.
+#[derive(Clone, Debug)]
+pub enum Query {
+    Predicate(Predicate),
+    Not(Box<Query>),
+    And(Box<Query>, Box<Query>),
+    Or(Box<Query>, Box<Query>),
+}
+
+/// Condition on a single property of a release.
+#[derive(Clone, Debug)]
+pub enum Predicate {
+    Version(Comparison, Version),
+    VersionRange(VersionReq),
+    Payload(Match),
+    Metadata(String, Match),
+}
+
+/// Comparison of a release version against a given version.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+/// Condition on a string value.
+#[derive(Clone, Debug)]
+pub enum Match {
+    Equal(String),
+    NotEqual(String),
+    Regex(Regex),
+    Contains(String),
+}
+
+impl Query {
+    /// Returns true if the release matches this query.
+    pub fn matches(&self, release: &Release) -> bool {
+        match self {
+            Query::Predicate(predicate) => predicate.matches(release),
+            Query::Not(query) => !query.matches(release),
+            Query::And(left, right) => left.matches(release) && right.matches(release),
+            Query::Or(left, right) => left.matches(release) || right.matches(release),
+        }
+    }
+}
+
+impl Predicate {
+    /// Returns true if the release matches this predicate.
+    pub fn matches(&self, release: &Release) -> bool {
+        match (self, release) {
+            (Predicate::Version(comparison, version), _) => {
+                comparison.matches(release.version().cmp(version))
+            }
+            (Predicate::VersionRange(range), _) => range.matches(release.version()),
+            (Predicate::Payload(condition), Release::Concrete(release)) => {
+                condition.matches(&release.payload)
+            }
+            (Predicate::Metadata(key, condition), Release::Concrete(release)) => release
+                .metadata
+                .get(key)
+                .map(|value| condition.matches(value))
+                .unwrap_or(false),
+            (_, Release::Abstract(_)) => false,
+        }
+    }
+}
+
+impl Comparison {
+    fn from_operator(operator: &str) -> Fallible<Self> {
+        match operator {
+            "==" => Ok(Comparison::Equal),
+            "!=" => Ok(Comparison::NotEqual),
+            "<" => Ok(Comparison::Less),
+            "<=" => Ok(Comparison::LessOrEqual),
+            ">" => Ok(Comparison::Greater),
+            ">=" => Ok(Comparison::GreaterOrEqual),
+            _ => bail!("operator '{}' can't be used to compare versions", operator),
+        }
+    }
+
+    /// Returns true if the ordering of a release version relative to the
+    /// given version satisfies this comparison.
+    pub fn matches(self, ordering: Ordering) -> bool {
+        match self {
+            Comparison::Equal => ordering == Ordering::Equal,
+            Comparison::NotEqual => ordering != Ordering::Equal,
+            Comparison::Less => ordering == Ordering::Less,
+            Comparison::LessOrEqual => ordering != Ordering::Greater,
+            Comparison::Greater => ordering == Ordering::Greater,
+            Comparison::GreaterOrEqual => ordering != Ordering::Less,
+        }
+    }
+}
+
+impl Match {
+    /// Returns true if the value satisfies this condition.
+    pub fn matches(&self, value: &str) -> bool {
+        match self {
+            Match::Equal(expected) => value == expected,
+            Match::NotEqual(expected) => value != expected,
+            Match::Regex(regex) => regex.is_match(value),
+            Match::Contains(entry) => metadata::split_list(value).contains(&entry.as_str()),
+        }
+    }
+}
+
+impl FromStr for Query {
+    type Err = Error;
+
+    fn from_str(input: &str) -> Fallible<Self> {
+        let mut parser = Parser {
+            tokens: tokenize(input)?,
+            position: 0,
+        };
+
+        let query = parser.parse_or()?;
+        if let Some(token) = parser.next() {
+            bail!("unexpected {:?} in query '{}'", token, input);
+        }
+
+        Ok(query)
+    }
+}
+
+impl<'de> Deserialize<'de> for Query {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+impl Graph {
+    /// Returns tuples of ReleaseId and its version String for releases which
+    /// match the given query.
+    pub fn select(&self, query: &Query) -> Vec<(ReleaseId, String)> {
+        self.dag
+            .node_references()
+            .filter(|(_, release)| query.matches(release))
+            .map(|(index, release)| (self.release_id(index), release.version().to_string()))
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    LeftParen,
+    RightParen,
+    And,
+    Or,
+    Not,
+    Operator(&'static str),
+    Word(String),
+    Quoted(String),
+}
+
+/// Symbols in the order they are matched, i.e. longer ones first.
+const SYMBOLS: &[&str] = &[
+    "&&", "||", "==", "!=", "=~", "<=", ">=", "<", ">", "!", "(", ")",
+];
+
+fn tokenize(input: &str) -> Fallible<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut rest = input.trim_start();
+
+    while let Some(first) = rest.chars().next() {
+        if let Some(symbol) = SYMBOLS.iter().find(|symbol| rest.starts_with(*symbol)) {
+            tokens.push(match *symbol {
+                "&&" => Token::And,
+                "||" => Token::Or,
+                "!" => Token::Not,
+                "(" => Token::LeftParen,
+                ")" => Token::RightParen,
+                operator => Token::Operator(operator),
+            });
+            rest = &rest[symbol.len()..];
+        } else if first == '"' {
+            let (value, remainder) = tokenize_quoted(&rest[1..])?;
+            tokens.push(Token::Quoted(value));
+            rest = remainder;
+        } else {
+            let end = rest
+                .find(|c: char| c.is_whitespace() || "()!&|=<>\"".contains(c))
+                .unwrap_or(rest.len());
+            ensure!(end > 0, "unexpected character '{}' in query", first);
+            tokens.push(Token::Word(rest[..end].to_string()));
+            rest = &rest[end..];
+        }
+        rest = rest.trim_start();
+    }
+
+    Ok(tokens)
+}
+
+/// Reads a quoted value up to the closing quote and returns it with the remaining input.
+///
+/// Only `\"` and `\\` are unescaped, so that regular expressions can be written as usual.
+fn tokenize_quoted(input: &str) -> Fallible<(String, &str)> {
+    let mut value = String::new();
+    let mut chars = input.char_indices();
+
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '"' => return Ok((value, &input[index + 1..])),
+            '\\' => match chars.next() {
+                Some((_, escaped)) if escaped == '"' || escaped == '\\' => value.push(escaped),
+                Some((_, other)) => {
+                    value.push('\\');
+                    value.push(other);
+                }
+                None => break,
+            },
+            c => value.push(c),
+        }
+    }
+
+    bail!("unterminated string in query")
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn parse_or(&mut self) -> Fallible<Query> {
+        let mut query = self.parse_and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.position += 1;
+            query = Query::Or(Box::new(query), Box::new(self.parse_and()?));
+        }
+        Ok(query)
+    }
+
+    fn parse_and(&mut self) -> Fallible<Query> {
+        let mut query = self.parse_unary()?;
+        while self.peek() == Some(&Token::And) {
+            self.position += 1;
+            query = Query::And(Box::new(query), Box::new(self.parse_unary()?));
+        }
+        Ok(query)
+    }
+
+    fn parse_unary(&mut self) -> Fallible<Query> {
+        match self.next() {
+            Some(Token::Not) => Ok(Query::Not(Box::new(self.parse_unary()?))),
+            Some(Token::LeftParen) => {
+                let query = self.parse_or()?;
+                match self.next() {
+                    Some(Token::RightParen) => Ok(query),
+                    _ => bail!("missing closing parenthesis in query"),
+                }
+            }
+            Some(Token::Word(field)) => self.parse_predicate(&field).map(Query::Predicate),
+            Some(token) => bail!("unexpected {:?} in query", token),
+            None => bail!("unexpected end of query"),
+        }
+    }
+
+    fn parse_predicate(&mut self, field: &str) -> Fallible<Predicate> {
+        let operator = self
+            .next()
+            .ok_or_else(|| format_err!("missing operator after '{}' in query", field))?;
+
+        match field {
+            "version" => match operator {
+                Token::Operator(operator) => {
+                    let comparison = Comparison::from_operator(operator)?;
+                    let value = self.parse_value(field)?;
+                    let version = Version::parse(&value)
+                        .map_err(|e| format_err!("invalid version '{}' in query: {}", value, e))?;
+                    Ok(Predicate::Version(comparison, version))
+                }
+                Token::Word(ref keyword) if keyword == "matches" => {
+                    let value = self.parse_value(field)?;
+                    let range = VersionReq::parse(&value).map_err(|e| {
+                        format_err!("invalid version range '{}' in query: {}", value, e)
+                    })?;
+                    Ok(Predicate::VersionRange(range))
+                }
+                token => bail!("unexpected {:?} after '{}' in query", token, field),
+            },
+            "payload" => self.parse_match(field, operator).map(Predicate::Payload),
+            "channels" => self.parse_match(field, operator).map(|condition| {
+                Predicate::Metadata(
+                    metadata::key(DEFAULT_NAMESPACE, "release.channels"),
+                    condition,
+                )
+            }),
+            _ if field.starts_with(METADATA_FIELD_PREFIX)
+                && field.len() > METADATA_FIELD_PREFIX.len() =>
+            {
+                self.parse_match(field, operator).map(|condition| {
+                    Predicate::Metadata(field[METADATA_FIELD_PREFIX.len()..].to_string(), condition)
+                })
+            }
+            _ => bail!("unknown field '{}' in query", field),
+        }
+    }
+
+    fn parse_match(&mut self, field: &str, operator: Token) -> Fallible<Match> {
+        let value = self.parse_value(field)?;
+
+        match operator {
+            Token::Operator("==") => Ok(Match::Equal(value)),
+            Token::Operator("!=") => Ok(Match::NotEqual(value)),
+            Token::Operator("=~") => Regex::new(&value)
+                .map(Match::Regex)
+                .map_err(|e| format_err!("invalid regex '{}' in query: {}", value, e)),
+            Token::Word(ref keyword) if keyword == "contains" => Ok(Match::Contains(value)),
+            token => bail!("unexpected {:?} after '{}' in query", token, field),
+        }
+    }
+
+    fn parse_value(&mut self, field: &str) -> Fallible<String> {
+        match self.next() {
+            Some(Token::Word(value)) | Some(Token::Quoted(value)) => Ok(value),
+            Some(token) => bail!(
+                "expected value for '{}' but got {:?} in query",
+                field,
+                token
+            ),
+            None => bail!("missing value for '{}' in query", field),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::generate_custom_graph;
+    use maplit::hashmap;
+
+    /// Returns a graph with the releases 0.0.0 to 4.0.0, where the releases
+    /// from 2.0.0 on are in the channels `stable` and `fast`.
+    fn generate_test_graph() -> Graph {
+        let channels = metadata::key(DEFAULT_NAMESPACE, "release.channels");
+        generate_custom_graph(
+            "image",
+            (0..5)
+                .map(|i| match i {
+                    0 | 1 => (i, hashmap! { channels.clone() => "fast".to_string() }),
+                    _ => (
+                        i,
+                        hashmap! {
+                            channels.clone() => "stable, fast".to_string(),
+                            "kind".to_string() => format!("kind {}", i % 2),
+                        },
+                    ),
+                })
+                .collect(),
+            None,
+        )
+    }
+
+    fn selected_versions(graph: &Graph, query: &str) -> Fallible<Vec<String>> {
+        let mut versions: Vec<String> = graph
+            .select(&query.parse()?)
+            .into_iter()
+            .map(|(_, version)| version)
+            .collect();
+        versions.sort();
+        Ok(versions)
+    }
+
+    #[test]
+    fn select_matching_releases() -> Fallible<()> {
+        let graph = generate_test_graph();
+
+        for (query, expected) in vec![
+            ("version >= 2.0.0", vec!["2.0.0", "3.0.0", "4.0.0"]),
+            ("version<2.0.0", vec!["0.0.0", "1.0.0"]),
+            (
+                "version != 1.0.0 && version <= 2.0.0",
+                vec!["0.0.0", "2.0.0"],
+            ),
+            (
+                r#"version matches ">=1.0.0, <3.0.0""#,
+                vec!["1.0.0", "2.0.0"],
+            ),
+            ("channels contains stable", vec!["2.0.0", "3.0.0", "4.0.0"]),
+            ("channels == fast", vec!["0.0.0", "1.0.0"]),
+            (r#"metadata.kind == "kind 1""#, vec!["3.0.0"]),
+            (
+                r#"metadata.kind =~ "\d$" && !metadata.kind =~ 0"#,
+                vec!["3.0.0"],
+            ),
+            ("payload =~ ^image:[03]", vec!["0.0.0", "3.0.0"]),
+            (
+                "version == 0.0.0 || version > 1.0.0 && !channels contains stable",
+                vec!["0.0.0"],
+            ),
+            (
+                "(version == 0.0.0 || version > 1.0.0) && !(channels contains fast)",
+                vec![],
+            ),
+            ("metadata.missing != value", vec![]),
+        ] {
+            assert_eq!(
+                selected_versions(&graph, query)?,
+                expected,
+                "query: {}",
+                query
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_invalid_queries() {
+        for query in &[
+            "",
+            "version",
+            "version >=",
+            "version >= a.b.c",
+            "version contains 1.0.0",
+            "version matches \"> x\"",
+            "payload > image",
+            "payload =~ \"(\"",
+            "metadata. == value",
+            "unknown == value",
+            "channels contains \"fast",
+            "(channels contains fast",
+            "channels contains fast)",
+            "channels contains fast &&",
+            "channels contains fast & version == 1.0.0",
+        ] {
+            assert!(query.parse::<Query>().is_err(), "query: {}", query);
+        }
+    }
+
+    #[test]
+    fn deserialize_query() -> Fallible<()> {
+        let query: Query = serde_json::from_str(r#""channels contains \"fast\"""#)?;
+        let graph = generate_test_graph();
+        assert_eq!(graph.select(&query).len(), 5);
+
+        assert!(serde_json::from_str::<Query>(r#""channels""#).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,164 @@
+//! Statistics about the shape of graphs
+
+use crate::metadata::{self, DEFAULT_NAMESPACE};
+use crate::{Graph, Release};
+use petgraph::visit::IntoNodeReferences;
+use petgraph::Direction;
+use std::collections::{BTreeMap, HashMap};
+
+/// Suffixes of the metadata keys in the default namespace which `Graph::stats` counts values of.
+pub const DEFAULT_STATS_METADATA_SUFFIXES: &[&str] = &["release.channels", "release.arch"];
+
+/// Statistics about the shape of a graph, as returned by `Graph::stats`.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct GraphStats {
+    pub releases: usize,
+    pub edges: usize,
+    /// Number of releases by their number of incoming edges.
+    pub in_degrees: BTreeMap<usize, usize>,
+    /// Number of releases by their number of outgoing edges.
+    pub out_degrees: BTreeMap<usize, usize>,
+    /// Number of edges on the longest path.
+    pub longest_path: usize,
+    /// Number of releases without outgoing edges.
+    pub heads: usize,
+    /// Number of releases without incoming edges.
+    pub roots: usize,
+    /// Number of releases by metadata key and value.
+    ///
+    /// Values are treated as comma-separated lists, so that a release in
+    /// several channels counts for each of them.
+    pub metadata_values: BTreeMap<String, BTreeMap<String, usize>>,
+}
+
+impl Graph {
+    /// Returns statistics about this graph, counting the values of the
+    /// channels and architecture metadata.
+    pub fn stats(&self) -> GraphStats {
+        let keys: Vec<String> = DEFAULT_STATS_METADATA_SUFFIXES
+            .iter()
+            .map(|suffix| metadata::key(DEFAULT_NAMESPACE, suffix))
+            .collect();
+        self.stats_with_metadata_keys(&keys)
+    }
+
+    /// Returns statistics about this graph, counting the values of the given metadata keys.
+    pub fn stats_with_metadata_keys<S>(&self, keys: &[S]) -> GraphStats
+    where
+        S: AsRef<str>,
+    {
+        let mut stats = GraphStats {
+            releases: self.dag.node_count(),
+            edges: self.dag.edge_count(),
+            longest_path: self.longest_path(),
+            ..Default::default()
+        };
+
+        for (index, release) in self.dag.node_references() {
+            let in_degree = self
+                .dag
+                .neighbors_directed(index, Direction::Incoming)
+                .count();
+            let out_degree = self
+                .dag
+                .neighbors_directed(index, Direction::Outgoing)
+                .count();
+            *stats.in_degrees.entry(in_degree).or_default() += 1;
+            *stats.out_degrees.entry(out_degree).or_default() += 1;
+            if in_degree == 0 {
+                stats.roots += 1;
+            }
+            if out_degree == 0 {
+                stats.heads += 1;
+            }
+
+            if let Release::Concrete(release) = release {
+                for key in keys {
+                    let key = key.as_ref();
+                    if let Some(value) = release.metadata.get(key) {
+                        let counts = stats.metadata_values.entry(key.to_string()).or_default();
+                        for entry in metadata::split_list(value) {
+                            *counts.entry(entry.to_string()).or_default() += 1;
+                        }
+                    }
+                }
+            }
+        }
+
+        stats
+    }
+
+    /// Returns the number of edges on the longest path.
+    fn longest_path(&self) -> usize {
+        let order = petgraph::algo::toposort(&self.dag, None).expect("the graph to be acyclic");
+
+        let mut lengths: HashMap<daggy::NodeIndex, usize> = HashMap::new();
+        for index in order {
+            let length = self
+                .dag
+                .neighbors_directed(index, Direction::Incoming)
+                .map(|parent| lengths[&parent] + 1)
+                .max()
+                .unwrap_or(0);
+            lengths.insert(index, length);
+        }
+
+        lengths.values().cloned().max().unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::generate_custom_graph;
+    use maplit::{btreemap, hashmap};
+
+    #[test]
+    fn stats_of_graph() {
+        let channels = metadata::key(DEFAULT_NAMESPACE, "release.channels");
+        let arch = metadata::key(DEFAULT_NAMESPACE, "release.arch");
+
+        // 0 -> 1 -> 2 -> 3
+        // 0 -> 2
+        // 4
+        let graph = generate_custom_graph(
+            "image",
+            vec![
+                (
+                    0,
+                    hashmap! { channels.clone() => "stable, fast".to_string() },
+                ),
+                (1, hashmap! { channels.clone() => "fast".to_string() }),
+                (2, hashmap! { arch.clone() => "amd64".to_string() }),
+                (3, hashmap! { arch.clone() => "amd64".to_string() }),
+                (4, hashmap! {}),
+            ],
+            Some(vec![(0, 1), (1, 2), (2, 3), (0, 2)]),
+        );
+
+        assert_eq!(
+            graph.stats(),
+            GraphStats {
+                releases: 5,
+                edges: 4,
+                in_degrees: btreemap! { 0 => 2, 1 => 2, 2 => 1 },
+                out_degrees: btreemap! { 0 => 2, 1 => 2, 2 => 1 },
+                longest_path: 3,
+                heads: 2,
+                roots: 2,
+                metadata_values: btreemap! {
+                    channels => btreemap! { "fast".to_string() => 2, "stable".to_string() => 1 },
+                    arch => btreemap! { "amd64".to_string() => 2 },
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn stats_of_empty_graph() {
+        assert_eq!(
+            Graph::default().stats_with_metadata_keys::<&str>(&[]),
+            GraphStats::default()
+        );
+    }
+}
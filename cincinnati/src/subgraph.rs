@@ -0,0 +1,255 @@
+//! Extraction of subgraphs around a given release
+
+use crate::{Graph, ReleaseId};
+use failure::Fallible;
+use petgraph::visit::{EdgeRef, IntoEdgeReferences, IntoNodeReferences};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+impl Graph {
+    /// Returns the subgraph of the release with the given version and all
+    /// releases which can be reached from it.
+    pub fn descendants_of(&self, version: &str) -> Fallible<Graph> {
+        Ok(self.subgraph(&self.descendants(version)?))
+    }
+
+    /// Returns the subgraph of the release with the given version and all
+    /// releases from which it can be reached.
+    pub fn ancestors_of(&self, version: &str) -> Fallible<Graph> {
+        Ok(self.subgraph(&self.ancestors(version)?))
+    }
+
+    /// Returns the subgraph of all releases which are at most `depth` edges
+    /// away from the release with the given version, regardless of the edge directions.
+    pub fn neighborhood(&self, version: &str, depth: usize) -> Fallible<Graph> {
+        Ok(self.subgraph(&self.neighbors_within(version, depth)?))
+    }
+
+    /// Removes all releases except the ones which `descendants_of` would return.
+    ///
+    /// Returns the number of removed releases.
+    pub fn retain_descendants_of(&mut self, version: &str) -> Fallible<usize> {
+        let keep = self.descendants(version)?;
+        Ok(self.retain_releases(&keep))
+    }
+
+    /// Removes all releases except the ones which `ancestors_of` would return.
+    ///
+    /// Returns the number of removed releases.
+    pub fn retain_ancestors_of(&mut self, version: &str) -> Fallible<usize> {
+        let keep = self.ancestors(version)?;
+        Ok(self.retain_releases(&keep))
+    }
+
+    /// Removes all releases except the ones which `neighborhood` would return.
+    ///
+    /// Returns the number of removed releases.
+    pub fn retain_neighborhood(&mut self, version: &str, depth: usize) -> Fallible<usize> {
+        let keep = self.neighbors_within(version, depth)?;
+        Ok(self.retain_releases(&keep))
+    }
+
+    /// Returns the ReleaseId for the given version, failing if it doesn't exist.
+    fn expect_version(&self, version: &str) -> Fallible<ReleaseId> {
+        self.find_by_version(version)
+            .ok_or_else(|| format_err!("release {} not found in graph", version))
+    }
+
+    fn descendants(&self, version: &str) -> Fallible<HashSet<ReleaseId>> {
+        let id = self.expect_version(version)?;
+        let mut releases = self.reachable_releases(&id)?;
+        releases.insert(id);
+        Ok(releases)
+    }
+
+    fn ancestors(&self, version: &str) -> Fallible<HashSet<ReleaseId>> {
+        let id = self.expect_version(version)?;
+        let mut releases = self.reaching_releases(&id)?;
+        releases.insert(id);
+        Ok(releases)
+    }
+
+    fn neighbors_within(&self, version: &str, depth: usize) -> Fallible<HashSet<ReleaseId>> {
+        let start = self.node_index(&self.expect_version(version)?)?;
+
+        let mut found = HashSet::new();
+        found.insert(start);
+        let mut queue = VecDeque::new();
+        queue.push_back((start, 0));
+
+        while let Some((current, current_depth)) = queue.pop_front() {
+            if current_depth == depth {
+                continue;
+            }
+            for neighbor in self.dag.neighbors_undirected(current) {
+                if found.insert(neighbor) {
+                    queue.push_back((neighbor, current_depth + 1));
+                }
+            }
+        }
+
+        Ok(found
+            .into_iter()
+            .map(|index| self.release_id(index))
+            .collect())
+    }
+
+    /// Returns a new graph with copies of the given releases and the edges between them.
+    fn subgraph(&self, keep: &HashSet<ReleaseId>) -> Graph {
+        let mut subgraph = Graph::default();
+
+        let indices: HashMap<daggy::NodeIndex, daggy::NodeIndex> = self
+            .dag
+            .node_references()
+            .filter(|(index, _)| keep.contains(&self.release_id(*index)))
+            .map(|(index, release)| (index, subgraph.add_node(release.clone()).index))
+            .collect();
+
+        for edge in self.dag.edge_references() {
+            if let (Some(source), Some(target)) =
+                (indices.get(&edge.source()), indices.get(&edge.target()))
+            {
+                subgraph
+                    .dag
+                    .add_edge(*source, *target, edge.weight().clone());
+            }
+        }
+
+        subgraph
+    }
+
+    /// Removes all releases which aren't contained in `keep` and returns their number.
+    fn retain_releases(&mut self, keep: &HashSet<ReleaseId>) -> usize {
+        let to_remove = self
+            .dag
+            .node_indices()
+            .map(|index| self.release_id(index))
+            .filter(|id| !keep.contains(id))
+            .collect();
+
+        self.remove_releases(to_remove)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::generate_custom_graph;
+    use maplit::hashmap;
+
+    /// Returns a graph with the following edges, where release 3 has metadata:
+    ///
+    /// 0 -> 1 -> 2 -> 3 -> 4
+    /// 1 -> 3
+    /// 5 -> 2
+    fn generate_test_graph() -> Graph {
+        generate_custom_graph(
+            "image",
+            (0..6)
+                .map(|i| match i {
+                    3 => (i, hashmap! { "key".to_string() => "value".to_string() }),
+                    _ => (i, hashmap! {}),
+                })
+                .collect(),
+            Some(vec![(0, 1), (1, 2), (2, 3), (3, 4), (1, 3), (5, 2)]),
+        )
+    }
+
+    /// Returns the subgraph of the test graph with the given releases and edges,
+    /// identified by their index in the test graph.
+    fn generate_expected_graph(releases: &[usize], edges: &[(usize, usize)]) -> Graph {
+        let position = |i: &usize| releases.iter().position(|r| r == i).unwrap();
+
+        generate_custom_graph(
+            "image",
+            generate_test_graph()
+                .dag
+                .node_references()
+                .enumerate()
+                .filter(|(i, _)| releases.contains(i))
+                .map(|(i, (_, release))| match release {
+                    crate::Release::Concrete(release) => (i, release.metadata.clone()),
+                    _ => unreachable!(),
+                })
+                .collect(),
+            Some(
+                edges
+                    .iter()
+                    .map(|(from, to)| (position(from), position(to)))
+                    .collect(),
+            ),
+        )
+    }
+
+    #[test]
+    fn descendants_of_keeps_reachable_releases() -> Fallible<()> {
+        let graph = generate_test_graph();
+
+        assert_eq!(
+            graph.descendants_of("1.0.0")?,
+            generate_expected_graph(&[1, 2, 3, 4], &[(1, 2), (2, 3), (3, 4), (1, 3)])
+        );
+        assert_eq!(
+            graph.descendants_of("4.0.0")?,
+            generate_expected_graph(&[4], &[])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn ancestors_of_keeps_reaching_releases() -> Fallible<()> {
+        let graph = generate_test_graph();
+
+        assert_eq!(
+            graph.ancestors_of("2.0.0")?,
+            generate_expected_graph(&[0, 1, 2, 5], &[(0, 1), (1, 2), (5, 2)])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn neighborhood_is_bounded_by_depth() -> Fallible<()> {
+        let graph = generate_test_graph();
+
+        assert_eq!(
+            graph.neighborhood("2.0.0", 0)?,
+            generate_expected_graph(&[2], &[])
+        );
+        assert_eq!(
+            graph.neighborhood("2.0.0", 1)?,
+            generate_expected_graph(&[1, 2, 3, 5], &[(1, 2), (2, 3), (1, 3), (5, 2)])
+        );
+        assert_eq!(graph.neighborhood("2.0.0", 3)?, graph);
+
+        assert!(graph.neighborhood("6.0.0", 1).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn retain_variants_remove_all_other_releases() -> Fallible<()> {
+        let graph = generate_test_graph();
+
+        let mut descendants = generate_test_graph();
+        let v3 = descendants
+            .find_by_version("3.0.0")
+            .ok_or_else(|| format_err!("couldn't find 3.0.0"))?;
+        assert_eq!(descendants.retain_descendants_of("1.0.0")?, 2);
+        assert_eq!(descendants, graph.descendants_of("1.0.0")?);
+        assert_eq!(
+            descendants.find_by_releaseid(&v3)?.version().to_string(),
+            "3.0.0"
+        );
+
+        let mut ancestors = generate_test_graph();
+        assert_eq!(ancestors.retain_ancestors_of("2.0.0")?, 2);
+        assert_eq!(ancestors, graph.ancestors_of("2.0.0")?);
+
+        let mut neighborhood = generate_test_graph();
+        assert_eq!(neighborhood.retain_neighborhood("2.0.0", 1)?, 2);
+        assert_eq!(neighborhood, graph.neighborhood("2.0.0", 1)?);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,318 @@
+//! Integrity validation of graphs
+
+use crate::{errors, version_key, Graph, Release};
+use failure::Fallible;
+use petgraph::visit::IntoNodeReferences;
+use std::collections::HashSet;
+use std::fmt;
+
+/// Severity of a validation finding.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warning,
+    Fatal,
+}
+
+impl Default for Severity {
+    fn default() -> Self {
+        Severity::Warning
+    }
+}
+
+/// Severity of each kind of finding.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct ValidationPolicy {
+    pub disconnected_components: Severity,
+    pub abstract_releases: Severity,
+    pub orphan_releases: Severity,
+}
+
+impl ValidationPolicy {
+    /// Returns the severity of the given finding.
+    pub fn severity(&self, finding: &Finding) -> Severity {
+        match finding {
+            Finding::DisconnectedComponents(_) => self.disconnected_components,
+            Finding::AbstractRelease { .. } => self.abstract_releases,
+            Finding::OrphanRelease(_) => self.orphan_releases,
+        }
+    }
+}
+
+/// Single integrity issue of a graph.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Finding {
+    /// The graph has more than one connected component with edges.
+    ///
+    /// Releases without edges aren't part of these, but are reported as
+    /// orphan releases.
+    DisconnectedComponents(Vec<Component>),
+
+    /// An abstract release remained in the graph.
+    AbstractRelease {
+        version: String,
+        /// Versions of the releases with an edge to the abstract release.
+        previous: Vec<String>,
+    },
+
+    /// A release has neither incoming nor outgoing edges.
+    OrphanRelease(String),
+}
+
+/// Connected component of a graph.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Component {
+    /// Number of releases in the component.
+    pub size: usize,
+
+    /// Lowest version in the component.
+    pub version: String,
+}
+
+impl Finding {
+    /// Returns the kind of the finding, named after the matching `ValidationPolicy` field.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Finding::DisconnectedComponents(_) => "disconnected_components",
+            Finding::AbstractRelease { .. } => "abstract_releases",
+            Finding::OrphanRelease(_) => "orphan_releases",
+        }
+    }
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Finding::DisconnectedComponents(components) => write!(
+                f,
+                "graph consists of {} disconnected components: [{}]",
+                components.len(),
+                components
+                    .iter()
+                    .map(|component| format!(
+                        "{} releases including {}",
+                        component.size, component.version
+                    ))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Finding::AbstractRelease { version, previous } => write!(
+                f,
+                "abstract release {} remained in the graph, previous releases: [{}]",
+                version,
+                previous.join(", ")
+            ),
+            Finding::OrphanRelease(version) => write!(f, "release {} has no edges", version),
+        }
+    }
+}
+
+/// Result of validating a graph.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ValidationReport {
+    pub findings: Vec<Finding>,
+}
+
+impl ValidationReport {
+    /// Returns true if the graph has no integrity issues.
+    pub fn is_empty(&self) -> bool {
+        self.findings.is_empty()
+    }
+
+    /// Returns all findings with the given severity according to the policy.
+    pub fn findings_with_severity<'a>(
+        &'a self,
+        policy: &'a ValidationPolicy,
+        severity: Severity,
+    ) -> impl Iterator<Item = &'a Finding> {
+        self.findings
+            .iter()
+            .filter(move |finding| policy.severity(finding) == severity)
+    }
+
+    /// Fails with the `InvalidGraph` error if any finding is fatal according to the policy.
+    pub fn check(&self, policy: &ValidationPolicy) -> Fallible<()> {
+        let fatal: Vec<String> = self
+            .findings_with_severity(policy, Severity::Fatal)
+            .map(ToString::to_string)
+            .collect();
+
+        if fatal.is_empty() {
+            Ok(())
+        } else {
+            Err(errors::InvalidGraph {
+                findings: fatal.join("; "),
+            }
+            .into())
+        }
+    }
+}
+
+impl Graph {
+    /// Validates the integrity of the graph.
+    ///
+    /// The returned report lists disconnected components, remaining abstract
+    /// releases, and releases without edges.
+    pub fn validate(&self) -> ValidationReport {
+        let mut findings = vec![];
+
+        let components = self.connected_components();
+        if components.len() > 1 {
+            findings.push(Finding::DisconnectedComponents(components));
+        }
+
+        let mut releases: Vec<(daggy::NodeIndex, &Release)> = self.dag.node_references().collect();
+        releases.sort_by(|(_, a), (_, b)| a.version().cmp(b.version()));
+
+        for (index, release) in &releases {
+            if let Release::Abstract(_) = release {
+                let mut previous: Vec<String> = self
+                    .dag
+                    .neighbors_directed(*index, petgraph::Direction::Incoming)
+                    .map(|previous| self.dag[previous].version().to_string())
+                    .collect();
+                previous.sort();
+
+                findings.push(Finding::AbstractRelease {
+                    version: release.version().to_string(),
+                    previous,
+                });
+            }
+        }
+
+        for (index, release) in &releases {
+            if self.dag.neighbors_undirected(*index).next().is_none() {
+                findings.push(Finding::OrphanRelease(release.version().to_string()));
+            }
+        }
+
+        ValidationReport { findings }
+    }
+
+    /// Returns all weakly connected components with more than one release,
+    /// ignoring the edge directions.
+    ///
+    /// The components are ordered by decreasing size and then by version.
+    fn connected_components(&self) -> Vec<Component> {
+        let mut visited = HashSet::new();
+        let mut components = vec![];
+
+        for start in self.dag.node_indices() {
+            if !visited.insert(start) {
+                continue;
+            }
+
+            let mut size = 0;
+            let mut lowest = self.dag[start].version();
+            let mut to_visit = vec![start];
+            while let Some(current) = to_visit.pop() {
+                size += 1;
+                let version = self.dag[current].version();
+                if version_key(version) < version_key(lowest) {
+                    lowest = version;
+                }
+                for neighbor in self.dag.neighbors_undirected(current) {
+                    if visited.insert(neighbor) {
+                        to_visit.push(neighbor);
+                    }
+                }
+            }
+
+            if size > 1 {
+                components.push((size, version_key(lowest)));
+            }
+        }
+
+        components.sort_by(|(a_size, a), (b_size, b)| b_size.cmp(a_size).then(a.cmp(b)));
+        components
+            .into_iter()
+            .map(|(size, (version, _))| Component {
+                size,
+                version: version.to_string(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::generate_custom_graph;
+    use crate::AbstractRelease;
+
+    #[test]
+    fn validate_valid_graph() {
+        let graph = generate_custom_graph(
+            "image",
+            (0..3).map(|i| (i, Default::default())).collect(),
+            None,
+        );
+
+        let report = graph.validate();
+        assert!(report.is_empty(), "{:?}", report);
+        assert!(report.check(&ValidationPolicy::default()).is_ok());
+    }
+
+    #[test]
+    fn validate_reports_all_findings() -> Fallible<()> {
+        let mut graph = generate_custom_graph(
+            "image",
+            (0..5).map(|i| (i, Default::default())).collect(),
+            Some(vec![(0, 1), (0, 4)]),
+        );
+        let abstract_id = graph.add_release(Release::Abstract(AbstractRelease {
+            version: semver::Version::new(5, 0, 0),
+        }))?;
+        let v2 = graph
+            .find_by_version("2.0.0")
+            .ok_or_else(|| format_err!("couldn't find version 2.0.0"))?;
+        graph.add_edge(&v2, &abstract_id)?;
+
+        let report = graph.validate();
+        assert_eq!(
+            report.findings,
+            vec![
+                Finding::DisconnectedComponents(vec![
+                    Component {
+                        size: 3,
+                        version: "0.0.0".to_string(),
+                    },
+                    Component {
+                        size: 2,
+                        version: "2.0.0".to_string(),
+                    },
+                ]),
+                Finding::AbstractRelease {
+                    version: "5.0.0".to_string(),
+                    previous: vec!["2.0.0".to_string()],
+                },
+                Finding::OrphanRelease("3.0.0".to_string()),
+            ]
+        );
+
+        let policy = ValidationPolicy::default();
+        assert_eq!(
+            report
+                .findings_with_severity(&policy, Severity::Warning)
+                .count(),
+            3
+        );
+        assert!(report.check(&policy).is_ok());
+
+        let policy = ValidationPolicy {
+            orphan_releases: Severity::Fatal,
+            ..Default::default()
+        };
+        let err = report.check(&policy).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<errors::InvalidGraph>(),
+            Some(&errors::InvalidGraph {
+                findings: "release 3.0.0 has no edges".to_string()
+            })
+        );
+
+        Ok(())
+    }
+}
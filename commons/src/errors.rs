@@ -36,6 +36,10 @@ pub enum GraphError {
     #[fail(display = "failed to serialize JSON: {}", _0)]
     FailedJsonOut(String),
 
+    /// Failed to serialize protobuf.
+    #[fail(display = "failed to serialize protobuf: {}", _0)]
+    FailedProtobufOut(String),
+
     /// Error response from upstream.
     #[fail(display = "failed to fetch upstream graph: {}", _0)]
     FailedUpstreamFetch(String),
@@ -92,6 +96,7 @@ impl GraphError {
         match *self {
             GraphError::FailedJsonIn(_) => http::StatusCode::INTERNAL_SERVER_ERROR,
             GraphError::FailedJsonOut(_) => http::StatusCode::INTERNAL_SERVER_ERROR,
+            GraphError::FailedProtobufOut(_) => http::StatusCode::INTERNAL_SERVER_ERROR,
             GraphError::FailedUpstreamFetch(_) => http::StatusCode::INTERNAL_SERVER_ERROR,
             GraphError::FailedPluginExecution(_) => http::StatusCode::INTERNAL_SERVER_ERROR,
             GraphError::FailedUpstreamRequest(_) => http::StatusCode::INTERNAL_SERVER_ERROR,
@@ -107,6 +112,7 @@ impl GraphError {
         let kind = match *self {
             GraphError::FailedJsonIn(_) => "failed_json_in",
             GraphError::FailedJsonOut(_) => "failed_json_out",
+            GraphError::FailedProtobufOut(_) => "failed_protobuf_out",
             GraphError::FailedUpstreamFetch(_) => "failed_upstream_fetch",
             GraphError::FailedPluginExecution(_) => "failed_plugin_execution",
             GraphError::FailedUpstreamRequest(_) => "failed_upstream_request",
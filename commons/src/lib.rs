@@ -80,24 +80,140 @@ pub fn ensure_query_params(
     Ok(())
 }
 
-/// Make sure client requested a valid content type.
+/// Make sure client accepts one of the given content types.
+///
+/// The `Accept` header is parsed as a list of media ranges with optional
+/// quality values, e.g. `application/json, application/*;q=0.5, */*;q=0.1`.
+/// Of the content types with the highest quality, the first one in
+/// `content_types` is returned. A missing `Accept` header is rejected.
 pub fn ensure_content_type(
     headers: &actix_web::http::HeaderMap,
-    content_type: &'static str,
-) -> Result<(), GraphError> {
-    let content_json = header::HeaderValue::from_static(content_type);
-
-    if !headers
+    content_types: &[&'static str],
+) -> Result<&'static str, GraphError> {
+    let media_ranges = headers
         .get(header::ACCEPT)
-        .map(|accept| accept == content_json)
-        .unwrap_or(false)
-    {
-        Err(GraphError::InvalidContentType)
+        .and_then(|accept| accept.to_str().ok())
+        .map(parse_accept)
+        .ok_or(GraphError::InvalidContentType)?;
+
+    let mut best: Option<(&'static str, f32)> = None;
+    for content_type in content_types.iter().cloned() {
+        let quality = match quality_of(&media_ranges, content_type) {
+            Some(quality) if quality > 0.0 => quality,
+            _ => continue,
+        };
+        match best {
+            Some((_, best_quality)) if best_quality >= quality => {}
+            _ => best = Some((content_type, quality)),
+        }
+    }
+
+    best.map(|(content_type, _)| content_type)
+        .ok_or(GraphError::InvalidContentType)
+}
+
+/// Media range of an `Accept` header, with lowercase type and subtype.
+#[derive(Debug, PartialEq)]
+struct MediaRange {
+    main_type: String,
+    sub_type: String,
+    quality: f32,
+}
+
+/// Parse the media ranges of an `Accept` header, ignoring malformed ones.
+fn parse_accept(accept: &str) -> Vec<MediaRange> {
+    accept
+        .split(',')
+        .filter_map(|range| {
+            let mut parts = range.split(';').map(str::trim);
+
+            let mut media_type = parts.next()?.splitn(2, '/').map(str::trim);
+            let main_type = media_type.next()?.to_lowercase();
+            let sub_type = media_type.next()?.to_lowercase();
+            if main_type.is_empty() || sub_type.is_empty() || (main_type == "*" && sub_type != "*")
+            {
+                return None;
+            }
+
+            let mut quality = 1.0;
+            for parameter in parts {
+                let mut key_value = parameter.splitn(2, '=').map(str::trim);
+                if key_value.next()?.eq_ignore_ascii_case("q") {
+                    quality = key_value.next()?.parse::<f32>().ok()?;
+                    if !(0.0..=1.0).contains(&quality) {
+                        return None;
+                    }
+                    break;
+                }
+            }
+
+            Some(MediaRange {
+                main_type,
+                sub_type,
+                quality,
+            })
+        })
+        .collect()
+}
+
+/// Return the quality of the most specific media range which matches `content_type`.
+fn quality_of(media_ranges: &[MediaRange], content_type: &str) -> Option<f32> {
+    let mut types = content_type.splitn(2, '/');
+    let (main_type, sub_type) = (types.next()?, types.next()?);
+
+    media_ranges
+        .iter()
+        .filter_map(|range| {
+            let specificity = match (range.main_type.as_str(), range.sub_type.as_str()) {
+                ("*", "*") => 0,
+                (main, "*") if main.eq_ignore_ascii_case(main_type) => 1,
+                (main, sub)
+                    if main.eq_ignore_ascii_case(main_type)
+                        && sub.eq_ignore_ascii_case(sub_type) =>
+                {
+                    2
+                }
+                _ => return None,
+            };
+            Some((specificity, range.quality))
+        })
+        .fold(None, |best: Option<(u8, f32)>, current| match best {
+            Some(best) if best.0 >= current.0 => Some(best),
+            _ => Some(current),
+        })
+        .map(|(_, quality)| quality)
+}
+
+/// Return the entity tag for a graph with the given content digest, served as `content_type`.
+///
+/// The content type is part of the tag, so that each representation of a
+/// graph has a distinct entity tag. The tag is only `strong` if the digest
+/// determines the body byte-for-byte, e.g. if the body is the canonical
+/// serialization of the graph, otherwise it is weak.
+pub fn entity_tag(digest: &str, content_type: &str, strong: bool) -> String {
+    let tag = format!("\"{};{}\"", digest, content_type);
+    if strong {
+        tag
     } else {
-        Ok(())
+        format!("W/{}", tag)
     }
 }
 
+/// Return true if the `If-None-Match` header matches `entity_tag`.
+///
+/// The header is either `*` or a list of entity tags, which are compared
+/// weakly, i.e. ignoring a `W/` prefix.
+pub fn if_none_match(headers: &actix_web::http::HeaderMap, entity_tag: &str) -> bool {
+    let entity_tag = entity_tag.trim_start_matches("W/");
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .any(|tag| tag == "*" || tag.trim_start_matches("W/") == entity_tag)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -142,9 +258,98 @@ mod tests {
 
     #[test]
     fn test_ensure_content_type() {
-        let mut headers = actix_web::http::HeaderMap::new();
-        headers.insert(header::ACCEPT, "application/json".parse().unwrap());
-        ensure_content_type(&headers, "application/json").unwrap();
-        ensure_content_type(&headers, "text/html").unwrap_err();
+        let offered = &["application/json", "application/x-protobuf"];
+        let ensure = |accept: &'static str| {
+            let mut headers = actix_web::http::HeaderMap::new();
+            headers.insert(header::ACCEPT, header::HeaderValue::from_static(accept));
+            ensure_content_type(&headers, offered)
+        };
+
+        assert_eq!(ensure("application/json"), Ok("application/json"));
+        assert_eq!(
+            ensure("application/x-protobuf"),
+            Ok("application/x-protobuf")
+        );
+        assert_eq!(ensure("*/*"), Ok("application/json"));
+        assert_eq!(ensure("application/*"), Ok("application/json"));
+        assert_eq!(
+            ensure("application/json;q=0.5, application/x-protobuf"),
+            Ok("application/x-protobuf")
+        );
+        assert_eq!(
+            ensure("application/*;q=0.9, application/json;q=0"),
+            Ok("application/x-protobuf")
+        );
+        assert_eq!(ensure("text/html, */*;q=0.1"), Ok("application/json"));
+        assert_eq!(
+            ensure("Application/JSON; charset=utf-8"),
+            Ok("application/json")
+        );
+        assert_eq!(ensure("text/html"), Err(GraphError::InvalidContentType));
+        assert_eq!(ensure("*/*;q=0"), Err(GraphError::InvalidContentType));
+        assert_eq!(
+            ensure("application/json;q=x"),
+            Err(GraphError::InvalidContentType)
+        );
+
+        let headers = actix_web::http::HeaderMap::new();
+        assert_eq!(
+            ensure_content_type(&headers, offered),
+            Err(GraphError::InvalidContentType)
+        );
+    }
+
+    #[test]
+    fn test_parse_accept() {
+        assert_eq!(
+            parse_accept("text/*;q=0.3, text/html;level=1, */json, invalid, */*;q=0.1"),
+            vec![
+                MediaRange {
+                    main_type: "text".to_string(),
+                    sub_type: "*".to_string(),
+                    quality: 0.3,
+                },
+                MediaRange {
+                    main_type: "text".to_string(),
+                    sub_type: "html".to_string(),
+                    quality: 1.0,
+                },
+                MediaRange {
+                    main_type: "*".to_string(),
+                    sub_type: "*".to_string(),
+                    quality: 0.1,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_if_none_match() {
+        assert_eq!(
+            entity_tag("abc", "application/json", false),
+            r#"W/"abc;application/json""#
+        );
+        let tag = entity_tag("abc", "application/json", true);
+        assert_eq!(tag, r#""abc;application/json""#);
+
+        let matches = |values: &[&'static str]| {
+            let mut headers = actix_web::http::HeaderMap::new();
+            for value in values {
+                headers.append(
+                    header::IF_NONE_MATCH,
+                    header::HeaderValue::from_static(value),
+                );
+            }
+            if_none_match(&headers, &tag)
+        };
+
+        assert!(matches(&[r#""abc;application/json""#]));
+        assert!(matches(&[r#"W/"abc;application/json""#]));
+        assert!(matches(&[r#""xyz", "abc;application/json""#]));
+        assert!(matches(&[r#""xyz""#, r#""abc;application/json""#]));
+        assert!(matches(&["*"]));
+        assert!(!matches(&[]));
+        assert!(!matches(&[r#""abc""#]));
+        assert!(!matches(&[r#""abc;application/x-protobuf""#]));
     }
 }
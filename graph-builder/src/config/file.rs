@@ -2,6 +2,7 @@
 
 use super::options;
 use super::AppSettings;
+use cincinnati::validation::ValidationPolicy;
 use commons::de::de_loglevel;
 use commons::MergeOptions;
 use failure::{Fallible, ResultExt};
@@ -23,6 +24,9 @@ pub struct FileOptions {
 
     /// Status service options.
     pub status: Option<options::StatusOptions>,
+
+    /// Severities of the graph validation findings.
+    pub validation: Option<ValidationPolicy>,
 }
 
 impl FileOptions {
@@ -51,6 +55,7 @@ impl MergeOptions<Option<FileOptions>> for AppSettings {
     fn try_merge(&mut self, opts: Option<FileOptions>) -> Fallible<()> {
         if let Some(file) = opts {
             assign_if_some!(self.verbosity, file.verbosity);
+            assign_if_some!(self.validation_policy, file.validation);
             self.try_merge(file.service)?;
             self.try_merge(file.status)?;
             self.try_merge(file.upstream)?;
@@ -111,6 +116,26 @@ mod tests {
         assert_eq!(settings.status_port, 2222);
     }
 
+    #[test]
+    fn toml_merge_validation_policy() {
+        use cincinnati::validation::{Severity, ValidationPolicy};
+
+        let mut settings = AppSettings::default();
+        assert_eq!(settings.validation_policy, ValidationPolicy::default());
+
+        let toml_input = "[validation]\norphan_releases = \"fatal\"";
+        let file_opts: FileOptions = toml::from_str(toml_input).unwrap();
+
+        settings.try_merge(Some(file_opts)).unwrap();
+        assert_eq!(
+            settings.validation_policy,
+            ValidationPolicy {
+                orphan_releases: Severity::Fatal,
+                ..Default::default()
+            }
+        );
+    }
+
     #[test]
     fn toml_sample_config() {
         use tempfile;
@@ -1,4 +1,5 @@
 use super::{cli, file};
+use cincinnati::validation::ValidationPolicy;
 use commons::MergeOptions;
 use failure::Fallible;
 use std::collections::HashSet;
@@ -27,6 +28,9 @@ pub struct AppSettings {
     /// Endpoints namespace for the main service.
     pub path_prefix: String,
 
+    /// Whether to serialize the JSON graph canonically.
+    pub canonical_json: bool,
+
     /// Pause (in seconds) between registry scrapes.
     #[default(time::Duration::from_secs(30))]
     pub pause_secs: time::Duration,
@@ -52,6 +56,9 @@ pub struct AppSettings {
     #[default(9080)]
     pub status_port: u16,
 
+    /// Severities of the graph validation findings.
+    pub validation_policy: ValidationPolicy,
+
     /// Global log level.
     #[default(log::LevelFilter::Warn)]
     pub verbosity: log::LevelFilter,
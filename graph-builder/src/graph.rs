@@ -15,18 +15,26 @@
 use crate::built_info;
 use crate::config;
 use crate::registry::{self, Registry};
+use actix_web::http::header;
+use actix_web::web::Query;
 use actix_web::{HttpRequest, HttpResponse};
+use cincinnati::export::{DOT_CONTENT_TYPE, GRAPHML_CONTENT_TYPE};
 use cincinnati::plugins::prelude::*;
-use cincinnati::{AbstractRelease, Graph, Release, CONTENT_TYPE};
+use cincinnati::validation::{Finding, Severity, ValidationPolicy, ValidationReport};
+use cincinnati::{
+    AbstractRelease, ExportOptions, Graph, GraphStats, Release, CONTENT_TYPE, PROTOBUF_CONTENT_TYPE,
+};
 use commons::metrics::HasRegistry;
 use commons::GraphError;
 use failure::{Error, Fallible};
 use futures::Future;
 use lazy_static;
 pub use parking_lot::RwLock;
-use prometheus::{self, histogram_opts, labels, opts, Counter, Gauge, Histogram, IntGauge};
+use prometheus::{
+    self, histogram_opts, labels, opts, Counter, Gauge, Histogram, IntGauge, IntGaugeVec,
+};
 use serde_json;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::sync::Arc;
 use std::thread;
 
@@ -36,6 +44,50 @@ lazy_static! {
         "Number of releases in the final graph, after processing"
     )
     .unwrap();
+    static ref GRAPH_FINAL_EDGES: IntGauge = IntGauge::new(
+        "graph_final_edges",
+        "Number of edges in the final graph, after processing"
+    )
+    .unwrap();
+    static ref GRAPH_FINAL_HEADS: IntGauge = IntGauge::new(
+        "graph_final_heads",
+        "Number of releases without outgoing edges in the final graph"
+    )
+    .unwrap();
+    static ref GRAPH_FINAL_ROOTS: IntGauge = IntGauge::new(
+        "graph_final_roots",
+        "Number of releases without incoming edges in the final graph"
+    )
+    .unwrap();
+    static ref GRAPH_FINAL_LONGEST_PATH: IntGauge = IntGauge::new(
+        "graph_final_longest_path",
+        "Number of edges on the longest path in the final graph"
+    )
+    .unwrap();
+    static ref GRAPH_FINAL_IN_DEGREES: IntGaugeVec = IntGaugeVec::new(
+        opts!(
+            "graph_final_in_degree_releases",
+            "Number of releases in the final graph by their number of incoming edges"
+        ),
+        &["degree"]
+    )
+    .unwrap();
+    static ref GRAPH_FINAL_OUT_DEGREES: IntGaugeVec = IntGaugeVec::new(
+        opts!(
+            "graph_final_out_degree_releases",
+            "Number of releases in the final graph by their number of outgoing edges"
+        ),
+        &["degree"]
+    )
+    .unwrap();
+    static ref GRAPH_FINAL_METADATA_VALUES: IntGaugeVec = IntGaugeVec::new(
+        opts!(
+            "graph_final_metadata_value_releases",
+            "Number of releases in the final graph by metadata value, e.g. per channel"
+        ),
+        &["key", "value"]
+    )
+    .unwrap();
     static ref GRAPH_LAST_SUCCESSFUL_REFRESH: IntGauge = IntGauge::new(
         "graph_last_successful_refresh_timestamp",
         "UTC timestamp of last successful graph refresh"
@@ -85,6 +137,13 @@ lazy_static! {
 pub fn register_metrics(registry: &prometheus::Registry) -> Fallible<()> {
     commons::register_metrics(&registry)?;
     registry.register(Box::new(GRAPH_FINAL_RELEASES.clone()))?;
+    registry.register(Box::new(GRAPH_FINAL_EDGES.clone()))?;
+    registry.register(Box::new(GRAPH_FINAL_HEADS.clone()))?;
+    registry.register(Box::new(GRAPH_FINAL_ROOTS.clone()))?;
+    registry.register(Box::new(GRAPH_FINAL_LONGEST_PATH.clone()))?;
+    registry.register(Box::new(GRAPH_FINAL_IN_DEGREES.clone()))?;
+    registry.register(Box::new(GRAPH_FINAL_OUT_DEGREES.clone()))?;
+    registry.register(Box::new(GRAPH_FINAL_METADATA_VALUES.clone()))?;
     registry.register(Box::new(GRAPH_LAST_SUCCESSFUL_REFRESH.clone()))?;
     registry.register(Box::new(GRAPH_UPSTREAM_RAW_RELEASES.clone()))?;
     registry.register(Box::new(UPSTREAM_ERRORS.clone()))?;
@@ -100,8 +159,16 @@ pub fn register_metrics(registry: &prometheus::Registry) -> Fallible<()> {
 pub fn index(req: HttpRequest) -> Result<HttpResponse, GraphError> {
     V1_GRAPH_INCOMING_REQS.inc();
 
-    // Check that the client can accept JSON media type.
-    commons::ensure_content_type(req.headers(), CONTENT_TYPE)?;
+    // Check that the client can accept JSON or one of the alternative media types.
+    let content_type = commons::ensure_content_type(
+        req.headers(),
+        &[
+            CONTENT_TYPE,
+            PROTOBUF_CONTENT_TYPE,
+            DOT_CONTENT_TYPE,
+            GRAPHML_CONTENT_TYPE,
+        ],
+    )?;
 
     // Check for required client parameters.
     let mandatory_params = &req
@@ -110,19 +177,87 @@ pub fn index(req: HttpRequest) -> Result<HttpResponse, GraphError> {
         .mandatory_params;
     commons::ensure_query_params(mandatory_params, req.query_string())?;
 
-    let resp = HttpResponse::Ok().content_type(CONTENT_TYPE).body(
-        req.app_data::<State>()
-            .expect(commons::MISSING_APPSTATE_PANIC_MSG)
-            .json
-            .read()
-            .clone(),
-    );
-    Ok(resp)
+    let (json, snapshot) = {
+        let state = req
+            .app_data::<State>()
+            .expect(commons::MISSING_APPSTATE_PANIC_MSG);
+        let json = state.json.read();
+        (json.clone(), state.snapshot.read().clone())
+    };
+
+    // Options for the DOT and GraphML representations, other parameters are ignored.
+    let options = match content_type {
+        DOT_CONTENT_TYPE | GRAPHML_CONTENT_TYPE => {
+            Query::<ExportOptions>::from_query(req.query_string())
+                .map_err(|e| GraphError::InvalidParams(e.to_string()))?
+                .into_inner()
+        }
+        _ => ExportOptions::default(),
+    };
+
+    // Let clients which already have the current graph skip the download.
+    let entity_tag = snapshot.as_ref().map(|snapshot| match content_type {
+        CONTENT_TYPE => {
+            commons::entity_tag(&snapshot.digest, content_type, snapshot.canonical_json)
+        }
+        PROTOBUF_CONTENT_TYPE => commons::entity_tag(&snapshot.digest, content_type, false),
+        // The exported representations also depend on the options.
+        _ => commons::entity_tag(
+            &format!("{}-{}", snapshot.digest, options.digest()),
+            content_type,
+            false,
+        ),
+    });
+    if let Some(entity_tag) = &entity_tag {
+        if commons::if_none_match(req.headers(), entity_tag) {
+            return Ok(HttpResponse::NotModified()
+                .header(header::ETAG, entity_tag.as_str())
+                .finish());
+        }
+    }
+
+    // Until the first refresh, the alternative representations are of the empty graph.
+    let body = match content_type {
+        CONTENT_TYPE => json.into_bytes(),
+        PROTOBUF_CONTENT_TYPE => match snapshot {
+            Some(snapshot) => snapshot.protobuf.clone(),
+            None => Graph::default().to_protobuf().map_err(|e| e.to_string()),
+        }
+        .map_err(GraphError::FailedProtobufOut)?,
+        other_content_type => {
+            let graph = snapshot
+                .map(|snapshot| snapshot.graph.clone())
+                .unwrap_or_default();
+            match other_content_type {
+                DOT_CONTENT_TYPE => graph.to_dot(&options).into_bytes(),
+                _ => graph.to_graphml(&options).into_bytes(),
+            }
+        }
+    };
+
+    let mut resp = HttpResponse::Ok();
+    resp.content_type(content_type);
+    if let Some(entity_tag) = entity_tag {
+        resp.header(header::ETAG, entity_tag);
+    }
+    Ok(resp.body(body))
+}
+
+/// Representations of the graph in `State::json`, computed once per refresh.
+struct Snapshot {
+    digest: String,
+    /// Whether `State::json` is the canonical serialization the digest was computed from.
+    canonical_json: bool,
+    graph: Arc<Graph>,
+    /// Graphs with abstract releases have no protobuf representation.
+    protobuf: Result<Vec<u8>, String>,
 }
 
 #[derive(Clone)]
 pub struct State {
     json: Arc<RwLock<String>>,
+    /// Snapshot of the graph in `json`, written while holding its lock.
+    snapshot: Arc<RwLock<Option<Arc<Snapshot>>>>,
     /// Query parameters that must be present in all client requests.
     mandatory_params: HashSet<String>,
     live: Arc<RwLock<bool>>,
@@ -143,6 +278,7 @@ impl State {
     ) -> State {
         State {
             json,
+            snapshot: Arc::new(RwLock::new(None)),
             mandatory_params,
             live,
             ready,
@@ -193,6 +329,9 @@ pub fn run<'a>(settings: &'a config::AppSettings, state: &State) -> ! {
     let mut first_iteration = true;
     let mut first_success = true;
 
+    // Graph of the last successful iteration, used to log the changes of each update
+    let mut previous_graph: Option<Arc<Graph>> = None;
+
     BUILD_INFO.inc();
 
     loop {
@@ -270,15 +409,49 @@ pub fn run<'a>(settings: &'a config::AppSettings, state: &State) -> ! {
             }
         };
 
-        let json_graph = match serde_json::to_string(&graph) {
-            Ok(json) => json,
+        let validation_report = graph.validate();
+        log_validation_warnings(&validation_report, &settings.validation_policy);
+        if let Err(err) = validation_report.check(&settings.validation_policy) {
+            err.iter_chain().for_each(|cause| error!("{}", cause));
+            continue;
+        }
+
+        let (canonical_json, digest) = match graph.canonical_json_with_digest() {
+            Ok(canonical_json_with_digest) => canonical_json_with_digest,
             Err(err) => {
-                error!("Failed to serialize graph: {}", err);
+                error!("Failed to compute graph digest: {}", err);
                 continue;
             }
         };
 
-        *state.json.write() = json_graph;
+        let json_graph = if settings.canonical_json {
+            canonical_json
+        } else {
+            match serde_json::to_string(&graph) {
+                Ok(json) => json,
+                Err(err) => {
+                    error!("Failed to serialize graph: {}", err);
+                    continue;
+                }
+            }
+        };
+
+        let protobuf = graph.to_protobuf().map_err(|err| {
+            warn!("Failed to serialize graph to protobuf: {}", err);
+            err.to_string()
+        });
+
+        let graph = Arc::new(graph);
+        {
+            let mut json = state.json.write();
+            *state.snapshot.write() = Some(Arc::new(Snapshot {
+                digest,
+                canonical_json: settings.canonical_json,
+                graph: graph.clone(),
+                protobuf,
+            }));
+            *json = json_graph;
+        }
 
         if first_success {
             *state.ready.write() = true;
@@ -292,7 +465,67 @@ pub fn run<'a>(settings: &'a config::AppSettings, state: &State) -> ! {
 
         let nodes_count = graph.releases_count();
         GRAPH_FINAL_RELEASES.set(nodes_count as i64);
+        update_stats_metrics(&graph.stats());
         debug!("graph update completed, {} valid releases", nodes_count);
+
+        if let Some(previous_graph) = &previous_graph {
+            let diff = previous_graph.diff(&graph);
+            if !diff.is_empty() {
+                info!("graph changed since the last update:\n{}", diff);
+            }
+        }
+        previous_graph = Some(graph);
+    }
+}
+
+/// Logs one summary line per kind of non-fatal validation finding, and the findings themselves at debug level.
+fn log_validation_warnings(report: &ValidationReport, policy: &ValidationPolicy) {
+    let mut warnings: BTreeMap<&str, Vec<&Finding>> = BTreeMap::new();
+    for finding in report.findings_with_severity(policy, Severity::Warning) {
+        warnings.entry(finding.kind()).or_default().push(finding);
+    }
+
+    for (kind, findings) in warnings {
+        warn!(
+            "graph validation: {} finding(s) of kind {}, e.g. {}",
+            findings.len(),
+            kind,
+            findings[0]
+        );
+        findings
+            .iter()
+            .for_each(|finding| debug!("graph validation: {}", finding));
+    }
+}
+
+/// Publishes the statistics of the final graph, replacing the previous ones.
+fn update_stats_metrics(stats: &GraphStats) {
+    GRAPH_FINAL_EDGES.set(stats.edges as i64);
+    GRAPH_FINAL_HEADS.set(stats.heads as i64);
+    GRAPH_FINAL_ROOTS.set(stats.roots as i64);
+    GRAPH_FINAL_LONGEST_PATH.set(stats.longest_path as i64);
+
+    GRAPH_FINAL_IN_DEGREES.reset();
+    for (degree, count) in &stats.in_degrees {
+        GRAPH_FINAL_IN_DEGREES
+            .with_label_values(&[&degree.to_string()])
+            .set(*count as i64);
+    }
+
+    GRAPH_FINAL_OUT_DEGREES.reset();
+    for (degree, count) in &stats.out_degrees {
+        GRAPH_FINAL_OUT_DEGREES
+            .with_label_values(&[&degree.to_string()])
+            .set(*count as i64);
+    }
+
+    GRAPH_FINAL_METADATA_VALUES.reset();
+    for (key, values) in &stats.metadata_values {
+        for (value, count) in values {
+            GRAPH_FINAL_METADATA_VALUES
+                .with_label_values(&[key, value])
+                .set(*count as i64);
+        }
     }
 }
 
@@ -334,7 +567,7 @@ pub fn create_graph(releases: Vec<registry::Release>) -> Result<Graph, Error> {
                 let previous = match graph.find_by_version(&version.to_string()) {
                     Some(id) => id,
                     None => graph.add_release(Release::Abstract(AbstractRelease {
-                        version: version.to_string(),
+                        version: version.clone(),
                     }))?,
                 };
                 graph.add_edge(&previous, &current)
@@ -344,7 +577,7 @@ pub fn create_graph(releases: Vec<registry::Release>) -> Result<Graph, Error> {
                 let next = match graph.find_by_version(&version.to_string()) {
                     Some(id) => id,
                     None => graph.add_release(Release::Abstract(AbstractRelease {
-                        version: version.to_string(),
+                        version: version.clone(),
                     }))?,
                 };
                 graph.add_edge(&current, &next)
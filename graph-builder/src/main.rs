@@ -70,6 +70,7 @@ fn main() -> Result<(), Error> {
             ),
             InternalPluginWrapper(NodeRemovePlugin {
                 key_prefix: DEFAULT_QUAY_LABEL_FILTER.to_string(),
+                query: None,
             }),
             InternalPluginWrapper(EdgeAddRemovePlugin {
                 key_prefix: DEFAULT_QUAY_LABEL_FILTER.to_string(),
@@ -28,15 +28,15 @@ use tar::Archive;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Release {
-    pub source: String,
+    pub source: cincinnati::ImageReference,
     pub metadata: Metadata,
 }
 
 impl Into<cincinnati::Release> for Release {
     fn into(self) -> cincinnati::Release {
         cincinnati::Release::Concrete(cincinnati::ConcreteRelease {
-            version: self.metadata.version.to_string(),
-            payload: self.source,
+            version: self.metadata.version,
+            payload: self.source.to_string(),
             metadata: self.metadata.metadata,
         })
     }
@@ -269,6 +269,7 @@ pub fn fetch_releases<S: ::std::hash::BuildHasher>(
             layers_digests,
             authenticated_client.to_owned(),
             registry.host.to_owned().to_string(),
+            registry.port,
             repo.to_owned(),
             tag.to_owned(),
             cache,
@@ -283,12 +284,8 @@ pub fn fetch_releases<S: ::std::hash::BuildHasher>(
         };
 
         // Replace the tag specifier with the manifestref
-        release.source = {
-            let mut source_split: Vec<&str> = release.source.split(':').collect();
-            let _ = source_split.pop();
-
-            format!("{}@{}", source_split.join(":"), manifestref)
-        };
+        release.source.tag = None;
+        release.source.digest = Some(manifestref.clone());
 
         // Attach the manifestref this release was found in for further processing
         release
@@ -329,6 +326,7 @@ fn cache_release<S: ::std::hash::BuildHasher>(
     layer_digests: Vec<String>,
     authenticated_client: dkregistry::v2::Client,
     registry_host: String,
+    registry_port: Option<u16>,
     repo: String,
     tag: String,
     cache: &mut HashMap<u64, Option<Release>, S>,
@@ -356,6 +354,7 @@ fn cache_release<S: ::std::hash::BuildHasher>(
         layer_digests,
         authenticated_client,
         registry_host,
+        registry_port,
         repo,
         tag,
     );
@@ -404,6 +403,7 @@ fn find_first_release(
     layer_digests: Vec<String>,
     authenticated_client: dkregistry::v2::Client,
     registry_host: String,
+    registry_port: Option<u16>,
     repo: String,
     repo_tag: String,
 ) -> impl Future<Item = (String, Option<Release>), Error = Error> {
@@ -430,7 +430,13 @@ fn find_first_release(
 
                 match assemble_metadata(&blob, metadata_filename) {
                     Ok(metadata) => Some(Release {
-                        source: format!("{}/{}:{}", registry_host, repo, &tag),
+                        source: cincinnati::ImageReference {
+                            host: Some(registry_host.clone()),
+                            port: registry_port,
+                            repository: repo.clone(),
+                            tag: Some(tag.clone()),
+                            digest: None,
+                        },
                         metadata,
                     }),
                     Err(e) => {
@@ -56,7 +56,9 @@ fn expected_releases(
                 format!("{}@{}", source_base, payload_shas[i])
             } else {
                 format!("{}:0.0.{}", source_base, i + start)
-            },
+            }
+            .parse()
+            .unwrap(),
             metadata: Metadata {
                 kind: V0,
                 version: Version {
@@ -94,9 +96,8 @@ fn remove_metadata_by_key(releases: &mut Vec<Release>, key: &str) {
 
 fn replace_sha_by_version_in_source(releases: &mut Vec<Release>) {
     for release in releases.iter_mut() {
-        let version = release.metadata.version.to_string();
-        let source_front = release.source.split("@").nth(0).unwrap();
-        release.source = format!("{}:{}", source_front, version);
+        release.source.digest = None;
+        release.source.tag = Some(release.metadata.version.to_string());
     }
 }
 
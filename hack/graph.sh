@@ -7,6 +7,11 @@
 # For example:
 #
 #   curl -sH 'Accept:application/json' 'https://api.openshift.com/api/upgrades_info/v1/graph?channel=prerelease-4.1' | graph.sh | dot -Tsvg >graph.svg
+#
+# Cincinnati services can also render DOT themselves, which additionally shows
+# conditional edges:
+#
+#   curl -sH 'Accept:text/vnd.graphviz' 'https://cincinnati.example.com/v1/graph?channel=prerelease-4.1' | dot -Tsvg >graph.svg
 set -e
 
 JQ_SCRIPT='"digraph Upgrades {\n  labelloc=t;\n  rankdir=BT;" as $header |
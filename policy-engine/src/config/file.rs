@@ -197,4 +197,39 @@ mod tests {
         let policies = settings.policy_plugins(None).unwrap();
         assert_eq!(policies, expected);
     }
+
+    #[test]
+    fn toml_subprocess_policy() {
+        use cincinnati::plugins::external::subprocess::SubprocessPlugin;
+        use cincinnati::plugins::prelude::*;
+
+        let expected: Vec<BoxedPlugin> = new_plugins!(ExternalPluginWrapper(SubprocessPlugin {
+            command: std::path::PathBuf::from("/usr/local/bin/policy"),
+            args: vec!["--verbose".to_string()],
+            timeout: std::time::Duration::from_secs(5),
+        }));
+        let mut settings = AppSettings::default();
+
+        let opts = {
+            use std::io::Write;
+
+            let sample_config = r#"
+                [[policy]]
+                name = "subprocess"
+                command = "/usr/local/bin/policy"
+                args = ["--verbose"]
+                timeout_secs = 5
+            "#;
+
+            let mut config_file = tempfile::NamedTempFile::new().unwrap();
+            config_file
+                .write_fmt(format_args!("{}", sample_config))
+                .unwrap();
+            crate::config::FileOptions::read_filepath(config_file.path()).unwrap()
+        };
+        settings.try_merge(Some(opts)).unwrap();
+
+        let policies = settings.policy_plugins(None).unwrap();
+        assert_eq!(policies, expected);
+    }
 }
@@ -49,6 +49,10 @@ pub struct ServiceOptions {
         parse(from_str = "parse_params_set")
     )]
     pub mandatory_client_parameters: Option<HashSet<String>>,
+
+    /// Serialize JSON graphs canonically, independent of the insertion order
+    #[structopt(long = "service.canonical_json")]
+    pub canonical_json: Option<bool>,
 }
 
 impl MergeOptions<Option<ServiceOptions>> for AppSettings {
@@ -57,6 +61,7 @@ impl MergeOptions<Option<ServiceOptions>> for AppSettings {
             assign_if_some!(self.address, service.address);
             assign_if_some!(self.port, service.port);
             assign_if_some!(self.path_prefix, service.path_prefix);
+            assign_if_some!(self.canonical_json, service.canonical_json);
             if let Some(params) = service.mandatory_client_parameters {
                 self.mandatory_client_parameters.extend(params);
             }
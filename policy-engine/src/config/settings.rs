@@ -46,6 +46,9 @@ pub struct AppSettings {
 
     /// Required client parameters for the main service.
     pub mandatory_client_parameters: HashSet<String>,
+
+    /// Whether to serialize JSON graphs canonically.
+    pub canonical_json: bool,
 }
 
 impl AppSettings {
@@ -106,6 +109,7 @@ impl AppSettings {
         use cincinnati::plugins::internal::arch_filter::ArchFilterPlugin;
         use cincinnati::plugins::internal::channel_filter::ChannelFilterPlugin;
         use cincinnati::plugins::internal::cincinnati_graph_fetch::CincinnatiGraphFetchPlugin;
+        use cincinnati::plugins::internal::conditional_edges::ConditionalEdgesPlugin;
         use std::iter::FromIterator;
 
         Ok(vec![
@@ -141,6 +145,7 @@ impl AppSettings {
                     cincinnati::plugins::internal::arch_filter::DEFAULT_DEFAULT_ARCH_THRESHOLD_VERSION
                 )
             )?,
+            plugin_config!(("name", ConditionalEdgesPlugin::PLUGIN_NAME))?,
         ])
     }
 }
@@ -1,9 +1,11 @@
 //! Cincinnati graph service.
 
 use crate::AppState;
+use actix_web::http::header;
 use actix_web::web::Query;
 use actix_web::{HttpRequest, HttpResponse};
-use cincinnati::CONTENT_TYPE;
+use cincinnati::export::{DOT_CONTENT_TYPE, GRAPHML_CONTENT_TYPE};
+use cincinnati::{ExportOptions, CONTENT_TYPE, PROTOBUF_CONTENT_TYPE};
 use commons::{self, GraphError};
 use failure::Fallible;
 use futures::{future, Future};
@@ -38,10 +40,19 @@ pub(crate) fn register_metrics(registry: &Registry) -> Fallible<()> {
 pub(crate) fn index(req: HttpRequest) -> Box<dyn Future<Item = HttpResponse, Error = GraphError>> {
     V1_GRAPH_INCOMING_REQS.inc();
 
-    // Check that the client can accept JSON media type.
-    if let Err(e) = commons::ensure_content_type(req.headers(), CONTENT_TYPE) {
-        return Box::new(future::err(e));
-    }
+    // Check that the client can accept JSON or one of the alternative media types.
+    let content_type = match commons::ensure_content_type(
+        req.headers(),
+        &[
+            CONTENT_TYPE,
+            PROTOBUF_CONTENT_TYPE,
+            DOT_CONTENT_TYPE,
+            GRAPHML_CONTENT_TYPE,
+        ],
+    ) {
+        Ok(content_type) => content_type,
+        Err(e) => return Box::new(future::err(e)),
+    };
 
     // Check for required client parameters.
     let mandatory_params = &req
@@ -61,10 +72,24 @@ pub(crate) fn index(req: HttpRequest) -> Box<dyn Future<Item = HttpResponse, Err
         }
     };
 
-    let plugins = req
-        .app_data::<AppState>()
-        .expect(commons::MISSING_APPSTATE_PANIC_MSG)
-        .plugins;
+    // Options for the DOT and GraphML representations, other parameters are ignored.
+    let export_options = match Query::<ExportOptions>::from_query(req.query_string()) {
+        Ok(query) => query.into_inner(),
+        Err(e) => {
+            return Box::new(futures::future::err(commons::GraphError::InvalidParams(
+                e.to_string(),
+            )))
+        }
+    };
+
+    let (plugins, canonical_json_enabled) = {
+        let state = req
+            .app_data::<AppState>()
+            .expect(commons::MISSING_APPSTATE_PANIC_MSG);
+        (state.plugins, state.canonical_json)
+    };
+
+    let headers = req.headers().clone();
 
     let timer = V1_GRAPH_SERVE_HIST.start_timer();
     let serve = futures::future::ok(())
@@ -81,14 +106,34 @@ pub(crate) fn index(req: HttpRequest) -> Box<dyn Future<Item = HttpResponse, Err
                 Err(other_error) => GraphError::FailedPluginExecution(other_error.to_string()),
             })
         })
-        .and_then(|internal_io| {
-            serde_json::to_string(&internal_io.graph)
-                .map_err(|e| GraphError::FailedJsonOut(e.to_string()))
-        })
-        .map(|graph_json| {
-            HttpResponse::Ok()
-                .content_type(CONTENT_TYPE)
-                .body(graph_json)
+        .and_then(move |internal_io| -> Result<HttpResponse, GraphError> {
+            let graph = internal_io.graph;
+            let body = match content_type {
+                PROTOBUF_CONTENT_TYPE => graph
+                    .to_protobuf()
+                    .map_err(|e| GraphError::FailedProtobufOut(e.to_string()))?,
+                DOT_CONTENT_TYPE => graph.to_dot(&export_options).into_bytes(),
+                GRAPHML_CONTENT_TYPE => graph.to_graphml(&export_options).into_bytes(),
+                _ if canonical_json_enabled => serde_json::to_vec(&graph.canonical())
+                    .map_err(|e| GraphError::FailedJsonOut(e.to_string()))?,
+                _ => serde_json::to_vec(&graph)
+                    .map_err(|e| GraphError::FailedJsonOut(e.to_string()))?,
+            };
+
+            // The graph is processed for each request, so the tag is the digest of
+            // the body itself and clients which already have it skip the download.
+            let digest = cincinnati::digest::digest(&body);
+            let entity_tag = commons::entity_tag(&digest, content_type, true);
+            if commons::if_none_match(&headers, &entity_tag) {
+                return Ok(HttpResponse::NotModified()
+                    .header(header::ETAG, entity_tag)
+                    .finish());
+            }
+
+            Ok(HttpResponse::Ok()
+                .content_type(content_type)
+                .header(header::ETAG, entity_tag)
+                .body(body))
         })
         .then(move |r| {
             timer.observe_duration();
@@ -137,6 +182,59 @@ mod tests {
         assert_eq!(resp, graph::GraphError::InvalidContentType);
     }
 
+    #[test]
+    fn protobuf_response() {
+        let mut rt = common_init();
+
+        let http_req = actix_web::test::TestRequest::get()
+            .data(AppState::default())
+            .header(
+                http::header::ACCEPT,
+                http::header::HeaderValue::from_static(
+                    "application/json;q=0.5, application/x-protobuf",
+                ),
+            )
+            .to_http_request();
+        let graph_call = graph::index(http_req);
+        let resp = rt.block_on(graph_call).unwrap();
+
+        assert_eq!(
+            resp.headers().get(http::header::CONTENT_TYPE),
+            Some(&http::header::HeaderValue::from_static(
+                cincinnati::PROTOBUF_CONTENT_TYPE
+            ))
+        );
+    }
+
+    #[test]
+    fn not_modified_response() {
+        let mut rt = common_init();
+        let request = |if_none_match: Option<&http::header::HeaderValue>| {
+            let mut http_req = actix_web::test::TestRequest::get()
+                .data(AppState::default())
+                .header(
+                    http::header::ACCEPT,
+                    http::header::HeaderValue::from_static(cincinnati::CONTENT_TYPE),
+                );
+            if let Some(entity_tag) = if_none_match {
+                http_req = http_req.header(http::header::IF_NONE_MATCH, entity_tag.clone());
+            }
+            graph::index(http_req.to_http_request())
+        };
+
+        let resp = rt.block_on(request(None)).unwrap();
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        let entity_tag = resp.headers().get(http::header::ETAG).unwrap().clone();
+
+        let resp = rt.block_on(request(Some(&entity_tag))).unwrap();
+        assert_eq!(resp.status(), http::StatusCode::NOT_MODIFIED);
+        assert_eq!(resp.headers().get(http::header::ETAG), Some(&entity_tag));
+
+        let other_entity_tag = http::header::HeaderValue::from_static("\"other\"");
+        let resp = rt.block_on(request(Some(&other_entity_tag))).unwrap();
+        assert_eq!(resp.status(), http::StatusCode::OK);
+    }
+
     #[test]
     fn missing_mandatory_params() {
         let mut rt = common_init();
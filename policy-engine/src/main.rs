@@ -95,6 +95,7 @@ fn main() -> Result<(), Error> {
         mandatory_params: settings.mandatory_client_parameters.clone(),
         path_prefix: settings.path_prefix.clone(),
         plugins: Box::leak(Box::new(plugins)),
+        canonical_json: settings.canonical_json,
     };
 
     HttpServer::new(move || {
@@ -127,6 +128,8 @@ struct AppState {
     pub path_prefix: String,
     /// Policy plugins.
     pub plugins: &'static [BoxedPlugin],
+    /// Whether to serialize JSON graphs canonically.
+    pub canonical_json: bool,
 }
 
 impl Default for AppState {
@@ -135,6 +138,7 @@ impl Default for AppState {
             plugins: Box::leak(Box::new([])),
             mandatory_params: HashSet::new(),
             path_prefix: String::new(),
+            canonical_json: false,
         }
     }
 }
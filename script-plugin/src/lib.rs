@@ -0,0 +1,714 @@
+//! Internal Cincinnati plugin which runs a script on the graph
+//!
+//! Scripts are written in [Rhai](https://rhai.rs) and are either given inline
+//! or read from a file. The graph is available to the script as `graph` and
+//! the request parameters as the map `parameters`, both of which may be
+//! modified. Releases are referred to by their version:
+//!
+//!  * `graph.releases()`: versions of all releases.
+//!  * `graph.contains(version)`: whether the release exists.
+//!  * `graph.payload(version)`: payload of the release, empty for abstract releases.
+//!  * `graph.metadata(version)`: copy of the metadata of the release.
+//!  * `graph.set_metadata(version, key, value)`, `graph.remove_metadata(version, key)`
+//!  * `graph.remove_release(version)`
+//!  * `graph.edges()`: all edges, as `[from, to]` pairs.
+//!  * `graph.edge_metadata(from, to)`: copy of the metadata of the edge.
+//!  * `graph.edge_risks(from, to)`: copy of the risks of the edge, as maps with
+//!    `name`, `url`, `message` and `matching_rules`, the latter being maps
+//!    with `parameter` and `values`.
+//!  * `graph.next_releases(version)`, `graph.previous_releases(version)`
+//!  * `graph.add_edge(from, to)`, `graph.remove_edge(from, to)`
+//!
+//! Scripts can't access anything but the graph and the parameters, and run
+//! with limits on the number of operations, on the size of their data and on
+//! their running time.
+//!
+//! The plugin isn't part of the *cincinnati* plugin catalog, as its
+//! dependencies need a newer Rust toolchain than the rest of the workspace.
+//! Services using it look up their configuration with `deserialize_config`
+//! from this crate, which knows about all the catalog's plugins as well.
+
+#[macro_use]
+extern crate cincinnati;
+#[macro_use]
+extern crate custom_debug_derive;
+#[macro_use]
+extern crate failure;
+#[macro_use]
+extern crate log;
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate smart_default;
+
+use cincinnati::plugins::{
+    AsyncIO, BoxedPlugin, InternalIO, InternalPlugin, InternalPluginWrapper, PluginSettings,
+};
+use cincinnati::{Graph, Release, ReleaseId, Risk};
+use failure::{Fallible, ResultExt};
+use futures::sync::oneshot;
+use futures::Future;
+use prometheus::Registry;
+use rhai::{Array, Dynamic, Engine, EvalAltResult, Map, Scope, AST};
+use std::cell::Cell;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Default limit for the number of operations of a single run.
+pub const DEFAULT_MAX_OPERATIONS: u64 = 1_000_000;
+
+/// Default limit for the running time of a single run, in seconds.
+pub const DEFAULT_TIMEOUT_SECS: u64 = 10;
+
+/// Number of operations after which the running time is checked.
+const TIMEOUT_CHECK_INTERVAL: u64 = 1024;
+
+/// Maximum depth of nested function calls.
+const MAX_CALL_LEVELS: usize = 64;
+
+/// Maximum depth of nested expressions, at global and at function level.
+const MAX_EXPR_DEPTHS: (usize, usize) = (64, 32);
+
+/// Maximum length of strings, in bytes.
+const MAX_STRING_SIZE: usize = 1024 * 1024;
+
+/// Maximum number of elements of arrays and maps.
+const MAX_COLLECTION_SIZE: usize = 1_000_000;
+
+/// Name of the variable holding the graph.
+pub static GRAPH_VARIABLE: &str = "graph";
+
+/// Name of the variable holding the request parameters.
+pub static PARAMETERS_VARIABLE: &str = "parameters";
+
+thread_local! {
+    /// Time at which the script running on the current thread is terminated.
+    static DEADLINE: Cell<Option<Instant>> = Cell::new(None);
+}
+
+/// Validate configuration for this or any plugin of the *cincinnati* catalog.
+pub fn deserialize_config(cfg: toml::Value) -> Fallible<Box<dyn PluginSettings>> {
+    match cfg.get("name").and_then(toml::Value::as_str) {
+        Some(ScriptPlugin::PLUGIN_NAME) => ScriptPlugin::deserialize_config(cfg),
+        _ => cincinnati::plugins::deserialize_config(cfg),
+    }
+}
+
+/// Plugin settings.
+#[derive(Clone, Debug, Deserialize, SmartDefault)]
+#[serde(default)]
+struct ScriptPluginSettings {
+    script: Option<String>,
+    script_path: Option<PathBuf>,
+
+    #[default(DEFAULT_MAX_OPERATIONS)]
+    max_operations: u64,
+
+    #[default(DEFAULT_TIMEOUT_SECS)]
+    timeout_secs: u64,
+}
+
+impl PluginSettings for ScriptPluginSettings {
+    fn build_plugin(&self, _: Option<&Registry>) -> Fallible<BoxedPlugin> {
+        let timeout = Duration::from_secs(self.timeout_secs);
+        let plugin = match (&self.script, &self.script_path) {
+            (Some(script), _) => ScriptPlugin::try_new(script, self.max_operations, timeout)?,
+            (None, Some(path)) => {
+                let script = std::fs::read_to_string(path)
+                    .context(format!("failed to read {}", path.display()))?;
+                ScriptPlugin::try_new(&script, self.max_operations, timeout)
+                    .context(format!("failed to load {}", path.display()))?
+            }
+            (None, None) => bail!("missing script"),
+        };
+        Ok(new_plugin!(InternalPluginWrapper(plugin)))
+    }
+}
+
+/// Plugin which runs a script on the graph, see the module documentation.
+#[derive(CustomDebug)]
+pub struct ScriptPlugin {
+    /// Limit for the number of operations of a single run
+    pub max_operations: u64,
+
+    /// Time after which a single run is terminated
+    pub timeout: Duration,
+
+    #[debug(skip)]
+    engine: Arc<Engine>,
+
+    #[debug(skip)]
+    ast: Arc<AST>,
+}
+
+impl ScriptPlugin {
+    /// Plugin name, for configuration.
+    pub const PLUGIN_NAME: &'static str = "script";
+
+    /// Validate plugin configuration and fill in defaults.
+    pub fn deserialize_config(cfg: toml::Value) -> Fallible<Box<dyn PluginSettings>> {
+        let settings: ScriptPluginSettings = cfg.try_into()?;
+
+        ensure!(
+            settings.script.is_some() != settings.script_path.is_some(),
+            "exactly one of script and script_path must be set"
+        );
+        ensure!(settings.max_operations > 0, "zero max_operations");
+        ensure!(settings.timeout_secs > 0, "zero timeout_secs");
+
+        Ok(Box::new(settings))
+    }
+
+    /// Compiles the given script.
+    pub fn try_new(script: &str, max_operations: u64, timeout: Duration) -> Fallible<Self> {
+        let mut engine = Engine::new();
+        engine
+            .set_max_operations(max_operations)
+            .set_max_call_levels(MAX_CALL_LEVELS)
+            .set_max_expr_depths(MAX_EXPR_DEPTHS.0, MAX_EXPR_DEPTHS.1)
+            .set_max_string_size(MAX_STRING_SIZE)
+            .set_max_array_size(MAX_COLLECTION_SIZE)
+            .set_max_map_size(MAX_COLLECTION_SIZE)
+            .on_progress(|operations| {
+                if operations % TIMEOUT_CHECK_INTERVAL != 0 {
+                    return None;
+                }
+                let expired = DEADLINE.with(|deadline| {
+                    deadline
+                        .get()
+                        .map(|deadline| Instant::now() >= deadline)
+                        .unwrap_or(false)
+                });
+                if expired {
+                    Some(Dynamic::UNIT)
+                } else {
+                    None
+                }
+            })
+            .on_print(|text| info!("[script] {}", text))
+            .on_debug(|text, _, pos| debug!("[script] {}: {}", pos, text));
+
+        engine
+            .register_type_with_name::<ScriptGraph>("Graph")
+            .register_fn("releases", ScriptGraph::releases)
+            .register_fn("contains", ScriptGraph::contains)
+            .register_fn("payload", ScriptGraph::payload)
+            .register_fn("metadata", ScriptGraph::metadata)
+            .register_fn("set_metadata", ScriptGraph::set_metadata)
+            .register_fn("remove_metadata", ScriptGraph::remove_metadata)
+            .register_fn("remove_release", ScriptGraph::remove_release)
+            .register_fn("edges", ScriptGraph::edges)
+            .register_fn("edge_metadata", ScriptGraph::edge_metadata)
+            .register_fn("edge_risks", ScriptGraph::edge_risks)
+            .register_fn("next_releases", ScriptGraph::next_releases)
+            .register_fn("previous_releases", ScriptGraph::previous_releases)
+            .register_fn("add_edge", ScriptGraph::add_edge)
+            .register_fn("remove_edge", ScriptGraph::remove_edge);
+
+        let ast = engine
+            .compile(script)
+            .map_err(|e| format_err!("failed to compile script: {}", e))?;
+
+        Ok(Self {
+            max_operations,
+            timeout,
+            engine: Arc::new(engine),
+            ast: Arc::new(ast),
+        })
+    }
+}
+
+impl InternalPlugin for ScriptPlugin {
+    fn run_internal(self: &Self, io: InternalIO) -> AsyncIO<InternalIO> {
+        let (engine, ast, timeout) = (self.engine.clone(), self.ast.clone(), self.timeout);
+
+        // The script is run on a separate thread to not block the executor.
+        let (tx, rx) = oneshot::channel();
+        thread::spawn(move || {
+            DEADLINE.with(|deadline| deadline.set(Some(Instant::now() + timeout)));
+            let _ = tx.send(run(&engine, &ast, timeout, io));
+        });
+
+        Box::new(rx.then(|result| match result {
+            Ok(result) => result,
+            Err(_) => Err(format_err!("script plugin terminated unexpectedly")),
+        }))
+    }
+}
+
+/// Runs the script on the given graph and parameters.
+///
+/// The script is terminated once the deadline of the current thread has passed.
+fn run(engine: &Engine, ast: &AST, timeout: Duration, io: InternalIO) -> Fallible<InternalIO> {
+    let graph = Arc::new(Mutex::new(io.graph));
+    let parameters: Map = io
+        .parameters
+        .into_iter()
+        .map(|(key, value)| (key.into(), value.into()))
+        .collect();
+
+    let mut scope = Scope::new();
+    scope.push(GRAPH_VARIABLE, ScriptGraph(graph.clone()));
+    scope.push(PARAMETERS_VARIABLE, parameters);
+
+    engine
+        .run_ast_with_scope(&mut scope, ast)
+        .map_err(|e| match *e {
+            EvalAltResult::ErrorTerminated(..) => {
+                format_err!("script timed out after {:?}", timeout)
+            }
+            e => format_err!("script failed: {}", e),
+        })?;
+
+    let parameters = scope
+        .get_value::<Map>(PARAMETERS_VARIABLE)
+        .ok_or_else(|| format_err!("script changed the type of the parameters"))?
+        .into_iter()
+        .map(|(key, value)| match value.into_string() {
+            Ok(value) => Ok((key.to_string(), value)),
+            Err(kind) => bail!("parameter '{}' has type {}, expected a string", key, kind),
+        })
+        .collect::<Fallible<_>>()?;
+
+    // Drop all other references to the graph held by the script.
+    drop(scope);
+    let graph = Arc::try_unwrap(graph)
+        .map_err(|_| format_err!("graph is still referenced by the script"))?
+        .into_inner()
+        .map_err(|_| format_err!("graph lock is poisoned"))?;
+
+    Ok(InternalIO { graph, parameters })
+}
+
+type ScriptResult<T> = Result<T, Box<EvalAltResult>>;
+
+/// Handle to the graph, as exposed to scripts.
+#[derive(Clone)]
+struct ScriptGraph(Arc<Mutex<Graph>>);
+
+impl ScriptGraph {
+    /// Runs `f` on the locked graph and converts its errors to script errors.
+    fn with_graph<F, T>(&self, f: F) -> ScriptResult<T>
+    where
+        F: FnOnce(&mut Graph) -> Fallible<T>,
+    {
+        let mut graph = self.0.lock().map_err(|_| "graph lock is poisoned")?;
+        f(&mut graph).map_err(|e| e.to_string().into())
+    }
+
+    fn releases(&mut self) -> ScriptResult<Array> {
+        self.with_graph(|graph| {
+            Ok(graph
+                .iter_releases()
+                .map(|(_, release)| release.version().to_string().into())
+                .collect())
+        })
+    }
+
+    fn contains(&mut self, version: &str) -> ScriptResult<bool> {
+        self.with_graph(|graph| Ok(graph.find_by_version(version).is_some()))
+    }
+
+    fn payload(&mut self, version: &str) -> ScriptResult<String> {
+        self.with_graph(|graph| match release(graph, version)? {
+            Release::Concrete(release) => Ok(release.payload.clone()),
+            Release::Abstract(_) => Ok(String::new()),
+        })
+    }
+
+    fn metadata(&mut self, version: &str) -> ScriptResult<Map> {
+        self.with_graph(|graph| match release(graph, version)? {
+            Release::Concrete(release) => Ok(release
+                .metadata
+                .iter()
+                .map(|(key, value)| (key.into(), value.into()))
+                .collect()),
+            Release::Abstract(_) => Ok(Map::new()),
+        })
+    }
+
+    fn set_metadata(&mut self, version: &str, key: &str, value: &str) -> ScriptResult<()> {
+        self.with_graph(|graph| {
+            let id = release_id(graph, version)?;
+            graph
+                .get_metadata_as_ref_mut(&id)?
+                .insert(key.to_string(), value.to_string());
+            Ok(())
+        })
+    }
+
+    fn remove_metadata(&mut self, version: &str, key: &str) -> ScriptResult<()> {
+        self.with_graph(|graph| {
+            let id = release_id(graph, version)?;
+            graph.get_metadata_as_ref_mut(&id)?.remove(key);
+            Ok(())
+        })
+    }
+
+    fn remove_release(&mut self, version: &str) -> ScriptResult<()> {
+        self.with_graph(|graph| {
+            let id = release_id(graph, version)?;
+            graph.remove_releases(vec![id]);
+            Ok(())
+        })
+    }
+
+    fn edges(&mut self) -> ScriptResult<Array> {
+        self.with_graph(|graph| {
+            let mut edges = Vec::new();
+            for (id, from) in graph.iter_releases() {
+                for (_, _, to) in graph.next_releases(&id)? {
+                    let (from, to) = (from.version().to_string(), to.version().to_string());
+                    edges.push(Dynamic::from_array(vec![from.into(), to.into()]));
+                }
+            }
+            Ok(edges)
+        })
+    }
+
+    fn edge_metadata(&mut self, from: &str, to: &str) -> ScriptResult<Map> {
+        self.with_graph(|graph| {
+            let (from_id, to_id) = (release_id(graph, from)?, release_id(graph, to)?);
+            Ok(graph
+                .get_edge_metadata_as_ref(&from_id, &to_id)?
+                .iter()
+                .map(|(key, value)| (key.into(), value.into()))
+                .collect())
+        })
+    }
+
+    fn edge_risks(&mut self, from: &str, to: &str) -> ScriptResult<Array> {
+        self.with_graph(|graph| {
+            let (from_id, to_id) = (release_id(graph, from)?, release_id(graph, to)?);
+            Ok(graph
+                .get_edge_risks_as_ref(&from_id, &to_id)?
+                .iter()
+                .map(|risk| Dynamic::from_map(risk_map(risk)))
+                .collect())
+        })
+    }
+
+    fn next_releases(&mut self, version: &str) -> ScriptResult<Array> {
+        self.with_graph(|graph| {
+            let id = release_id(graph, version)?;
+            Ok(graph
+                .next_releases(&id)?
+                .map(|(_, _, release)| release.version().to_string().into())
+                .collect())
+        })
+    }
+
+    fn previous_releases(&mut self, version: &str) -> ScriptResult<Array> {
+        self.with_graph(|graph| {
+            let id = release_id(graph, version)?;
+            Ok(graph
+                .previous_releases(&id)?
+                .map(|(_, _, release)| release.version().to_string().into())
+                .collect())
+        })
+    }
+
+    fn add_edge(&mut self, from: &str, to: &str) -> ScriptResult<()> {
+        self.with_graph(|graph| {
+            let (from_id, to_id) = (release_id(graph, from)?, release_id(graph, to)?);
+            Ok(graph
+                .add_edge(&from_id, &to_id)
+                .context(format!("failed to add edge {} -> {}", from, to))?)
+        })
+    }
+
+    fn remove_edge(&mut self, from: &str, to: &str) -> ScriptResult<()> {
+        self.with_graph(|graph| {
+            let (from_id, to_id) = (release_id(graph, from)?, release_id(graph, to)?);
+            Ok(graph
+                .remove_edge(&from_id, &to_id)
+                .context(format!("failed to remove edge {} -> {}", from, to))?)
+        })
+    }
+}
+
+fn release_id(graph: &Graph, version: &str) -> Fallible<ReleaseId> {
+    graph
+        .find_by_version(version)
+        .ok_or_else(|| format_err!("unknown release {}", version))
+}
+
+fn release<'a>(graph: &'a Graph, version: &str) -> Fallible<&'a Release> {
+    graph.find_by_releaseid(&release_id(graph, version)?)
+}
+
+fn risk_map(risk: &Risk) -> Map {
+    let matching_rules: Array = risk
+        .matching_rules
+        .iter()
+        .map(|rule| {
+            let values: Array = rule.values.iter().map(|value| value.into()).collect();
+            let mut rule_map = Map::new();
+            rule_map.insert("parameter".into(), rule.parameter.clone().into());
+            rule_map.insert("values".into(), values.into());
+            Dynamic::from_map(rule_map)
+        })
+        .collect();
+
+    let mut map = Map::new();
+    map.insert("name".into(), risk.name.clone().into());
+    map.insert("url".into(), risk.url.clone().into());
+    map.insert("message".into(), risk.message.clone().into());
+    map.insert("matching_rules".into(), matching_rules.into());
+    map
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cincinnati::GraphBuilder;
+    use commons::testing::init_runtime;
+    use maplit::hashmap;
+    use std::collections::HashMap;
+
+    fn generate_graph() -> Graph {
+        GraphBuilder::new()
+            .release("1.0.0", "image/1.0.0", HashMap::new())
+            .release("2.0.0", "image/2.0.0", HashMap::new())
+            .release("3.0.0", "image/3.0.0", HashMap::new())
+            .edge("1.0.0", "2.0.0")
+            .edge("2.0.0", "3.0.0")
+            .edge("1.0.0", "3.0.0")
+            .build()
+            .expect("valid graph")
+    }
+
+    fn run_script_on(
+        graph: Graph,
+        script: &str,
+        parameters: HashMap<String, String>,
+    ) -> Fallible<InternalIO> {
+        let mut runtime = init_runtime()?;
+        let plugin = ScriptPlugin::try_new(
+            script,
+            DEFAULT_MAX_OPERATIONS,
+            Duration::from_secs(DEFAULT_TIMEOUT_SECS),
+        )?;
+
+        runtime.block_on(plugin.run_internal(InternalIO { graph, parameters }))
+    }
+
+    fn run_script(script: &str, parameters: HashMap<String, String>) -> Fallible<InternalIO> {
+        run_script_on(generate_graph(), script, parameters)
+    }
+
+    #[test]
+    fn script_modifies_graph() -> Fallible<()> {
+        let script = r#"
+            for version in graph.releases() {
+                if graph.payload(version) == "image/2.0.0" {
+                    graph.remove_release(version);
+                }
+            }
+            graph.set_metadata("1.0.0", "key", "value");
+            graph.set_metadata("3.0.0", "previous", graph.previous_releases("3.0.0")[0]);
+            if graph.contains("4.0.0") {
+                graph.add_edge("1.0.0", "4.0.0");
+            }
+        "#;
+
+        let output = run_script(script, HashMap::new())?;
+
+        let expected = GraphBuilder::new()
+            .release(
+                "1.0.0",
+                "image/1.0.0",
+                hashmap! { "key".to_string() => "value".to_string() },
+            )
+            .release(
+                "3.0.0",
+                "image/3.0.0",
+                hashmap! { "previous".to_string() => "1.0.0".to_string() },
+            )
+            .edge("1.0.0", "3.0.0")
+            .build()?;
+        assert_eq!(output.graph, expected);
+
+        Ok(())
+    }
+
+    #[test]
+    fn script_reads_graph() -> Fallible<()> {
+        let script = r#"
+            parameters.edges = graph.edges().len().to_string();
+            parameters.next = graph.next_releases("1.0.0").len().to_string();
+            parameters.metadata = graph.metadata("2.0.0").len().to_string();
+        "#;
+
+        let output = run_script(script, HashMap::new())?;
+        assert_eq!(output.graph, generate_graph());
+        assert_eq!(
+            output.parameters,
+            hashmap! {
+                "edges".to_string() => "3".to_string(),
+                "next".to_string() => "2".to_string(),
+                "metadata".to_string() => "0".to_string(),
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn script_reads_edges() -> Fallible<()> {
+        let graph = || -> Fallible<Graph> {
+            let mut graph = generate_graph();
+            let (v1, v2) = (
+                graph.find_by_version("1.0.0").expect("1.0.0 to exist"),
+                graph.find_by_version("2.0.0").expect("2.0.0 to exist"),
+            );
+            graph
+                .get_edge_metadata_as_ref_mut(&v1, &v2)?
+                .insert("key".to_string(), "value".to_string());
+            graph.get_edge_risks_as_ref_mut(&v1, &v2)?.push(Risk {
+                name: "Risk".to_string(),
+                url: "https://example.com/risk".to_string(),
+                message: "A risk.".to_string(),
+                matching_rules: vec![cincinnati::MatchingRule {
+                    parameter: "arch".to_string(),
+                    values: vec!["amd64".to_string()],
+                }],
+            });
+            Ok(graph)
+        };
+
+        let script = r#"
+            parameters.metadata = graph.edge_metadata("1.0.0", "2.0.0").key;
+            let risk = graph.edge_risks("1.0.0", "2.0.0")[0];
+            parameters.risk = risk.name;
+            parameters.rule = risk.matching_rules[0].parameter + "=" + risk.matching_rules[0].values[0];
+            parameters.risks = graph.edge_risks("1.0.0", "3.0.0").len().to_string();
+        "#;
+
+        let output = run_script_on(graph()?, script, HashMap::new())?;
+        assert_eq!(output.graph, graph()?);
+        assert_eq!(
+            output.parameters,
+            hashmap! {
+                "metadata".to_string() => "value".to_string(),
+                "risk".to_string() => "Risk".to_string(),
+                "rule".to_string() => "arch=amd64".to_string(),
+                "risks".to_string() => "0".to_string(),
+            }
+        );
+
+        assert!(run_script(r#"graph.edge_risks("2.0.0", "1.0.0");"#, HashMap::new()).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn script_modifies_parameters() -> Fallible<()> {
+        let script = r#"
+            if parameters.channel == "stable" {
+                parameters.channel = "fast";
+            }
+            parameters.remove("arch");
+        "#;
+
+        let output = run_script(
+            script,
+            hashmap! {
+                "channel".to_string() => "stable".to_string(),
+                "arch".to_string() => "amd64".to_string(),
+            },
+        )?;
+        assert_eq!(
+            output.parameters,
+            hashmap! { "channel".to_string() => "fast".to_string() }
+        );
+
+        assert!(run_script("parameters.channel = 1;", HashMap::new()).is_err());
+        assert!(run_script("parameters = 1;", HashMap::new()).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn script_errors() {
+        for (name, script) in vec![
+            ("unknown release", r#"graph.remove_release("4.0.0");"#),
+            ("unknown edge", r#"graph.remove_edge("2.0.0", "1.0.0");"#),
+            ("cycle", r#"graph.add_edge("3.0.0", "1.0.0");"#),
+            ("thrown error", r#"throw "failed on purpose";"#),
+        ] {
+            assert!(run_script(script, HashMap::new()).is_err(), "{}", name);
+        }
+
+        assert!(ScriptPlugin::try_new(
+            "let x = ;",
+            DEFAULT_MAX_OPERATIONS,
+            Duration::from_secs(DEFAULT_TIMEOUT_SECS)
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn script_limits() {
+        for (script, expected) in vec![
+            ("loop {}", "Too many operations"),
+            ("fn f(x) { f(x) } f(1);", "Stack overflow"),
+            (
+                r#"let s = "x"; loop { s += s; }"#,
+                "Length of string too large",
+            ),
+        ] {
+            let err = run_script(script, HashMap::new()).unwrap_err();
+            assert!(err.to_string().contains(expected), "{}", err);
+        }
+    }
+
+    #[test]
+    fn script_timeout() -> Fallible<()> {
+        let mut runtime = init_runtime()?;
+        // Zero means an unlimited number of operations.
+        let plugin = ScriptPlugin::try_new("loop {}", 0, Duration::from_millis(200))?;
+
+        let start = Instant::now();
+        let err = runtime
+            .block_on(plugin.run_internal(InternalIO {
+                graph: generate_graph(),
+                parameters: HashMap::new(),
+            }))
+            .unwrap_err();
+        assert!(err.to_string().contains("timed out"), "{}", err);
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        Ok(())
+    }
+
+    #[test]
+    fn deserialize_settings() -> Fallible<()> {
+        for (cfg, valid) in vec![
+            ("name = 'script'\nscript = 'print(1);'", true),
+            ("name = 'script'\nscript_path = 'policy.rhai'", true),
+            ("name = 'script'", false),
+            (
+                "name = 'script'\nscript = 'print(1);'\nscript_path = 'policy.rhai'",
+                false,
+            ),
+            (
+                "name = 'script'\nscript = 'print(1);'\nmax_operations = 0",
+                false,
+            ),
+            (
+                "name = 'script'\nscript = 'print(1);'\ntimeout_secs = 0",
+                false,
+            ),
+            ("name = 'node-remove'", true),
+            ("name = 'unknown'", false),
+        ] {
+            let cfg: toml::Value = toml::from_str(cfg)?;
+            assert_eq!(deserialize_config(cfg).is_ok(), valid);
+        }
+
+        Ok(())
+    }
+}
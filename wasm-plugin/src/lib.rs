@@ -0,0 +1,432 @@
+//! External Cincinnati plugin which runs a WebAssembly module
+//!
+//! The module is run in a sandbox without any imports, and every call is
+//! limited in the fuel it may consume and the memory it may allocate.
+//!
+//! The module must export:
+//!  * `memory`: its linear memory.
+//!  * `alloc(len: i32) -> i32`: allocates `len` bytes and returns their offset.
+//!  * `run(ptr: i32, len: i32) -> i64`: processes the serialized
+//!    `PluginExchange` at the given offset. It returns the offset of the output
+//!    in the upper and its length in the lower 32 bits.
+//!
+//! The first byte of the output is a status, followed by the serialized
+//! resulting `PluginExchange` (`STATUS_EXCHANGE`) or a `PluginError`
+//! (`STATUS_ERROR`).
+//!
+//! The plugin isn't part of the *cincinnati* plugin catalog, as its
+//! dependencies need a newer Rust toolchain than the rest of the workspace.
+//! Services using it look up their configuration with `deserialize_config`
+//! from this crate, which knows about all the catalog's plugins as well.
+
+#[macro_use]
+extern crate cincinnati;
+#[macro_use]
+extern crate custom_debug_derive;
+#[macro_use]
+extern crate failure;
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate smart_default;
+
+use cincinnati::plugins::interface::{PluginError, PluginExchange};
+use cincinnati::plugins::{
+    AsyncIO, BoxedPlugin, ExternalIO, ExternalPlugin, ExternalPluginWrapper, PluginResult,
+    PluginSettings,
+};
+use failure::{Fallible, ResultExt};
+use futures::sync::oneshot;
+use futures::Future;
+use prometheus::Registry;
+use std::convert::{TryFrom, TryInto};
+use std::path::{Path, PathBuf};
+use std::thread;
+use wasmtime::{
+    Config, Engine, Instance, Module, Store, StoreLimits, StoreLimitsBuilder, TypedFunc,
+};
+
+/// Default fuel available to a single call.
+pub const DEFAULT_FUEL: u64 = 1_000_000_000;
+
+/// Default limit for the memory of a single call, in bytes.
+pub const DEFAULT_MAX_MEMORY_BYTES: usize = 64 * 1024 * 1024;
+
+/// Name of the exported memory.
+pub const EXPORT_MEMORY: &str = "memory";
+
+/// Name of the exported allocation function.
+pub const EXPORT_ALLOC: &str = "alloc";
+
+/// Name of the exported entry point.
+pub const EXPORT_RUN: &str = "run";
+
+/// Output status of a successful call.
+pub const STATUS_EXCHANGE: u8 = 0;
+
+/// Output status of a call which returned a `PluginError`.
+pub const STATUS_ERROR: u8 = 1;
+
+/// Validate configuration for this or any plugin of the *cincinnati* catalog.
+pub fn deserialize_config(cfg: toml::Value) -> Fallible<Box<dyn PluginSettings>> {
+    match cfg.get("name").and_then(toml::Value::as_str) {
+        Some(WasmPlugin::PLUGIN_NAME) => WasmPlugin::deserialize_config(cfg),
+        _ => cincinnati::plugins::deserialize_config(cfg),
+    }
+}
+
+/// Plugin settings.
+#[derive(Clone, Debug, Deserialize, SmartDefault)]
+#[serde(default)]
+struct WasmPluginSettings {
+    module: PathBuf,
+
+    #[default(DEFAULT_FUEL)]
+    fuel: u64,
+
+    #[default(DEFAULT_MAX_MEMORY_BYTES)]
+    max_memory_bytes: usize,
+}
+
+impl PluginSettings for WasmPluginSettings {
+    fn build_plugin(&self, _: Option<&Registry>) -> Fallible<BoxedPlugin> {
+        let plugin = WasmPlugin::try_new(&self.module, self.fuel, self.max_memory_bytes)?;
+        Ok(new_plugin!(ExternalPluginWrapper(plugin)))
+    }
+}
+
+/// Plugin which runs a WebAssembly module for every request.
+#[derive(CustomDebug)]
+pub struct WasmPlugin {
+    /// Fuel available to a single call
+    pub fuel: u64,
+
+    /// Limit for the memory of a single call, in bytes
+    pub max_memory_bytes: usize,
+
+    #[debug(skip)]
+    engine: Engine,
+
+    #[debug(skip)]
+    module: Module,
+}
+
+impl WasmPlugin {
+    /// Plugin name, for configuration.
+    pub const PLUGIN_NAME: &'static str = "wasm";
+
+    /// Validate plugin configuration and fill in defaults.
+    pub fn deserialize_config(cfg: toml::Value) -> Fallible<Box<dyn PluginSettings>> {
+        let settings: WasmPluginSettings = cfg.try_into()?;
+
+        ensure!(!settings.module.as_os_str().is_empty(), "empty module");
+        ensure!(settings.fuel > 0, "zero fuel");
+        ensure!(settings.max_memory_bytes > 0, "zero max_memory_bytes");
+
+        Ok(Box::new(settings))
+    }
+
+    /// Loads the module at the given path.
+    pub fn try_new<P>(module: P, fuel: u64, max_memory_bytes: usize) -> Fallible<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let bytes = std::fs::read(module.as_ref())
+            .context(format!("failed to read {}", module.as_ref().display()))?;
+        Ok(Self::from_bytes(&bytes, fuel, max_memory_bytes)
+            .context(format!("failed to load {}", module.as_ref().display()))?)
+    }
+
+    /// Compiles the module from its binary or text representation.
+    pub fn from_bytes(bytes: &[u8], fuel: u64, max_memory_bytes: usize) -> Fallible<Self> {
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config).map_err(|e| format_err!("{:#}", e))?;
+        let module = Module::new(&engine, bytes).map_err(|e| format_err!("{:#}", e))?;
+
+        Ok(Self {
+            fuel,
+            max_memory_bytes,
+            engine,
+            module,
+        })
+    }
+}
+
+impl ExternalPlugin for WasmPlugin {
+    fn run_external(self: &Self, io: ExternalIO) -> AsyncIO<ExternalIO> {
+        let (engine, module) = (self.engine.clone(), self.module.clone());
+        let (fuel, max_memory_bytes) = (self.fuel, self.max_memory_bytes);
+
+        // The module is run on a separate thread to not block the executor.
+        let (tx, rx) = oneshot::channel();
+        thread::spawn(move || {
+            let output = call(&engine, &module, fuel, max_memory_bytes, &io.bytes)
+                .map_err(|e| format_err!("wasm plugin failed: {:#}", e))
+                .and_then(|output| into_result(&output));
+            let _ = tx.send(output);
+        });
+
+        Box::new(rx.then(|result| match result {
+            Ok(result) => result,
+            Err(_) => Err(format_err!("wasm plugin terminated unexpectedly")),
+        }))
+    }
+}
+
+/// Calls the module on the given input and returns its raw output.
+fn call(
+    engine: &Engine,
+    module: &Module,
+    fuel: u64,
+    max_memory_bytes: usize,
+    input: &[u8],
+) -> wasmtime::Result<Vec<u8>> {
+    let limits = StoreLimitsBuilder::new()
+        .memory_size(max_memory_bytes)
+        .build();
+    let mut store: Store<StoreLimits> = Store::new(engine, limits);
+    store.limiter(|limits| limits);
+    store.add_fuel(fuel)?;
+
+    let instance = Instance::new(&mut store, module, &[])?;
+    let memory = instance
+        .get_memory(&mut store, EXPORT_MEMORY)
+        .ok_or_else(|| wasmtime::Error::msg(format!("missing export '{}'", EXPORT_MEMORY)))?;
+    let alloc: TypedFunc<i32, i32> = instance.get_typed_func(&mut store, EXPORT_ALLOC)?;
+    let run: TypedFunc<(i32, i32), i64> = instance.get_typed_func(&mut store, EXPORT_RUN)?;
+
+    let len = i32::try_from(input.len())?;
+    let ptr = alloc.call(&mut store, len)?;
+    memory.write(&mut store, ptr as u32 as usize, input)?;
+
+    let packed = run.call(&mut store, (ptr, len))? as u64;
+    let (ptr, len) = ((packed >> 32) as usize, (packed & 0xffff_ffff) as usize);
+
+    // The output is only copied once it is known to lie within the memory.
+    let size = memory.data_size(&store);
+    let end = ptr
+        .checked_add(len)
+        .filter(|end| *end <= size)
+        .ok_or_else(|| {
+            wasmtime::Error::msg(format!(
+                "output of {} bytes at offset {} exceeds the memory of {} bytes",
+                len, ptr, size
+            ))
+        })?;
+
+    Ok(memory.data(&store)[ptr..end].to_vec())
+}
+
+/// Converts the raw output of the module to the result of the plugin.
+fn into_result(output: &[u8]) -> Fallible<ExternalIO> {
+    let (status, bytes) = output
+        .split_first()
+        .ok_or_else(|| format_err!("wasm plugin returned empty output"))?;
+    let output = ExternalIO {
+        bytes: bytes.to_vec(),
+    };
+
+    let result = match *status {
+        STATUS_EXCHANGE => PluginResult::PluginExchange(PluginExchange::try_from(output)?),
+        STATUS_ERROR => PluginResult::PluginError(PluginError::try_from(output)?),
+        status => bail!("wasm plugin returned unknown status {}", status),
+    };
+
+    match result {
+        PluginResult::PluginExchange(exchange) => exchange.try_into(),
+        PluginResult::PluginError(error) => error.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cincinnati::plugins::{interface, InternalIO};
+    use cincinnati::{Graph, GraphBuilder};
+    use commons::testing::init_runtime;
+
+    /// Bump allocator which grows the memory as needed.
+    static ALLOC: &str = r#"
+        (memory (export "memory") 1)
+        (global $next (mut i32) (i32.const 1024))
+        (func $alloc (export "alloc") (param $len i32) (result i32)
+            (local $ptr i32)
+            (local $end i32)
+            (local.set $ptr (global.get $next))
+            (local.set $end (i32.add (local.get $ptr) (local.get $len)))
+            (block $done
+                (loop $grow
+                    (br_if $done
+                        (i32.le_u (local.get $end) (i32.mul (memory.size) (i32.const 65536))))
+                    (if (i32.eq (memory.grow (i32.const 1)) (i32.const -1))
+                        (then unreachable))
+                    (br $grow)))
+            (global.set $next (local.get $end))
+            (local.get $ptr))
+    "#;
+
+    /// Module which returns its input.
+    static PASSTHROUGH: &str = r#"
+        (func (export "run") (param $ptr i32) (param $len i32) (result i64)
+            (local $out i32)
+            (local.set $out (call $alloc (i32.add (local.get $len) (i32.const 1))))
+            (i32.store8 (local.get $out) (i32.const 0))
+            (memory.copy
+                (i32.add (local.get $out) (i32.const 1))
+                (local.get $ptr)
+                (local.get $len))
+            (i64.or
+                (i64.shl (i64.extend_i32_u (local.get $out)) (i64.const 32))
+                (i64.extend_i32_u (i32.add (local.get $len) (i32.const 1)))))
+    "#;
+
+    /// Module which returns an INVALID_PARAM error with the value "wasm".
+    static ERROR: &str = r#"
+        (data (i32.const 0) "\01\08\02\12\04wasm")
+        (func (export "run") (param i32 i32) (result i64)
+            (i64.const 9))
+    "#;
+
+    /// Module which never returns.
+    static LOOP: &str = r#"
+        (func (export "run") (param i32 i32) (result i64)
+            (loop $forever (br $forever))
+            (i64.const 0))
+    "#;
+
+    /// Modules which return outputs beyond the end of their memory.
+    static OUT_OF_RANGE: &[&str] = &[
+        r#"(func (export "run") (param i32 i32) (result i64) (i64.const 0xffffffff))"#,
+        r#"(func (export "run") (param i32 i32) (result i64) (i64.const 0xffffffff00000010))"#,
+    ];
+
+    fn generate_graph() -> Graph {
+        GraphBuilder::new()
+            .release("1.0.0", "image/1.0.0", Default::default())
+            .release("2.0.0", "image/2.0.0", Default::default())
+            .release("3.0.0", "image/3.0.0", Default::default())
+            .edge("1.0.0", "2.0.0")
+            .edge("2.0.0", "3.0.0")
+            .edge("1.0.0", "3.0.0")
+            .build()
+            .expect("valid graph")
+    }
+
+    fn test_plugin(run: &str, fuel: u64, max_memory_bytes: usize) -> Fallible<WasmPlugin> {
+        let wat = format!("(module {} {})", ALLOC, run);
+        WasmPlugin::from_bytes(wat.as_bytes(), fuel, max_memory_bytes)
+    }
+
+    fn test_input() -> InternalIO {
+        InternalIO {
+            graph: generate_graph(),
+            parameters: [("key".to_string(), "value".to_string())]
+                .iter()
+                .cloned()
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn deserialize_wasm_config() {
+        let wasm: toml::Value = toml::from_str("name = 'wasm'\nmodule = 'policy.wasm'").unwrap();
+        deserialize_config(wasm).unwrap();
+
+        let wasm_no_fuel: toml::Value =
+            toml::from_str("name = 'wasm'\nmodule = 'policy.wasm'\nfuel = 0").unwrap();
+        deserialize_config(wasm_no_fuel).unwrap_err();
+
+        let node_remove: toml::Value = toml::from_str("name = 'node-remove'").unwrap();
+        deserialize_config(node_remove).unwrap();
+
+        let unknown: toml::Value = toml::from_str("name = 'unknown'").unwrap();
+        deserialize_config(unknown).unwrap_err();
+    }
+
+    #[test]
+    fn wasm_plugin_success() -> Fallible<()> {
+        let mut runtime = init_runtime()?;
+        let plugin = test_plugin(PASSTHROUGH, DEFAULT_FUEL, DEFAULT_MAX_MEMORY_BYTES)?;
+
+        let output = runtime.block_on(plugin.run_external(test_input().try_into()?))?;
+        assert_eq!(InternalIO::try_from(output)?, test_input());
+
+        Ok(())
+    }
+
+    #[test]
+    fn wasm_plugin_error() -> Fallible<()> {
+        let mut runtime = init_runtime()?;
+        let plugin = test_plugin(ERROR, DEFAULT_FUEL, DEFAULT_MAX_MEMORY_BYTES)?;
+
+        let output = runtime.block_on(plugin.run_external(test_input().try_into()?));
+        match PluginResult::try_from(output)? {
+            PluginResult::PluginError(error) => {
+                assert_eq!(error.get_kind(), interface::PluginError_Kind::INVALID_PARAM);
+                assert_eq!(error.get_value(), "wasm");
+            }
+            result => bail!("expected a plugin error, got {:?}", result),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn wasm_plugin_limits() -> Fallible<()> {
+        let mut runtime = init_runtime()?;
+
+        let plugin = test_plugin(LOOP, 100_000, DEFAULT_MAX_MEMORY_BYTES)?;
+        let err = runtime
+            .block_on(plugin.run_external(test_input().try_into()?))
+            .unwrap_err();
+        assert!(format!("{}", err).contains("fuel"), "{}", err);
+
+        // The input doesn't fit into a single page, so the allocator fails to grow the memory.
+        let plugin = test_plugin(PASSTHROUGH, DEFAULT_FUEL, 64 * 1024)?;
+        let mut input = test_input();
+        input
+            .parameters
+            .insert("large".to_string(), "x".repeat(128 * 1024));
+        let err = runtime
+            .block_on(plugin.run_external(input.try_into()?))
+            .unwrap_err();
+        assert!(format!("{}", err).contains("unreachable"), "{}", err);
+
+        Ok(())
+    }
+
+    #[test]
+    fn wasm_plugin_output_out_of_range() -> Fallible<()> {
+        let mut runtime = init_runtime()?;
+
+        for run in OUT_OF_RANGE {
+            let plugin = test_plugin(run, DEFAULT_FUEL, DEFAULT_MAX_MEMORY_BYTES)?;
+            let err = runtime
+                .block_on(plugin.run_external(test_input().try_into()?))
+                .unwrap_err();
+            assert!(format!("{}", err).contains("exceeds the memory"), "{}", err);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn wasm_plugin_invalid_module() -> Fallible<()> {
+        let mut runtime = init_runtime()?;
+
+        assert!(
+            WasmPlugin::from_bytes(b"not a module", DEFAULT_FUEL, DEFAULT_MAX_MEMORY_BYTES)
+                .is_err()
+        );
+        assert!(WasmPlugin::try_new("/nonexistent/plugin.wasm", DEFAULT_FUEL, 1).is_err());
+
+        // The module doesn't export `run`.
+        let plugin = test_plugin("", DEFAULT_FUEL, DEFAULT_MAX_MEMORY_BYTES)?;
+        assert!(runtime
+            .block_on(plugin.run_external(test_input().try_into()?))
+            .is_err());
+
+        Ok(())
+    }
+}